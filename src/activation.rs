@@ -0,0 +1,191 @@
+//! Configuring processes to run with a profile's environment.
+
+use std::ffi::OsStr;
+use std::process::Command;
+use std::time::SystemTime;
+
+use crate::config::KRAVEN_ACTIVE;
+use crate::profile::Profile;
+use crate::prompt::KRAVEN_STACK;
+use crate::session::{format_deadline, KRAVEN_EXPIRES_AT, KRAVEN_PID};
+
+/// The environment a profile gives to the processes it is used with.
+///
+/// By default only the profile's variables are added to the inherited
+/// environment, as `kraven exec` does. [`session`](Self::session) and
+/// [`expires_at`](Self::expires_at) also mark the process as a kraven session,
+/// as `kraven activate` does for its shell.
+///
+/// ```no_run
+/// # fn main() -> kraven::Result<()> {
+/// let resolved = kraven::resolve_profile("dev")?;
+/// let profile = kraven::Profile::load(&resolved.name, &resolved.path)?;
+/// let status = kraven::Activation::new(&resolved.name, &profile)
+///     .command("cargo")
+///     .arg("test")
+///     .status();
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Activation<'a> {
+    name: &'a str,
+    profile: &'a Profile,
+    allowlist: Option<&'a [String]>,
+    stack: Option<&'a [String]>,
+    expires_at: Option<SystemTime>,
+}
+
+impl<'a> Activation<'a> {
+    /// An activation of `profile`, known to the user as `name`.
+    #[must_use]
+    pub fn new(name: &'a str, profile: &'a Profile) -> Self {
+        Self {
+            name,
+            profile,
+            allowlist: None,
+            stack: None,
+            expires_at: None,
+        }
+    }
+
+    /// Starts from an empty environment, keeping only the variables in `allowlist`.
+    #[must_use]
+    pub fn isolate(mut self, allowlist: &'a [String]) -> Self {
+        self.allowlist = Some(allowlist);
+        self
+    }
+
+    /// Marks the process as a kraven session nested in the sessions of `stack`,
+    /// outermost first. The session is tied to the current process, which should
+    /// live as long as it.
+    ///
+    /// This sets `KRAVEN_ACTIVE` to the profile name, `KRAVEN_STACK` to the
+    /// enclosing sessions, and `KRAVEN_PID` to the id of the current process,
+    /// which `kraven doctor` uses to tell whether the session has ended.
+    #[must_use]
+    pub fn session(mut self, stack: &'a [String]) -> Self {
+        self.stack = Some(stack);
+        self
+    }
+
+    /// Records when the session expires.
+    #[must_use]
+    pub fn expires_at(mut self, deadline: SystemTime) -> Self {
+        self.expires_at = Some(deadline);
+        self
+    }
+
+    /// Applies the environment to `cmd`.
+    pub fn apply(&self, cmd: &mut Command) {
+        // Keep only the allowlisted variables from the parent environment
+        if let Some(allowlist) = self.allowlist {
+            cmd.env_clear();
+            for key in allowlist {
+                if let Some(value) = std::env::var_os(key) {
+                    cmd.env(key, value);
+                }
+            }
+        }
+
+        cmd.envs(&self.profile.vars);
+
+        if let Some(stack) = self.stack {
+            cmd.env(KRAVEN_ACTIVE, self.name);
+            cmd.env(KRAVEN_PID, std::process::id().to_string());
+            if stack.is_empty() {
+                cmd.env_remove(KRAVEN_STACK);
+            } else {
+                cmd.env(KRAVEN_STACK, stack.join(" "));
+            }
+        }
+        if let Some(deadline) = self.expires_at {
+            cmd.env(KRAVEN_EXPIRES_AT, format_deadline(deadline));
+        }
+    }
+
+    /// A command running `program` with the environment applied.
+    #[must_use]
+    pub fn command(&self, program: impl AsRef<OsStr>) -> Command {
+        let mut cmd = Command::new(program);
+        self.apply(&mut cmd);
+        cmd
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    fn profile() -> Profile {
+        Profile::parse("REGION=eu\nTOKEN=abc\n", Path::new("dev")).unwrap()
+    }
+
+    /// The variables set (`Some`) or removed (`None`) by the command.
+    fn env(cmd: &Command) -> Vec<(String, Option<String>)> {
+        let mut env: Vec<_> = cmd
+            .get_envs()
+            .map(|(key, value)| {
+                (
+                    key.to_string_lossy().into_owned(),
+                    value.map(|value| value.to_string_lossy().into_owned()),
+                )
+            })
+            .collect();
+        env.sort();
+        env
+    }
+
+    fn set(key: &str, value: &str) -> (String, Option<String>) {
+        (key.to_string(), Some(value.to_string()))
+    }
+
+    #[test]
+    fn test_profile_vars_only() {
+        let profile = profile();
+        let cmd = Activation::new("dev", &profile).command("true");
+        assert_eq!(env(&cmd), [set("REGION", "eu"), set("TOKEN", "abc")]);
+    }
+
+    #[test]
+    fn test_session() {
+        let profile = profile();
+        let cmd = Activation::new("dev", &profile)
+            .session(&[])
+            .command("true");
+        assert!(env(&cmd).contains(&set(KRAVEN_ACTIVE, "dev")));
+        assert!(env(&cmd).contains(&(KRAVEN_STACK.to_string(), None)));
+        assert!(env(&cmd).contains(&set(KRAVEN_PID, &std::process::id().to_string())));
+
+        let stack = ["base".to_string(), "work".to_string()];
+        let deadline = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let cmd = Activation::new("dev", &profile)
+            .session(&stack)
+            .expires_at(deadline)
+            .command("true");
+        assert!(env(&cmd).contains(&set(KRAVEN_STACK, "base work")));
+        assert!(env(&cmd).contains(&set(KRAVEN_EXPIRES_AT, "1700000000")));
+    }
+
+    #[test]
+    fn test_isolate() {
+        let profile = profile();
+        let allowlist = ["KRAVEN_TEST_SURELY_UNSET".to_string()];
+        let cmd = Activation::new("dev", &profile)
+            .isolate(&allowlist)
+            .command("true");
+        // Cleared, so only the profile remains
+        assert_eq!(env(&cmd), [set("REGION", "eu"), set("TOKEN", "abc")]);
+        let output = Activation::new("dev", &profile)
+            .isolate(&allowlist)
+            .command("env")
+            .output()
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(output.stdout).unwrap(),
+            "REGION=eu\nTOKEN=abc\n"
+        );
+    }
+}
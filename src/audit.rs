@@ -0,0 +1,493 @@
+//! Audit log of profile activations and changes.
+//!
+//! Records are appended as JSON lines to a log that is rotated once it grows
+//! past [`MAX_LOG_SIZE`]. Records identify profiles by name and by a hash of
+//! their content, and never contain variable values.
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::config::get_audit_log_path;
+use crate::digest::hash;
+use crate::duration::parse_duration;
+
+/// Size after which the log is rotated.
+const MAX_LOG_SIZE: u64 = 1024 * 1024;
+
+/// Number of rotated logs kept next to the current one (`audit.log.1` is the newest).
+const ROTATED_LOGS: usize = 3;
+
+const SECONDS_PER_DAY: u64 = 86_400;
+
+/// What happened to a profile.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Event {
+    Activate,
+    Exec,
+    Edit,
+    Remove,
+    Rename,
+    Copy,
+    Create,
+    Import,
+    ReplaceValue,
+}
+
+impl Event {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Activate => "activate",
+            Self::Exec => "exec",
+            Self::Edit => "edit",
+            Self::Remove => "remove",
+            Self::Rename => "rename",
+            Self::Copy => "copy",
+            Self::Create => "create",
+            Self::Import => "import",
+            Self::ReplaceValue => "replace-value",
+        }
+    }
+}
+
+/// One line of the audit log.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Record {
+    pub event: Event,
+    /// The profile reference: the name of a global profile, `project:<name>`
+    /// for a project profile.
+    pub profile: String,
+    /// Where the profile came from: the original of a rename or copy, a template or a bundle.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from: Option<String>,
+    /// SHA-256 of the profile file (`sha256:<hex>`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hash: Option<String>,
+    pub user: String,
+    pub host: String,
+    pub cwd: String,
+    pub pid: u32,
+    /// Seconds since the Unix epoch.
+    pub start: u64,
+    /// Set once a session or command has finished.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end: Option<u64>,
+    /// Exit status of the session or command, if it exited normally.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<i32>,
+}
+
+impl Record {
+    /// A record starting now, for the profile `profile` (see
+    /// [`ResolvedProfile::reference`](crate::config::ResolvedProfile::reference))
+    /// stored at `path`.
+    #[must_use]
+    pub fn new(event: Event, profile: &str, path: &Path) -> Self {
+        Self {
+            event,
+            profile: profile.to_string(),
+            from: None,
+            hash: fs::read(path).ok().map(|content| hash(&content)),
+            user: username(),
+            host: hostname(),
+            cwd: std::env::current_dir()
+                .map(|dir| dir.display().to_string())
+                .unwrap_or_default(),
+            pid: std::process::id(),
+            start: now(),
+            end: None,
+            status: None,
+        }
+    }
+
+    /// Marks the record as finished now, with the given exit status.
+    pub fn finish(&mut self, status: Option<i32>) {
+        self.end = Some(now());
+        self.status = status;
+    }
+
+    /// Whether `other` is the same session (a finished copy of an open record).
+    fn same_session(&self, other: &Self) -> bool {
+        self.event == other.event
+            && self.pid == other.pid
+            && self.host == other.host
+            && self.start == other.start
+    }
+}
+
+/// Appends a record to the audit log.
+///
+/// Failing to write the log is reported but never stops the command itself.
+pub fn log(record: &Record) {
+    let result = get_audit_log_path().and_then(|path| append(&path, record));
+    if let Err(err) = result {
+        eprintln!("kraven: failed to write audit log: {err:#}");
+    }
+}
+
+fn append(path: &Path, record: &Record) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create directory: {}", parent.display()))?;
+    }
+
+    if fs::metadata(path).is_ok_and(|meta| meta.len() >= MAX_LOG_SIZE) {
+        rotate(path).with_context(|| format!("Failed to rotate {}", path.display()))?;
+    }
+
+    let mut line = serde_json::to_string(record)?;
+    line.push('\n');
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut file| file.write_all(line.as_bytes()))
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+fn rotated_path(path: &Path, index: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{index}"));
+    PathBuf::from(name)
+}
+
+fn rotate(path: &Path) -> std::io::Result<()> {
+    for index in (1..ROTATED_LOGS).rev() {
+        let from = rotated_path(path, index);
+        if from.exists() {
+            fs::rename(&from, rotated_path(path, index + 1))?;
+        }
+    }
+    fs::rename(path, rotated_path(path, 1))
+}
+
+/// Reads all records, oldest first, including rotated logs.
+///
+/// Open session records are dropped when a finished copy of them follows.
+pub fn read(path: &Path) -> Result<Vec<Record>> {
+    let mut files: Vec<PathBuf> = (1..=ROTATED_LOGS)
+        .rev()
+        .map(|index| rotated_path(path, index))
+        .collect();
+    files.push(path.to_path_buf());
+
+    let mut records: Vec<Record> = Vec::new();
+    for file in files.iter().filter(|file| file.exists()) {
+        let content = fs::read_to_string(file)
+            .with_context(|| format!("Failed to read {}", file.display()))?;
+        for (line_num, line) in content.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str::<Record>(line) {
+                Ok(record) => {
+                    if record.end.is_some() {
+                        records.retain(|open| open.end.is_some() || !open.same_session(&record));
+                    }
+                    records.push(record);
+                }
+                Err(err) => eprintln!(
+                    "kraven: skipping malformed audit record at {}:{}: {err}",
+                    file.display(),
+                    line_num + 1
+                ),
+            }
+        }
+    }
+
+    Ok(records)
+}
+
+/// Returns the records of `profile`, including those from before it was renamed.
+#[must_use]
+pub fn history<'a>(records: &'a [Record], profile: &str) -> Vec<&'a Record> {
+    let mut names = vec![profile.to_string()];
+    let mut matching = Vec::new();
+
+    // Walk backwards so an old name only matches records from before the rename
+    for record in records.iter().rev() {
+        if !names.contains(&record.profile) {
+            continue;
+        }
+        matching.push(record);
+        if let (Event::Rename, Some(from)) = (record.event, &record.from) {
+            names.push(from.clone());
+        }
+    }
+
+    matching.reverse();
+    matching
+}
+
+/// Parses `--since` as a duration ago (`2h`, `7d`) or a UTC date (`2024-05-01`).
+pub fn parse_since(input: &str, now: u64) -> Result<u64> {
+    if let Ok(duration) = parse_duration(input) {
+        return Ok(now.saturating_sub(duration.as_secs()));
+    }
+
+    let parts: Vec<&str> = input.trim().split('-').collect();
+    let [year, month, day] = parts[..] else {
+        bail!(
+            "Invalid time '{input}': expected a duration such as 2h or a date such as 2024-05-01"
+        );
+    };
+    let (Ok(year), Ok(month), Ok(day)) = (year.parse(), month.parse(), day.parse()) else {
+        bail!("Invalid date '{input}': expected YYYY-MM-DD");
+    };
+    if year < 1970 || !(1..=12).contains(&month) || !(1..=days_in_month(year, month)).contains(&day)
+    {
+        bail!("Invalid date '{input}': expected YYYY-MM-DD");
+    }
+
+    Ok(days_from_civil(year, month, day) * SECONDS_PER_DAY)
+}
+
+/// The number of days of `month` (1-12) in `year`.
+fn days_in_month(year: u64, month: u64) -> u64 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Formats a timestamp as `YYYY-MM-DD HH:MM:SS` in UTC.
+#[must_use]
+pub fn format_timestamp(seconds: u64) -> String {
+    let (year, month, day) = civil_from_days(seconds / SECONDS_PER_DAY);
+    let time = seconds % SECONDS_PER_DAY;
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02}:{:02}",
+        time / 3_600,
+        time % 3_600 / 60,
+        time % 60
+    )
+}
+
+/// Converts days since the Unix epoch to a (year, month, day) date.
+fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    // Howard Hinnant's algorithm, restricted to dates after the epoch
+    let days = days + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+    (year, month, day)
+}
+
+/// Converts a date to days since the Unix epoch (the inverse of [`civil_from_days`]).
+fn days_from_civil(year: u64, month: u64, day: u64) -> u64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year / 400;
+    let year_of_era = year % 400;
+    let month_index = if month > 2 { month - 3 } else { month + 9 };
+    let day_of_year = (153 * month_index + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Formats a record for `kraven log`.
+#[must_use]
+pub fn format_record(record: &Record) -> String {
+    let outcome = match (record.end, record.status) {
+        (None, _) if matches!(record.event, Event::Activate | Event::Exec) => {
+            "no end recorded".to_string()
+        }
+        (None, _) => String::new(),
+        (Some(end), status) => {
+            let elapsed = crate::duration::format_duration(std::time::Duration::from_secs(
+                end.saturating_sub(record.start),
+            ));
+            match status {
+                Some(code) => format!("exit {code} after {elapsed}"),
+                None => format!("killed after {elapsed}"),
+            }
+        }
+    };
+
+    let outcome = match &record.from {
+        Some(from) => format!("from {from}  {outcome}"),
+        None => outcome,
+    };
+
+    format!(
+        "{}  {:<8}  {}  {}@{}  {outcome}",
+        format_timestamp(record.start),
+        record.event.name(),
+        record.profile,
+        record.user,
+        record.host
+    )
+    .trim_end()
+    .to_string()
+}
+
+/// The current time in seconds since the Unix epoch.
+#[must_use]
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+fn username() -> String {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .unwrap_or_else(|_| "unknown".to_string())
+}
+
+#[cfg(unix)]
+fn hostname() -> String {
+    let mut buf = [0u8; 256];
+    // SAFETY: the buffer is valid for writes of `buf.len()` bytes.
+    let result = unsafe { libc::gethostname(buf.as_mut_ptr().cast(), buf.len()) };
+    if result != 0 {
+        return "unknown".to_string();
+    }
+    let len = buf.iter().position(|&byte| byte == 0).unwrap_or(buf.len());
+    String::from_utf8_lossy(&buf[..len]).into_owned()
+}
+
+#[cfg(not(unix))]
+fn hostname() -> String {
+    std::env::var("COMPUTERNAME").unwrap_or_else(|_| "unknown".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    fn record(event: Event, start: u64) -> Record {
+        Record {
+            event,
+            profile: "prod".to_string(),
+            from: None,
+            hash: Some(hash(b"KEY=value\n")),
+            user: "alice".to_string(),
+            host: "laptop".to_string(),
+            cwd: "/tmp".to_string(),
+            pid: 42,
+            start,
+            end: None,
+            status: None,
+        }
+    }
+
+    #[test]
+    fn test_finished_session_replaces_open_record() {
+        let dir = TempDir::new("audit-sessions");
+        let path = dir.join("audit.log");
+        let open = record(Event::Activate, 1_000);
+        let mut finished = open.clone();
+        finished.end = Some(1_300);
+        finished.status = Some(0);
+
+        append(&path, &open).unwrap();
+        append(&path, &record(Event::Edit, 1_100)).unwrap();
+        append(&path, &finished).unwrap();
+
+        let records = read(&path).unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].event, Event::Edit);
+        assert_eq!(records[1], finished);
+    }
+
+    #[test]
+    fn test_rotation_keeps_older_records() {
+        let dir = TempDir::new("audit-rotation");
+        let path = dir.join("audit.log");
+        let old = serde_json::to_string(&record(Event::Edit, 1)).unwrap();
+        let padding = usize::try_from(MAX_LOG_SIZE).unwrap() / (old.len() + 1) + 1;
+        fs::write(&path, format!("{old}\n").repeat(padding)).unwrap();
+
+        append(&path, &record(Event::Remove, 2)).unwrap();
+
+        assert!(rotated_path(&path, 1).exists());
+        let records = read(&path).unwrap();
+        assert_eq!(records.len(), padding + 1);
+        assert_eq!(records.last().unwrap().event, Event::Remove);
+    }
+
+    #[test]
+    fn test_history_follows_renames() {
+        let named = |event, profile: &str, from: Option<&str>, start| Record {
+            profile: profile.to_string(),
+            from: from.map(ToString::to_string),
+            ..record(event, start)
+        };
+        let records = [
+            named(Event::Edit, "staging", None, 1),
+            named(Event::Activate, "old", None, 2),
+            named(Event::Rename, "prod", Some("old"), 3),
+            // A new profile reusing the old name is not part of the history
+            named(Event::Edit, "old", None, 4),
+            named(Event::Activate, "prod", None, 5),
+        ];
+
+        let starts: Vec<u64> = history(&records, "prod")
+            .iter()
+            .map(|record| record.start)
+            .collect();
+        assert_eq!(starts, [2, 3, 5]);
+    }
+
+    #[test]
+    fn test_format_timestamp() {
+        assert_eq!(format_timestamp(0), "1970-01-01 00:00:00");
+        assert_eq!(format_timestamp(951_827_696), "2000-02-29 12:34:56");
+        assert_eq!(format_timestamp(1_700_000_000), "2023-11-14 22:13:20");
+    }
+
+    #[test]
+    fn test_parse_since() {
+        let now = 1_700_000_000;
+        assert_eq!(parse_since("2h", now).unwrap(), now - 7_200);
+        assert_eq!(parse_since("2023-11-14", now).unwrap(), 1_699_920_000);
+        assert_eq!(parse_since("2000-03-01", now).unwrap(), 951_868_800);
+        assert!(parse_since("yesterday", now).is_err());
+        assert!(parse_since("2023-13-01", now).is_err());
+
+        // Days past the end of the month are not rolled over into the next one
+        assert_eq!(parse_since("2024-02-29", now).unwrap(), 1_709_164_800);
+        assert!(parse_since("2023-02-29", now).is_err());
+        assert!(parse_since("2024-02-30", now).is_err());
+        assert!(parse_since("2023-04-31", now).is_err());
+        assert!(parse_since("2100-02-29", now).is_err());
+        assert!(parse_since("2023-12-31", now).is_ok());
+    }
+
+    #[test]
+    fn test_format_record() {
+        let mut session = record(Event::Activate, 1_700_000_000);
+        assert_eq!(
+            format_record(&session),
+            "2023-11-14 22:13:20  activate  prod  alice@laptop  no end recorded"
+        );
+        session.end = Some(1_700_000_300);
+        session.status = Some(0);
+        assert_eq!(
+            format_record(&session),
+            "2023-11-14 22:13:20  activate  prod  alice@laptop  exit 0 after 5m"
+        );
+        assert_eq!(
+            format_record(&record(Event::Edit, 1_700_000_000)),
+            "2023-11-14 22:13:20  edit      prod  alice@laptop"
+        );
+    }
+}
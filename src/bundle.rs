@@ -0,0 +1,383 @@
+//! Bundles of profiles for moving them between machines.
+//!
+//! A bundle is a gzipped tar archive holding a `manifest.json`, the profile
+//! files under `profiles/` and their hook scripts under `hooks/`. It may be
+//! encrypted as a whole with a passphrase, in which case the file starts with
+//! [`ENCRYPTED_MAGIC`], followed by the key derivation salt, the nonce and the
+//! ChaCha20-Poly1305 ciphertext.
+
+use anyhow::{anyhow, bail, Context, Result};
+use argon2::Argon2;
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::Read;
+
+use crate::digest;
+use crate::hooks::Stage;
+use crate::interact::Prompter;
+
+/// Environment variable supplying the bundle passphrase non-interactively.
+const ENV_PASSPHRASE: &str = "KRAVEN_BUNDLE_PASSPHRASE";
+
+const ENCRYPTED_MAGIC: &[u8] = b"KRAVENC1";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+const MANIFEST_PATH: &str = "manifest.json";
+const FORMAT_VERSION: u32 = 1;
+
+/// A profile in a bundle.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BundledProfile {
+    /// The profile name, without store qualifier.
+    pub name: String,
+    pub content: Vec<u8>,
+    /// Unix permission bits.
+    pub mode: u32,
+    pub hooks: Vec<BundledHook>,
+}
+
+/// A hook script in a bundle.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BundledHook {
+    pub stage: Stage,
+    pub content: Vec<u8>,
+    pub mode: u32,
+}
+
+/// A set of profiles to export or import.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bundle {
+    /// Seconds since the Unix epoch.
+    pub created: u64,
+    /// Whether secret values were stripped on export.
+    pub secrets_excluded: bool,
+    pub profiles: Vec<BundledProfile>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Manifest {
+    version: u32,
+    created: u64,
+    secrets_excluded: bool,
+    profiles: Vec<ManifestEntry>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ManifestEntry {
+    name: String,
+    mode: u32,
+    /// Checked on import to catch damaged archives.
+    hash: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    hooks: Vec<ManifestHook>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ManifestHook {
+    stage: String,
+    mode: u32,
+}
+
+impl Bundle {
+    /// Serializes the bundle, encrypting it when a passphrase is given.
+    pub fn to_bytes(&self, passphrase: Option<&str>) -> Result<Vec<u8>> {
+        let archive = self.to_archive()?;
+        match passphrase {
+            Some(passphrase) => encrypt(&archive, passphrase),
+            None => Ok(archive),
+        }
+    }
+
+    /// Reads a bundle, calling `passphrase` only if it is encrypted.
+    pub fn from_bytes(data: &[u8], passphrase: impl FnOnce() -> Result<String>) -> Result<Self> {
+        if is_encrypted(data) {
+            let archive = decrypt(data, &passphrase()?)?;
+            Self::from_archive(&archive)
+        } else {
+            Self::from_archive(data)
+        }
+    }
+
+    fn to_archive(&self) -> Result<Vec<u8>> {
+        let manifest = Manifest {
+            version: FORMAT_VERSION,
+            created: self.created,
+            secrets_excluded: self.secrets_excluded,
+            profiles: self
+                .profiles
+                .iter()
+                .map(|profile| ManifestEntry {
+                    name: profile.name.clone(),
+                    mode: profile.mode,
+                    hash: digest::hash(&profile.content),
+                    hooks: profile
+                        .hooks
+                        .iter()
+                        .map(|hook| ManifestHook {
+                            stage: hook.stage.name().to_string(),
+                            mode: hook.mode,
+                        })
+                        .collect(),
+                })
+                .collect(),
+        };
+
+        let mut builder = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+        let mut append = |path: &str, content: &[u8], mode: u32| {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(mode);
+            header.set_mtime(self.created);
+            header.set_cksum();
+            builder.append_data(&mut header, path, content)
+        };
+
+        append(MANIFEST_PATH, &serde_json::to_vec_pretty(&manifest)?, 0o644)?;
+        for profile in &self.profiles {
+            append(&profile_path(&profile.name), &profile.content, profile.mode)?;
+            for hook in &profile.hooks {
+                append(
+                    &hook_path(&profile.name, hook.stage),
+                    &hook.content,
+                    hook.mode,
+                )?;
+            }
+        }
+
+        Ok(builder.into_inner()?.finish()?)
+    }
+
+    fn from_archive(data: &[u8]) -> Result<Self> {
+        let mut files = BTreeMap::new();
+        let mut archive = tar::Archive::new(GzDecoder::new(data));
+        for entry in archive.entries().context("Not a kraven bundle")? {
+            let mut entry = entry.context("Failed to read bundle")?;
+            if !entry.header().entry_type().is_file() {
+                bail!("Unexpected non-file entry in bundle");
+            }
+            let path = entry.path()?.to_string_lossy().into_owned();
+            let mut content = Vec::new();
+            entry.read_to_end(&mut content)?;
+            files.insert(path, content);
+        }
+
+        let manifest: Manifest = serde_json::from_slice(
+            &files
+                .remove(MANIFEST_PATH)
+                .context("Not a kraven bundle: missing manifest")?,
+        )
+        .context("Invalid bundle manifest")?;
+        if manifest.version != FORMAT_VERSION {
+            bail!(
+                "Unsupported bundle version {} (this kraven reads version {FORMAT_VERSION})",
+                manifest.version
+            );
+        }
+
+        let mut profiles = Vec::new();
+        for entry in manifest.profiles {
+            let content = files
+                .remove(&profile_path(&entry.name))
+                .with_context(|| format!("Bundle is missing profile '{}'", entry.name))?;
+            if digest::hash(&content) != entry.hash {
+                bail!("Profile '{}' in the bundle is corrupted", entry.name);
+            }
+
+            let mut hooks = Vec::new();
+            for hook in entry.hooks {
+                let stage = Stage::from_name(&hook.stage)
+                    .with_context(|| format!("Unknown hook '{}' in bundle", hook.stage))?;
+                let content = files
+                    .remove(&hook_path(&entry.name, stage))
+                    .with_context(|| format!("Bundle is missing a hook of '{}'", entry.name))?;
+                hooks.push(BundledHook {
+                    stage,
+                    content,
+                    mode: hook.mode,
+                });
+            }
+
+            profiles.push(BundledProfile {
+                name: entry.name,
+                content,
+                mode: entry.mode,
+                hooks,
+            });
+        }
+
+        if let Some(path) = files.keys().next() {
+            bail!("Unexpected file in bundle: {path}");
+        }
+
+        Ok(Self {
+            created: manifest.created,
+            secrets_excluded: manifest.secrets_excluded,
+            profiles,
+        })
+    }
+}
+
+fn profile_path(name: &str) -> String {
+    format!("profiles/{name}")
+}
+
+fn hook_path(name: &str, stage: Stage) -> String {
+    format!("hooks/{name}/{}", stage.name())
+}
+
+/// Whether `data` is an encrypted bundle.
+#[must_use]
+pub fn is_encrypted(data: &[u8]) -> bool {
+    data.starts_with(ENCRYPTED_MAGIC)
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<Key> {
+    let mut key = Key::default();
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|err| anyhow!("Failed to derive key: {err}"))?;
+    Ok(key)
+}
+
+fn encrypt(plaintext: &[u8], passphrase: &str) -> Result<Vec<u8>> {
+    let mut salt = [0u8; SALT_LEN];
+    let mut nonce = [0u8; NONCE_LEN];
+    getrandom::getrandom(&mut salt).map_err(|err| anyhow!("Failed to generate salt: {err}"))?;
+    getrandom::getrandom(&mut nonce).map_err(|err| anyhow!("Failed to generate nonce: {err}"))?;
+
+    let cipher = ChaCha20Poly1305::new(&derive_key(passphrase, &salt)?);
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce), plaintext)
+        .map_err(|_| anyhow!("Failed to encrypt bundle"))?;
+
+    Ok([ENCRYPTED_MAGIC, &salt, &nonce, &ciphertext].concat())
+}
+
+fn decrypt(data: &[u8], passphrase: &str) -> Result<Vec<u8>> {
+    let header_len = ENCRYPTED_MAGIC.len() + SALT_LEN + NONCE_LEN;
+    if data.len() < header_len {
+        bail!("Encrypted bundle is truncated");
+    }
+    let (salt, rest) = data[ENCRYPTED_MAGIC.len()..].split_at(SALT_LEN);
+    let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+
+    let cipher = ChaCha20Poly1305::new(&derive_key(passphrase, salt)?);
+    cipher
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| anyhow!("Wrong passphrase or corrupted bundle"))
+}
+
+/// Asks for the passphrase of an encrypted bundle.
+pub fn passphrase(prompter: &mut dyn Prompter) -> Result<String> {
+    if let Ok(passphrase) = std::env::var(ENV_PASSPHRASE) {
+        return Ok(passphrase);
+    }
+    if !prompter.is_interactive() {
+        bail!("The bundle is encrypted. Set {ENV_PASSPHRASE} to import it non-interactively.");
+    }
+    prompter.read_secret("Bundle passphrase: ")
+}
+
+/// Asks for a new passphrase, twice to catch typos.
+pub fn new_passphrase(prompter: &mut dyn Prompter) -> Result<String> {
+    if let Ok(passphrase) = std::env::var(ENV_PASSPHRASE) {
+        if passphrase.is_empty() {
+            bail!("{ENV_PASSPHRASE} is empty.");
+        }
+        return Ok(passphrase);
+    }
+    if !prompter.is_interactive() {
+        bail!("Set {ENV_PASSPHRASE} to encrypt a bundle non-interactively.");
+    }
+
+    let passphrase = prompter.read_secret("New bundle passphrase: ")?;
+    if passphrase.is_empty() {
+        bail!("The passphrase cannot be empty.");
+    }
+    if prompter.read_secret("Repeat the passphrase: ")? != passphrase {
+        bail!("The passphrases do not match.");
+    }
+    Ok(passphrase)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interact::testing::Scripted;
+
+    fn sample() -> Bundle {
+        Bundle {
+            created: 1_700_000_000,
+            secrets_excluded: false,
+            profiles: vec![
+                BundledProfile {
+                    name: "dev".to_string(),
+                    content: b"KEY=value\n".to_vec(),
+                    mode: 0o600,
+                    hooks: vec![],
+                },
+                BundledProfile {
+                    name: "work/prod".to_string(),
+                    content: b"#@protected\nTOKEN=abc\n".to_vec(),
+                    mode: 0o640,
+                    hooks: vec![BundledHook {
+                        stage: Stage::PreActivate,
+                        content: b"#!/bin/sh\necho hi\n".to_vec(),
+                        mode: 0o755,
+                    }],
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let bundle = sample();
+        let data = bundle.to_bytes(None).unwrap();
+        assert!(!is_encrypted(&data));
+
+        let read = Bundle::from_bytes(&data, || panic!("not encrypted")).unwrap();
+        assert_eq!(read, bundle);
+    }
+
+    #[test]
+    fn test_encrypted_roundtrip() {
+        let bundle = sample();
+        let data = bundle.to_bytes(Some("correct horse")).unwrap();
+        assert!(is_encrypted(&data));
+        assert!(!data.windows(9).any(|window| window == b"TOKEN=abc"));
+
+        let read = Bundle::from_bytes(&data, || Ok("correct horse".to_string())).unwrap();
+        assert_eq!(read, bundle);
+
+        let wrong = Bundle::from_bytes(&data, || Ok("battery staple".to_string()));
+        assert!(wrong.is_err());
+    }
+
+    #[test]
+    fn test_rejects_garbage() {
+        assert!(Bundle::from_bytes(b"not a bundle", || unreachable!()).is_err());
+        assert!(Bundle::from_bytes(b"KRAVENC1short", || Ok(String::new())).is_err());
+    }
+
+    #[test]
+    fn test_new_passphrase_must_match() {
+        if std::env::var(ENV_PASSPHRASE).is_ok() {
+            return;
+        }
+        let mut prompter = Scripted::new(&["secret", "secret"]);
+        assert_eq!(new_passphrase(&mut prompter).unwrap(), "secret");
+
+        let mut prompter = Scripted::new(&["secret", "typo"]);
+        assert!(new_passphrase(&mut prompter).is_err());
+
+        let mut prompter = Scripted::new(&[""]);
+        assert!(new_passphrase(&mut prompter).is_err());
+    }
+}
@@ -1,61 +1,130 @@
 use anyhow::{bail, Context, Result};
 use std::process::Command;
+use std::time::Duration;
 
-use crate::config::{get_profile_path, KRAVEN_ACTIVE};
-use crate::profile::Profile;
+use kraven::Activation;
 
-pub fn run(profile_name: &str) -> Result<()> {
-    // Prevent nested sessions
+use crate::audit::{self, Event, Record};
+use crate::commands::{allowed_hooks, load_profile};
+use crate::config::{resolve_profile, KRAVEN_ACTIVE};
+use crate::exit::KravenError;
+use crate::hooks::Stage;
+use crate::interact::{self, Terminal};
+use crate::prompt::{self, current_stack, PromptContext};
+use crate::session::{self, current_deadline, Clock, Expiry, SystemClock};
+use crate::settings::{NestingPolicy, Settings};
+use crate::shell::Shell;
+
+/// Command-line overrides for `activate`.
+#[allow(clippy::struct_excessive_bools)] // Mirrors the command-line flags
+pub struct Options {
+    pub shell: Option<String>,
+    pub prompt: Option<String>,
+    pub no_prompt: bool,
+    pub isolate: bool,
+    pub nesting: Option<NestingPolicy>,
+    pub ttl: Option<Duration>,
+    pub yes: bool,
+    pub no_hooks: bool,
+}
+
+pub fn run(profile_name: &str, options: &Options) -> Result<()> {
+    let settings = Settings::load()?;
+
+    // Prevent nested sessions unless the policy allows them
     if let Ok(active_profile) = std::env::var(KRAVEN_ACTIVE) {
-        bail!(
-            "Already in kraven session for profile '{active_profile}'.\n\
-             Exit the current session first with 'exit' or Ctrl+D."
-        );
+        if settings.nesting(options.nesting)? == NestingPolicy::Deny {
+            bail!(KravenError::NestedSession {
+                active: active_profile
+            });
+        }
     }
 
-    let profile_path = get_profile_path(profile_name)?;
-    let profile = Profile::load(profile_name, &profile_path)?;
+    let resolved = resolve_profile(profile_name)?;
+    let profile = load_profile(profile_name, &resolved.path)?;
+    interact::confirm_protected(
+        &mut Terminal,
+        profile_name,
+        profile.meta.protected,
+        options.yes,
+    )?;
 
-    let shell = std::env::var("SHELL").unwrap_or_else(|_| "/bin/sh".to_string());
+    let program = settings.shell(options.shell.as_deref());
+    let shell = Shell::from_program(&program);
 
-    let mut cmd = Command::new(&shell);
+    // Remember any enclosing session
+    let mut stack = current_stack();
+    if let Ok(active_profile) = std::env::var(KRAVEN_ACTIVE) {
+        stack.push(active_profile);
+    }
 
-    // Inject all parsed environment variables
-    for (key, value) in &profile.vars {
-        cmd.env(key, value);
+    // Limit the session lifetime, never outliving an enclosing time-limited session
+    let expiry = match options.ttl.or(profile.meta.ttl) {
+        Some(ttl) => {
+            let expiry = Expiry::new(SystemClock.now(), ttl)?;
+            Some(match current_deadline() {
+                Some(outer) => expiry.min(outer),
+                None => expiry,
+            })
+        }
+        None => None,
+    };
+
+    // The environment of the shell, also given to the hooks
+    let allowlist = settings.isolate_allowlist();
+    let mut activation = Activation::new(profile_name, &profile).session(&stack);
+    if options.isolate {
+        activation = activation.isolate(&allowlist);
+    }
+    if let Some(expiry) = &expiry {
+        activation = activation.expires_at(expiry.deadline());
     }
+    let session_env = |cmd: &mut Command| activation.apply(cmd);
 
-    // Mark this session
-    cmd.env(KRAVEN_ACTIVE, profile_name);
+    let hooks = (!options.no_hooks)
+        .then(|| allowed_hooks(&resolved, &profile))
+        .transpose()?;
+    if let Some(hooks) = &hooks {
+        hooks.run(Stage::PreActivate, &session_env)?;
+    }
+
+    let mut cmd = shell.interactive_command(&program);
+    session_env(&mut cmd);
 
     // Modify prompt to show active profile
-    // Different shells need different approaches since rc files override PS1
-    let shell_name = std::path::Path::new(&shell)
-        .file_name()
-        .and_then(|s| s.to_str())
-        .unwrap_or("");
-
-    if shell_name == "bash" {
-        // PROMPT_COMMAND runs before each prompt, allowing us to modify PS1
-        // after .bashrc has set it
-        let existing = std::env::var("PROMPT_COMMAND").unwrap_or_default();
-        let prefix_cmd = format!(r#"PS1="({profile_name}) ${{PS1#\({profile_name}\) }}""#);
-        let new_prompt_cmd = if existing.is_empty() {
-            prefix_cmd
-        } else {
-            format!("{prefix_cmd}; {existing}")
-        };
-        cmd.env("PROMPT_COMMAND", new_prompt_cmd);
+    let prompt_guard = if options.no_prompt {
+        None
     } else {
-        // For zsh and other shells, set PS1 directly
-        let current_ps1 = std::env::var("PS1").unwrap_or_default();
-        cmd.env("PS1", format!("({profile_name}) {current_ps1}"));
-    }
+        let template = settings.prompt(options.prompt.as_deref());
+        let context = PromptContext {
+            profile: profile_name,
+            stack: &stack,
+        };
+        let mut prefix = prompt::render(&template, &context, shell.escapes())?;
+        if profile.meta.protected {
+            prefix = prompt::warning(&prefix, shell.escapes());
+        }
+        Some(shell.inject_prompt(&mut cmd, &prefix)?)
+    };
+
+    // Run interactively, logging the session before it starts in case kraven is killed with it
+    let mut child = cmd
+        .spawn()
+        .with_context(|| format!("Failed to spawn shell: {program}"))?;
+    let mut audit_record = Record::new(Event::Activate, &resolved.reference(), &resolved.path);
+    audit::log(&audit_record);
+    let status = session::supervise(&mut child, profile_name, expiry, &SystemClock)?;
+    audit_record.finish(status.code());
+    audit::log(&audit_record);
 
-    // Run interactively
-    let status = cmd
-        .status()
-        .with_context(|| format!("Failed to spawn shell: {shell}"))?;
+    // exit() skips destructors, so clean up the prompt files first
+    drop(prompt_guard);
 
+    // The shell is gone, so a failing post hook can only be reported
+    if let Some(hooks) = &hooks {
+        if let Err(err) = hooks.run(Stage::PostDeactivate, &session_env) {
+            eprintln!("kraven: {err:#}");
+        }
+    }
     std::process::exit(status.code().unwrap_or(1));
 }
@@ -0,0 +1,43 @@
+use anyhow::{bail, Result};
+
+use crate::commands::load_profile;
+use crate::config::{resolve_profile, ProfileSource};
+use crate::hooks::Hooks;
+use crate::interact::{self, Prompter, Terminal};
+use crate::trust;
+
+/// Show the hooks of a project profile and allow them to run, as they are now.
+pub fn run(profile_name: &str, yes: bool) -> Result<()> {
+    let resolved = resolve_profile(profile_name)?;
+    let profile = load_profile(profile_name, &resolved.path)?;
+    let reference = resolved.reference();
+    let hooks = Hooks::new(&profile.meta.hooks, resolved.hooks_dir());
+
+    if hooks.is_empty() {
+        println!("Profile '{reference}' has no hooks.");
+        return Ok(());
+    }
+    if resolved.source == ProfileSource::Global {
+        println!("The hooks of global profiles always run.");
+        return Ok(());
+    }
+
+    println!("Hooks of '{reference}':");
+    for hook in hooks.describe() {
+        println!("  {hook}");
+    }
+    if !yes {
+        let mut prompter = Terminal;
+        if !prompter.is_interactive() {
+            bail!("Pass --yes to allow the hooks non-interactively.");
+        }
+        if !interact::confirm(&mut prompter, "Allow these hooks to run?")? {
+            println!("Aborted.");
+            return Ok(());
+        }
+    }
+
+    trust::allow(&resolved.path, &hooks)?;
+    println!("Allowed the hooks of '{reference}' until they change.");
+    Ok(())
+}
@@ -0,0 +1,253 @@
+use anyhow::{bail, Context, Result};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+
+use crate::audit::{self, Event, Record};
+use crate::bundle::{self, Bundle, BundledHook, BundledProfile};
+use crate::commands::load_profile;
+use crate::config::{
+    ensure_profile_dir_exists, get_profile_dir, list_profiles, resolve_profile, ProfileSource,
+};
+use crate::exit::KravenError;
+use crate::hooks::Stage;
+use crate::interact::Terminal;
+use crate::profile;
+use crate::store;
+
+/// Permissions given to profiles whose original permissions are unknown.
+const DEFAULT_MODE: u32 = 0o600;
+
+/// Write the given profiles (all global profiles by default) to a bundle file.
+pub fn export(
+    profile_names: &[String],
+    output: &Path,
+    encrypt: bool,
+    exclude_secrets: bool,
+) -> Result<()> {
+    let profile_names = if profile_names.is_empty() {
+        list_profiles(&get_profile_dir()?)?
+    } else {
+        profile_names.to_vec()
+    };
+    if profile_names.is_empty() {
+        bail!("No profiles to export.");
+    }
+
+    let mut profiles: Vec<BundledProfile> = Vec::new();
+    for profile_name in &profile_names {
+        let resolved = resolve_profile(profile_name)?;
+        if !resolved.path.is_file() {
+            bail!(KravenError::NotFound {
+                name: profile_name.clone(),
+            });
+        }
+        if profiles.iter().any(|profile| profile.name == resolved.name) {
+            bail!("Profile '{}' is listed more than once.", resolved.name);
+        }
+
+        let mut content = fs::read(&resolved.path)
+            .with_context(|| format!("Failed to read profile '{profile_name}'"))?;
+        if exclude_secrets {
+            let loaded = load_profile(profile_name, &resolved.path)?;
+            let text = String::from_utf8(content)
+                .with_context(|| format!("Profile '{profile_name}' is not valid UTF-8"))?;
+            content = profile::strip_values(&text, &loaded.meta.secrets).into_bytes();
+        }
+
+        let mut hooks = Vec::new();
+        for stage in Stage::ALL {
+            let script = resolved.hooks_dir().join(stage.name());
+            if script.is_file() {
+                hooks.push(BundledHook {
+                    stage,
+                    content: fs::read(&script)
+                        .with_context(|| format!("Failed to read {}", script.display()))?,
+                    mode: file_mode(&script),
+                });
+            }
+        }
+
+        profiles.push(BundledProfile {
+            name: resolved.name,
+            content,
+            mode: file_mode(&resolved.path),
+            hooks,
+        });
+    }
+
+    let passphrase = if encrypt {
+        Some(bundle::new_passphrase(&mut Terminal)?)
+    } else {
+        None
+    };
+    let bundle = Bundle {
+        created: audit::now(),
+        secrets_excluded: exclude_secrets,
+        profiles,
+    };
+    write_file(
+        output,
+        &bundle.to_bytes(passphrase.as_deref())?,
+        DEFAULT_MODE,
+    )?;
+
+    if !encrypt && !exclude_secrets {
+        eprintln!(
+            "Warning: the bundle holds secret values unencrypted. \
+             Use --encrypt or --exclude-secrets before sharing it."
+        );
+    }
+    println!(
+        "Exported {} profile(s) to {}.",
+        bundle.profiles.len(),
+        output.display()
+    );
+
+    Ok(())
+}
+
+/// What importing a bundled profile would do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Plan {
+    Create,
+    Unchanged,
+    Conflict,
+}
+
+/// Import the profiles of a bundle into the global profile directory.
+pub fn import(file: &Path, force: bool, skip_existing: bool) -> Result<()> {
+    let data = fs::read(file).with_context(|| format!("Failed to read {}", file.display()))?;
+    let bundle = Bundle::from_bytes(&data, || bundle::passphrase(&mut Terminal))?;
+
+    // Validate every name before writing anything
+    let mut planned = Vec::new();
+    for profile in &bundle.profiles {
+        let resolved = resolve_profile(&format!(
+            "{}:{}",
+            ProfileSource::Global.qualifier(),
+            profile.name
+        ))
+        .with_context(|| format!("Bundle holds an invalid profile name: '{}'", profile.name))?;
+        let plan = match fs::read(&resolved.path) {
+            Ok(existing) if existing == profile.content => Plan::Unchanged,
+            Ok(_) => Plan::Conflict,
+            Err(_) if resolved.path.is_dir() => {
+                bail!(
+                    "'{}' is a namespace in your profile directory.",
+                    profile.name
+                )
+            }
+            Err(_) => Plan::Create,
+        };
+        planned.push((profile, resolved, plan));
+    }
+
+    for (profile, _, plan) in &planned {
+        let label = match plan {
+            Plan::Create => "new",
+            Plan::Unchanged => "unchanged",
+            Plan::Conflict if force => "overwrite",
+            Plan::Conflict if skip_existing => "skip",
+            Plan::Conflict => "conflict",
+        };
+        println!("  {label:<10} {}", profile.name);
+    }
+
+    let conflicts = planned
+        .iter()
+        .filter(|(_, _, plan)| *plan == Plan::Conflict)
+        .count();
+    if conflicts > 0 && !force && !skip_existing {
+        bail!(
+            "{conflicts} profile(s) already exist with different content. \
+             Re-run with --force to overwrite them or --skip-existing to import the rest."
+        );
+    }
+
+    let profile_dir = ensure_profile_dir_exists()?;
+    let mut imported = 0;
+    for (profile, resolved, plan) in &planned {
+        if *plan == Plan::Unchanged || (*plan == Plan::Conflict && !force) {
+            continue;
+        }
+
+        if let Some(parent) = resolved.path.parent() {
+            fs::create_dir_all(parent).with_context(|| {
+                format!("Failed to create namespace directory: {}", parent.display())
+            })?;
+        }
+        write_file(&resolved.path, &profile.content, profile.mode)?;
+
+        let hooks_dir = resolved.hooks_dir();
+        for stage in Stage::ALL {
+            let script = hooks_dir.join(stage.name());
+            match profile.hooks.iter().find(|hook| hook.stage == stage) {
+                Some(hook) => {
+                    fs::create_dir_all(&hooks_dir).with_context(|| {
+                        format!("Failed to create directory: {}", hooks_dir.display())
+                    })?;
+                    write_file(&script, &hook.content, hook.mode)?;
+                }
+                // Scripts of an overwritten profile must not survive it
+                None if script.is_file() => fs::remove_file(&script)
+                    .with_context(|| format!("Failed to remove {}", script.display()))?,
+                None => {}
+            }
+        }
+
+        audit::log(&Record {
+            from: Some(format!("bundle:{}", file.display())),
+            ..Record::new(Event::Import, &resolved.reference(), &resolved.path)
+        });
+        imported += 1;
+    }
+
+    if imported > 0 {
+        store::commit(
+            &profile_dir,
+            &format!("Import {imported} profile(s) from {}", file.display()),
+        );
+    }
+
+    println!("Imported {imported} profile(s) from {}.", file.display());
+    if bundle.secrets_excluded {
+        println!("Secret values were excluded from this bundle. Fill them in with 'kraven edit'.");
+    }
+
+    Ok(())
+}
+
+#[cfg(unix)]
+fn file_mode(path: &Path) -> u32 {
+    use std::os::unix::fs::PermissionsExt;
+    fs::metadata(path).map_or(DEFAULT_MODE, |meta| meta.permissions().mode() & 0o777)
+}
+
+#[cfg(not(unix))]
+fn file_mode(_path: &Path) -> u32 {
+    DEFAULT_MODE
+}
+
+/// Writes a file with the given permissions, never exposing it with looser ones.
+fn write_file(path: &Path, content: &[u8], mode: u32) -> Result<()> {
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        options.mode(mode & 0o777);
+        // The mode above only applies to new files
+        if path.exists() {
+            fs::set_permissions(path, fs::Permissions::from_mode(mode & 0o777))
+                .with_context(|| format!("Failed to set permissions of {}", path.display()))?;
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = mode;
+
+    options
+        .open(path)
+        .and_then(|mut file| file.write_all(content))
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
@@ -0,0 +1,45 @@
+use anyhow::Result;
+
+use crate::config::get_config_path;
+use crate::settings::{Settings, KEYS};
+
+/// Print the configured value of a setting.
+pub fn get(key: &str) -> Result<()> {
+    let settings = Settings::load()?;
+    if let Some(value) = settings.get(key)? {
+        println!("{value}");
+    }
+    Ok(())
+}
+
+/// Set a setting in the configuration file.
+pub fn set(key: &str, value: &str) -> Result<()> {
+    let mut settings = Settings::load()?;
+    settings.set(key, value)?;
+    settings.save()
+}
+
+/// Remove a setting from the configuration file.
+pub fn unset(key: &str) -> Result<()> {
+    let mut settings = Settings::load()?;
+    settings.unset(key)?;
+    settings.save()
+}
+
+/// Print every setting, marking the ones that use their default.
+pub fn list() -> Result<()> {
+    let settings = Settings::load()?;
+    for key in KEYS {
+        match settings.get(key)? {
+            Some(value) => println!("{key} = {value}"),
+            None => println!("{key} (default)"),
+        }
+    }
+    Ok(())
+}
+
+/// Print the path of the configuration file.
+pub fn path() -> Result<()> {
+    println!("{}", get_config_path()?.display());
+    Ok(())
+}
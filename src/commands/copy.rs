@@ -0,0 +1,29 @@
+use anyhow::{Context, Result};
+use std::fs;
+
+use crate::audit::{self, Event, Record};
+use crate::commands::rename::resolve_pair;
+use crate::hooks;
+use crate::store;
+
+pub fn run(profile_name: &str, destination_name: &str, force: bool) -> Result<()> {
+    let (source, destination) = resolve_pair(profile_name, destination_name, force)?;
+
+    fs::copy(&source.path, &destination.path)
+        .with_context(|| format!("Failed to copy profile '{profile_name}'"))?;
+    hooks::copy_scripts(&source.hooks_dir(), &destination.hooks_dir())?;
+
+    audit::log(&Record {
+        from: Some(source.reference()),
+        ..Record::new(Event::Copy, &destination.reference(), &destination.path)
+    });
+
+    store::commit(
+        &destination.root,
+        &format!("Copy profile '{}' to '{}'", source.name, destination.name),
+    );
+
+    println!("Profile '{profile_name}' copied to '{destination_name}'.");
+
+    Ok(())
+}
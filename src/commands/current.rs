@@ -1,10 +1,21 @@
 use anyhow::Result;
+use std::time::SystemTime;
 
 use crate::config::KRAVEN_ACTIVE;
+use crate::duration::format_duration;
+use crate::session::current_deadline;
 
 pub fn run() -> Result<()> {
     let profile =
         std::env::var(KRAVEN_ACTIVE).map_err(|_| anyhow::anyhow!("No profile active."))?;
-    println!("{profile}");
+
+    match current_deadline() {
+        Some(deadline) => match deadline.duration_since(SystemTime::now()) {
+            Ok(remaining) => println!("{profile} (expires in {})", format_duration(remaining)),
+            Err(_) => println!("{profile} (expired)"),
+        },
+        None => println!("{profile}"),
+    }
+
     Ok(())
 }
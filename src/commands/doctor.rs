@@ -0,0 +1,560 @@
+use anyhow::{bail, Result};
+use std::env;
+use std::ffi::OsStr;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::commands::sync::restrict_permissions;
+use crate::config::{
+    discover_profiles, ensure_profile_dir_exists, get_config_path, get_profile_dir, profile_stores,
+    ProfileSource, KRAVEN_ACTIVE,
+};
+use crate::profile::{self, Profile};
+use crate::prompt::KRAVEN_STACK;
+use crate::session::{self, KRAVEN_EXPIRES_AT, KRAVEN_PID};
+use crate::settings::Settings;
+use crate::shell::Shell;
+
+/// Outcome of a check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Status {
+    Pass,
+    Warn,
+    Fail,
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Pass => write!(f, "pass"),
+            Self::Warn => write!(f, "warn"),
+            Self::Fail => write!(f, "fail"),
+        }
+    }
+}
+
+/// A repair that can't lose data, applied by `--fix`.
+#[derive(Debug, PartialEq, Eq)]
+enum Fix {
+    /// Create the profile directory, private to the user.
+    CreateDir(PathBuf),
+    /// Remove the permissions of the group and other users.
+    Restrict(Vec<PathBuf>),
+}
+
+impl Fix {
+    /// Applies the fix, describing what was done.
+    fn apply(&self) -> Result<String> {
+        match self {
+            Self::CreateDir(dir) => {
+                ensure_profile_dir_exists()?;
+                restrict_permissions(dir)?;
+                Ok(format!("created {}", dir.display()))
+            }
+            Self::Restrict(paths) => {
+                for path in paths {
+                    restrict_permissions(path)?;
+                }
+                Ok(format!("made {} path(s) private", paths.len()))
+            }
+        }
+    }
+}
+
+/// The result of one check.
+#[derive(Debug)]
+struct Check {
+    name: &'static str,
+    status: Status,
+    summary: String,
+    details: Vec<String>,
+    fix: Option<Fix>,
+}
+
+impl Check {
+    fn new(name: &'static str, status: Status, summary: impl Into<String>) -> Self {
+        Self {
+            name,
+            status,
+            summary: summary.into(),
+            details: Vec::new(),
+            fix: None,
+        }
+    }
+
+    fn details(mut self, details: Vec<String>) -> Self {
+        self.details = details;
+        self
+    }
+
+    fn fix(mut self, fix: Fix) -> Self {
+        self.fix = Some(fix);
+        self
+    }
+}
+
+/// A file found in one of the stores.
+struct Entry {
+    /// Name to show, qualified for project profiles.
+    label: String,
+    path: PathBuf,
+}
+
+/// Check the kraven setup, applying the safe fixes if `fix` is set.
+pub fn run(fix: bool) -> Result<()> {
+    let (settings, mut checks) = match Settings::load() {
+        Ok(settings) => (
+            settings,
+            vec![Check::new(
+                "config",
+                Status::Pass,
+                get_config_path()?.display().to_string(),
+            )],
+        ),
+        Err(err) => (
+            Settings::default(),
+            vec![Check::new("config", Status::Fail, format!("{err:#}"))],
+        ),
+    };
+
+    let dir = get_profile_dir()?;
+    checks.push(check_profile_dir(&dir));
+    let (entries, invalid) = find_profiles()?;
+    let roots: Vec<PathBuf> = profile_stores()?
+        .into_iter()
+        .map(|store| store.dir)
+        .collect();
+    checks.push(check_permissions(&roots));
+    checks.push(check_parse(&entries));
+    checks.push(check_names(&invalid));
+    checks.push(check_duplicates(&entries));
+    checks.push(check_executable("shell", &settings.shell(None)));
+    checks.push(check_editor(&settings.editor()));
+    checks.push(check_completions(
+        env::var("SHELL").ok().as_deref(),
+        dirs::home_dir().as_deref(),
+    ));
+    checks.push(check_session());
+
+    let mut fixable = false;
+    let mut warnings = 0;
+    let mut failures = 0;
+    for mut check in checks {
+        if let Some(repair) = check.fix.take() {
+            if fix {
+                check.summary = format!("fixed, {}", repair.apply()?);
+                check.status = Status::Pass;
+            } else {
+                fixable = true;
+            }
+        }
+
+        println!("[{}] {}: {}", check.status, check.name, check.summary);
+        for detail in &check.details {
+            println!("       {detail}");
+        }
+        match check.status {
+            Status::Pass => {}
+            Status::Warn => warnings += 1,
+            Status::Fail => failures += 1,
+        }
+    }
+
+    if fixable {
+        println!("\nRun 'kraven doctor --fix' to apply the safe fixes.");
+    }
+    if failures > 0 {
+        bail!("{failures} check(s) failed.");
+    }
+    if warnings == 0 {
+        println!("\nEverything looks good.");
+    }
+    Ok(())
+}
+
+/// Lists the profiles of every store, and the files with invalid names.
+fn find_profiles() -> Result<(Vec<Entry>, Vec<Entry>)> {
+    let mut entries = Vec::new();
+    let mut invalid = Vec::new();
+    for store in profile_stores()? {
+        let discovery = discover_profiles(&store.dir)?;
+        let label = |name: &str| {
+            if store.source == ProfileSource::Global {
+                name.to_string()
+            } else {
+                format!("{}:{name}", store.source.qualifier())
+            }
+        };
+        for name in discovery.profiles {
+            entries.push(Entry {
+                label: label(&name),
+                path: store.dir.join(&name),
+            });
+        }
+        for entry in discovery.invalid {
+            invalid.push(Entry {
+                label: format!("{}: {}", label(&entry.name), entry.reason),
+                path: store.dir.join(&entry.name),
+            });
+        }
+    }
+    Ok((entries, invalid))
+}
+
+fn check_profile_dir(dir: &Path) -> Check {
+    const NAME: &str = "profile directory";
+    if !dir.exists() {
+        Check::new(
+            NAME,
+            Status::Warn,
+            format!("{} does not exist yet", dir.display()),
+        )
+        .fix(Fix::CreateDir(dir.to_path_buf()))
+    } else if !dir.is_dir() {
+        Check::new(
+            NAME,
+            Status::Fail,
+            format!("{} is not a directory", dir.display()),
+        )
+    } else {
+        Check::new(NAME, Status::Pass, dir.display().to_string())
+    }
+}
+
+/// Checks every file and directory of the stores, not only the profiles:
+/// backups, ignored files and hooks can hold secrets too.
+fn check_permissions(roots: &[PathBuf]) -> Check {
+    const NAME: &str = "permissions";
+    let mut paths = Vec::new();
+    for root in roots {
+        collect_paths(root, &mut paths);
+    }
+    let exposed: Vec<PathBuf> = paths.into_iter().filter(|path| is_exposed(path)).collect();
+    if exposed.is_empty() {
+        return Check::new(NAME, Status::Pass, "profiles are private");
+    }
+    Check::new(
+        NAME,
+        Status::Warn,
+        format!("{} path(s) can be accessed by other users", exposed.len()),
+    )
+    .details(
+        exposed
+            .iter()
+            .map(|path| path.display().to_string())
+            .collect(),
+    )
+    .fix(Fix::Restrict(exposed))
+}
+
+/// Adds `path` and, for a directory, everything below it. Symlinks are not
+/// followed, as what they point to is not part of the store.
+fn collect_paths(path: &Path, paths: &mut Vec<PathBuf>) {
+    let Ok(meta) = fs::symlink_metadata(path) else {
+        return;
+    };
+    if meta.is_file() {
+        paths.push(path.to_path_buf());
+    } else if meta.is_dir() {
+        paths.push(path.to_path_buf());
+        let Ok(entries) = fs::read_dir(path) else {
+            return;
+        };
+        let mut children: Vec<PathBuf> = entries.flatten().map(|entry| entry.path()).collect();
+        children.sort();
+        for child in children {
+            collect_paths(&child, paths);
+        }
+    }
+}
+
+#[cfg(unix)]
+fn is_exposed(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    fs::metadata(path).is_ok_and(|meta| meta.permissions().mode() & 0o077 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_exposed(_path: &Path) -> bool {
+    false
+}
+
+fn check_parse(entries: &[Entry]) -> Check {
+    const NAME: &str = "profiles";
+    let mut problems = Vec::new();
+    let mut ignored = Vec::new();
+    for entry in entries {
+        let content = match fs::read_to_string(&entry.path) {
+            Ok(content) => content,
+            Err(err) => {
+                problems.push(format!("{}: {err}", entry.label));
+                continue;
+            }
+        };
+        match Profile::check(&content) {
+            Ok(profile) => ignored.extend(profile.warnings.iter().map(|warning| {
+                format!("{}: line {}: {}", entry.label, warning.line, warning.kind)
+            })),
+            Err(diagnostics) => {
+                let first = &diagnostics[0];
+                problems.push(format!(
+                    "{}: {} error(s), the first on line {}: {}",
+                    entry.label,
+                    diagnostics.len(),
+                    first.line,
+                    first.kind
+                ));
+            }
+        }
+    }
+
+    if problems.is_empty() && ignored.is_empty() {
+        return Check::new(NAME, Status::Pass, format!("{} parsed", entries.len()));
+    }
+    if problems.is_empty() {
+        return Check::new(
+            NAME,
+            Status::Warn,
+            format!(
+                "{} parsed, ignoring {} line(s)",
+                entries.len(),
+                ignored.len()
+            ),
+        )
+        .details(ignored);
+    }
+    problems.push("Fix them with 'kraven edit <profile>'.".to_string());
+    Check::new(
+        NAME,
+        Status::Fail,
+        format!("{} of {} have errors", problems.len() - 1, entries.len()),
+    )
+    .details(problems)
+}
+
+fn check_names(invalid: &[Entry]) -> Check {
+    const NAME: &str = "profile names";
+    if invalid.is_empty() {
+        return Check::new(NAME, Status::Pass, "all valid");
+    }
+    let mut details: Vec<String> = invalid.iter().map(|entry| entry.label.clone()).collect();
+    details.push("Rename them, or list them in a .kravenignore file.".to_string());
+    Check::new(
+        NAME,
+        Status::Warn,
+        format!("{} file(s) can't be used as profiles", invalid.len()),
+    )
+    .details(details)
+}
+
+fn check_duplicates(entries: &[Entry]) -> Check {
+    const NAME: &str = "duplicate keys";
+    let mut duplicates = Vec::new();
+    for entry in entries {
+        let Ok(content) = fs::read_to_string(&entry.path) else {
+            continue;
+        };
+        for (key, lines) in profile::duplicate_keys(&content) {
+            let lines: Vec<String> = lines.iter().map(ToString::to_string).collect();
+            duplicates.push(format!(
+                "{}: {key} on lines {}",
+                entry.label,
+                lines.join(", ")
+            ));
+        }
+    }
+    if duplicates.is_empty() {
+        return Check::new(NAME, Status::Pass, "none");
+    }
+    Check::new(
+        NAME,
+        Status::Warn,
+        format!(
+            "{} variable(s) set more than once, the last value is used",
+            duplicates.len()
+        ),
+    )
+    .details(duplicates)
+}
+
+fn check_executable(name: &'static str, program: &str) -> Check {
+    match find_executable(program, env::var_os("PATH").as_deref()) {
+        Some(path) => Check::new(name, Status::Pass, path.display().to_string()),
+        None => Check::new(
+            name,
+            Status::Fail,
+            format!("'{program}' is not an executable"),
+        ),
+    }
+}
+
+fn check_editor(editor: &str) -> Check {
+    const NAME: &str = "editor";
+    match shell_words::split(editor) {
+        Ok(words) if !words.is_empty() => check_executable(NAME, &words[0]),
+        _ => Check::new(
+            NAME,
+            Status::Fail,
+            format!("'{editor}' is not a valid command"),
+        ),
+    }
+}
+
+/// Finds `program` the way a shell would, in the directories of `path` unless it
+/// has a `/`.
+fn find_executable(program: &str, path: Option<&OsStr>) -> Option<PathBuf> {
+    if program.contains('/') {
+        let program = PathBuf::from(program);
+        return is_executable(&program).then_some(program);
+    }
+    env::split_paths(path?)
+        .map(|dir| dir.join(program))
+        .find(|candidate| is_executable(candidate))
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    fs::metadata(path).is_ok_and(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+fn check_completions(login_shell: Option<&str>, home: Option<&Path>) -> Check {
+    const NAME: &str = "completions";
+    let (Some(program), Some(home)) = (login_shell, home) else {
+        return Check::new(
+            NAME,
+            Status::Warn,
+            "$SHELL or the home directory is unknown",
+        );
+    };
+    let Some(rc_file) = rc_file(Shell::from_program(program), home) else {
+        return Check::new(NAME, Status::Pass, format!("not checked for {program}"));
+    };
+
+    let installed = fs::read_to_string(&rc_file).is_ok_and(|content| {
+        content.lines().any(|line| {
+            !line.trim_start().starts_with('#')
+                && line.contains("COMPLETE=")
+                && line.contains("kraven")
+        })
+    });
+    if installed {
+        Check::new(
+            NAME,
+            Status::Pass,
+            format!("set up in {}", rc_file.display()),
+        )
+    } else {
+        Check::new(
+            NAME,
+            Status::Warn,
+            format!(
+                "not set up in {}, see 'kraven completions'",
+                rc_file.display()
+            ),
+        )
+    }
+}
+
+/// The rc file where completions are set up for `shell`, as `kraven completions` shows.
+fn rc_file(shell: Shell, home: &Path) -> Option<PathBuf> {
+    match shell {
+        Shell::Bash => Some(home.join(".bashrc")),
+        Shell::Zsh => Some(
+            env::var_os("ZDOTDIR")
+                .map_or_else(|| home.to_path_buf(), PathBuf::from)
+                .join(".zshrc"),
+        ),
+        Shell::Fish => Some(home.join(".config/fish/config.fish")),
+        _ => None,
+    }
+}
+
+fn check_session() -> Check {
+    const NAME: &str = "session";
+    let Ok(active) = env::var(KRAVEN_ACTIVE) else {
+        return Check::new(NAME, Status::Pass, "no session active");
+    };
+    let expired = session::current_deadline().is_some_and(|deadline| deadline <= SystemTime::now());
+    let cleanup = format!(
+        "Clear it with: unset {KRAVEN_ACTIVE} {KRAVEN_STACK} {KRAVEN_EXPIRES_AT} {KRAVEN_PID}"
+    );
+
+    match session::supervisor_alive() {
+        Some(true) => Check::new(NAME, Status::Pass, format!("in the '{active}' session")),
+        Some(false) => Check::new(
+            NAME,
+            Status::Fail,
+            format!("{KRAVEN_ACTIVE}={active} is left over from a session that has ended"),
+        )
+        .details(vec![cleanup]),
+        None if expired => Check::new(
+            NAME,
+            Status::Fail,
+            format!("{KRAVEN_ACTIVE}={active} is left over from a session that has expired"),
+        )
+        .details(vec![cleanup]),
+        None => Check::new(
+            NAME,
+            Status::Warn,
+            format!("can't tell whether the '{active}' session is still running"),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_find_executable() {
+        let path = OsStr::new("/nonexistent:/bin:/usr/bin");
+        assert!(find_executable("sh", Some(path)).is_some());
+        assert_eq!(find_executable("kraven-surely-missing", Some(path)), None);
+        assert_eq!(find_executable("sh", None), None);
+        assert_eq!(
+            find_executable("/bin/sh", None),
+            Some(PathBuf::from("/bin/sh"))
+        );
+        // Not executable
+        assert_eq!(find_executable("/etc/passwd", Some(path)), None);
+    }
+
+    #[test]
+    fn test_completions() {
+        let home = TempDir::new("doctor");
+        let status = |shell| check_completions(Some(shell), Some(&home)).status;
+
+        assert_eq!(status("/bin/bash"), Status::Warn);
+        fs::write(
+            home.join(".bashrc"),
+            "# source <(COMPLETE=bash kraven)\nalias k=kraven\n",
+        )
+        .unwrap();
+        assert_eq!(status("/bin/bash"), Status::Warn);
+        fs::write(home.join(".bashrc"), "source <(COMPLETE=bash kraven)\n").unwrap();
+        assert_eq!(status("/bin/bash"), Status::Pass);
+
+        assert_eq!(status("/bin/sh"), Status::Pass);
+        assert_eq!(check_completions(None, Some(&home)).status, Status::Warn);
+    }
+
+    #[test]
+    fn test_invalid_editor() {
+        assert_eq!(check_editor("'unbalanced").status, Status::Fail);
+        assert_eq!(check_editor("").status, Status::Fail);
+        assert_eq!(
+            check_editor("kraven-surely-missing --wait").status,
+            Status::Fail
+        );
+    }
+}
@@ -1,43 +1,98 @@
 use anyhow::{bail, Context, Result};
-use std::process::Command;
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
 
-use crate::config::{ensure_profile_dir_exists, get_profile_path};
+use kraven::diagnostic;
+
+use crate::audit::{self, Event, Record};
+use crate::config::{
+    ensure_profile_dir_exists, prune_empty_namespaces, resolve_profile, DEFAULT_TEMPLATE,
+};
+use crate::digest;
+use crate::editor;
+use crate::exit::KravenError;
+use crate::interact::{Prompter, Terminal};
 use crate::profile::Profile;
+use crate::settings::Settings;
+use crate::store;
+
+/// Suffix of the copy of a profile being edited, next to the profile.
+const DRAFT_SUFFIX: &str = ".kraven-edit";
+
+/// Content of new profiles, unless the store has a `default-template` file.
+///
+/// Everything is commented out, so the scaffold itself is an empty profile.
+const SCAFFOLD: &str = r#"# New kraven profile. Lines starting with '#' are comments.
+# Quit without changing this text to cancel.
+#
+# Variables are KEY=value, one per line:
+#   AWS_REGION=eu-west-1
+#   GREETING="hello world"        double quotes allow the escapes \" \\ \n \t
+#   PATTERN='literal $value'      single quotes keep the text as is
+# Names use letters, digits and underscores, and don't start with a digit.
+#
+# Directives start with '#@' (remove the '#   ' in front of these to use them):
+#   #@ttl: 30m                    close activated sessions after this long
+#   #@protected                   type the profile name before each use
+#   #@secret: API_TOKEN, DB_PASS  values masked by `kraven show --mask=auto`
+#   #@pre-activate: aws sso login run before activation (#@pre-activate?: ignores failures)
+#   #@post-deactivate: echo bye   run after the session or command ends
+"#;
 
 pub fn run(profile_name: &str) -> Result<()> {
     // Ensure profile directory exists
     ensure_profile_dir_exists()?;
 
-    let profile_path = get_profile_path(profile_name)?;
-
-    // Get editor from environment
-    let editor = std::env::var("EDITOR")
-        .or_else(|_| std::env::var("VISUAL"))
-        .unwrap_or_else(|_| "vi".to_string());
-
-    // Split editor command to handle editors with arguments (e.g., "emacsclient -nw")
-    let mut parts = editor.split_whitespace();
-    let program = parts.next().unwrap_or("vi");
-    let editor_args: Vec<&str> = parts.collect();
+    let profile = resolve_profile(profile_name)?;
+    let profile_path = &profile.path;
 
-    let status = Command::new(program)
-        .args(&editor_args)
-        .arg(&profile_path)
-        .status()
-        .with_context(|| format!("Failed to launch editor: {editor}"))?;
-
-    if !status.success() {
-        bail!("Editor exited with non-zero status");
+    // Namespaced profiles live in subdirectories that may not exist yet
+    if let Some(parent) = profile_path.parent() {
+        fs::create_dir_all(parent).with_context(|| {
+            format!("Failed to create namespace directory: {}", parent.display())
+        })?;
     }
 
-    if !profile_path.exists() {
+    let original = fs::read_to_string(profile_path).ok();
+    let original_hash = original
+        .as_ref()
+        .map(|content| digest::hash(content.as_bytes()));
+    let seed = match &original {
+        Some(content) => content.clone(),
+        None => scaffold(&profile.root)?,
+    };
+
+    // Edit a copy, so the profile only changes once it is valid or the user says so
+    let draft = Draft::create(profile_path, &seed)?;
+    let Some(content) = edit_until_valid(
+        &draft,
+        profile_name,
+        profile_path,
+        &mut launch_editor,
+        &mut Terminal,
+    )?
+    else {
+        drop(draft);
+        prune_empty_namespaces(&profile);
+        println!("Changes to profile '{profile_name}' were discarded.");
+        return Ok(());
+    };
+    if original.is_none() && (content.trim().is_empty() || content == seed) {
+        drop(draft);
+        prune_empty_namespaces(&profile);
         println!("Profile '{profile_name}' was not created (no content saved).");
         return Ok(());
     }
+    draft.save(&content)?;
 
-    // Validate the profile after edit
-    if let Err(e) = Profile::load(profile_name, &profile_path) {
-        eprintln!("Warning: Profile '{profile_name}' has errors:\n{e}");
+    let audit_record = Record::new(Event::Edit, &profile.reference(), profile_path);
+    if audit_record.hash != original_hash {
+        audit::log(&audit_record);
+        store::commit(&profile.root, &format!("Edit profile '{}'", profile.name));
+    }
+
+    if Profile::check(&content).is_err() {
         eprintln!("The file was saved, but you may want to fix these issues.");
     } else {
         println!("Profile '{profile_name}' saved.");
@@ -45,3 +100,298 @@ pub fn run(profile_name: &str) -> Result<()> {
 
     Ok(())
 }
+
+/// Returns the initial content of a new profile in the store at `root`.
+fn scaffold(root: &Path) -> Result<String> {
+    let path = root.join(DEFAULT_TEMPLATE);
+    match fs::read_to_string(&path) {
+        Ok(content) => Ok(content),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(SCAFFOLD.to_string()),
+        Err(err) => Err(err).with_context(|| format!("Failed to read {}", path.display())),
+    }
+}
+
+/// Opens the draft in the editor until it holds a valid profile, or the user
+/// gives up on fixing it.
+///
+/// Returns the content to save, or `None` to discard the changes. Each time
+/// the profile is invalid, the errors are added to the draft as `# kraven error:`
+/// lines, which are removed again when it is saved.
+fn edit_until_valid(
+    draft: &Draft,
+    profile_name: &str,
+    profile_path: &Path,
+    editor: &mut dyn FnMut(&Path) -> Result<()>,
+    prompter: &mut dyn Prompter,
+) -> Result<Option<String>> {
+    loop {
+        editor(&draft.path)?;
+        let content = diagnostic::strip_annotations(&draft.read()?);
+        let Err(diagnostics) = Profile::check(&content) else {
+            return Ok(Some(content));
+        };
+
+        eprintln!(
+            "Warning: Profile '{profile_name}' has {} error(s):\n",
+            diagnostics.len()
+        );
+        eprintln!(
+            "{}",
+            diagnostic::render(&diagnostics, &content, profile_path)
+        );
+        // Without a user to fix them, keep the old behaviour of saving anyway
+        if !prompter.is_interactive() {
+            return Ok(Some(content));
+        }
+
+        loop {
+            let answer =
+                prompter.read_line("[e]dit again, [s]ave anyway, or [d]iscard changes? ")?;
+            match answer.trim().to_lowercase().as_str() {
+                "" | "e" | "edit" => {
+                    draft.write(&diagnostic::annotate(&diagnostics, &content))?;
+                    break;
+                }
+                "s" | "save" => return Ok(Some(content)),
+                "d" | "discard" => return Ok(None),
+                _ => {}
+            }
+        }
+    }
+}
+
+/// A copy of a profile being edited, removed when dropped.
+struct Draft {
+    path: PathBuf,
+    /// The file to replace: the profile, or the file it links to.
+    profile_path: PathBuf,
+}
+
+impl Draft {
+    /// Creates the draft next to the profile, hidden from `kraven list`.
+    ///
+    /// The draft also locks the profile: while it exists, another edit of the
+    /// profile is refused rather than overwriting it.
+    fn create(profile_path: &Path, content: &str) -> Result<Self> {
+        // A symlinked profile is saved to its target, keeping the link
+        let profile_path =
+            fs::canonicalize(profile_path).unwrap_or_else(|_| profile_path.to_path_buf());
+        let file_name = profile_path
+            .file_name()
+            .context("Profile path has no file name")?
+            .to_string_lossy();
+        let path = profile_path.with_file_name(format!(".{file_name}{DRAFT_SUFFIX}"));
+
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        match options.open(&path) {
+            Ok(_) => {}
+            Err(err) if err.kind() == ErrorKind::AlreadyExists => bail!(
+                "{} is already being edited.\n\
+                 If no other edit is running, its draft is left over: recover any changes \
+                 from {} and remove it.",
+                profile_path.display(),
+                path.display()
+            ),
+            Err(err) => {
+                return Err(err).with_context(|| format!("Failed to write {}", path.display()))
+            }
+        }
+
+        let draft = Self { path, profile_path };
+        draft.write(content)?;
+        Ok(draft)
+    }
+
+    fn read(&self) -> Result<String> {
+        fs::read_to_string(&self.path)
+            .with_context(|| format!("Failed to read {}", self.path.display()))
+    }
+
+    /// Replaces the content of the draft, readable by the user only.
+    fn write(&self, content: &str) -> Result<()> {
+        let mut options = OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        options
+            .open(&self.path)
+            .and_then(|mut file| {
+                file.write_all(content.as_bytes())?;
+                file.sync_all()
+            })
+            .with_context(|| format!("Failed to write {}", self.path.display()))
+    }
+
+    /// Writes `content` to the profile at once, by renaming the draft over it.
+    /// An existing profile keeps its permissions.
+    fn save(self, content: &str) -> Result<()> {
+        self.write(content)?;
+        if let Ok(metadata) = fs::metadata(&self.profile_path) {
+            fs::set_permissions(&self.path, metadata.permissions())
+                .with_context(|| format!("Failed to write {}", self.path.display()))?;
+        }
+        fs::rename(&self.path, &self.profile_path)
+            .with_context(|| format!("Failed to write {}", self.profile_path.display()))
+    }
+}
+
+impl Drop for Draft {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Opens `path` in the user's editor, waiting for it to exit.
+pub fn launch_editor(path: &Path) -> Result<()> {
+    let editor = Settings::load()?.editor();
+
+    // Editors may come with arguments and quoted paths (e.g. "emacsclient -nw")
+    let mut cmd = editor::command(&editor, path).map_err(|err| KravenError::EditorFailed {
+        editor: editor.clone(),
+        reason: format!("is not a valid command: {err:#}"),
+    })?;
+    let status = cmd.status().map_err(|err| KravenError::EditorFailed {
+        editor: editor.clone(),
+        reason: format!("could not be launched: {err}"),
+    })?;
+
+    if !status.success() {
+        bail!(KravenError::EditorFailed {
+            editor,
+            reason: format!("exited with {status}"),
+        });
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+    use std::collections::VecDeque;
+
+    /// Answers questions from a list, as a user at a terminal.
+    struct Answers(VecDeque<&'static str>);
+
+    impl Prompter for Answers {
+        fn read_line(&mut self, _prompt: &str) -> Result<String> {
+            self.0
+                .pop_front()
+                .map(ToString::to_string)
+                .context("No answer left")
+        }
+
+        fn read_secret(&mut self, prompt: &str) -> Result<String> {
+            self.read_line(prompt)
+        }
+
+        fn is_interactive(&self) -> bool {
+            true
+        }
+    }
+
+    /// Edits a draft of `dev` in a temporary directory, with `edits` applied in
+    /// turn by the editor.
+    fn edit(name: &str, edits: &[fn(&str) -> String], answers: &[&'static str]) -> Option<String> {
+        let dir = TempDir::new(&format!("edit-{name}"));
+        let profile_path = dir.join("dev");
+        let draft = Draft::create(&profile_path, "A=1\n").unwrap();
+
+        let mut edits = edits.iter();
+        let mut editor = |path: &Path| {
+            let content = fs::read_to_string(path)?;
+            let edit = edits.next().context("Editor opened too often")?;
+            fs::write(path, edit(&content))?;
+            Ok(())
+        };
+        let mut answers = Answers(answers.iter().copied().collect());
+        let result =
+            edit_until_valid(&draft, "dev", &profile_path, &mut editor, &mut answers).unwrap();
+        assert!(edits.next().is_none(), "editor opened too rarely");
+        result
+    }
+
+    #[test]
+    fn test_scaffold_is_an_empty_profile() {
+        let profile = Profile::check(SCAFFOLD).unwrap();
+        assert!(profile.vars.is_empty());
+        assert!(profile.meta.secrets.is_empty());
+        assert!(profile.meta.hooks.is_empty());
+    }
+
+    #[test]
+    fn test_reopens_with_annotations() {
+        let result = edit(
+            "reopen",
+            &[
+                |content| format!("{content}9X=2\n"),
+                |content| {
+                    assert!(content.contains("# kraven error: Invalid variable name '9X'"));
+                    content.replace("9X", "X")
+                },
+            ],
+            &["e"],
+        );
+        assert_eq!(result.as_deref(), Some("A=1\nX=2\n"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_save_replaces_the_linked_profile() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = TempDir::new("edit-save");
+        let target = dir.join("dev");
+        fs::write(&target, "A=1\n").unwrap();
+        fs::set_permissions(&target, fs::Permissions::from_mode(0o640)).unwrap();
+        std::os::unix::fs::symlink(&target, dir.join("link")).unwrap();
+
+        Draft::create(&dir.join("link"), "A=1\n")
+            .unwrap()
+            .save("A=2\n")
+            .unwrap();
+        assert!(fs::symlink_metadata(dir.join("link"))
+            .unwrap()
+            .file_type()
+            .is_symlink());
+        assert_eq!(fs::read_to_string(&target).unwrap(), "A=2\n");
+        let mode = fs::metadata(&target).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o640);
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+    }
+
+    #[test]
+    fn test_second_edit_is_refused() {
+        let dir = TempDir::new("edit-twice");
+        let profile_path = dir.join("dev");
+        let draft = Draft::create(&profile_path, "A=1\n").unwrap();
+        draft.write("A=2\n").unwrap();
+
+        let err = Draft::create(&profile_path, "A=1\n").err().unwrap();
+        assert!(err.to_string().contains("is already being edited"), "{err}");
+        assert_eq!(draft.read().unwrap(), "A=2\n");
+
+        drop(draft);
+        assert!(Draft::create(&profile_path, "A=1\n").is_ok());
+    }
+
+    #[test]
+    fn test_save_or_discard_invalid() {
+        let broken: fn(&str) -> String = |content| format!("{content}BROKEN\n");
+        assert_eq!(
+            edit("save", &[broken], &["s"]).as_deref(),
+            Some("A=1\nBROKEN\n")
+        );
+        assert_eq!(edit("discard", &[broken], &["?", "d"]), None);
+    }
+}
@@ -0,0 +1,101 @@
+use anyhow::{Context, Result};
+use std::io;
+use std::process::{Child, Command, ExitStatus, Stdio};
+
+use kraven::Activation;
+
+use crate::audit::{self, Event, Record};
+use crate::commands::{allowed_hooks, load_profile};
+use crate::config::resolve_profile;
+use crate::hooks::Stage;
+use crate::interact::{self, Terminal};
+use crate::redact::{Redactor, Stream};
+
+/// Options of `kraven exec`.
+#[derive(Debug, Default)]
+pub struct Options {
+    pub yes: bool,
+    pub no_hooks: bool,
+    pub redact: bool,
+}
+
+/// Run a single command with the profile's variables, exiting with its status.
+pub fn run(profile_name: &str, command: &[String], options: &Options) -> Result<()> {
+    let Options {
+        yes,
+        no_hooks,
+        redact,
+    } = *options;
+    let resolved = resolve_profile(profile_name)?;
+    let profile = load_profile(profile_name, &resolved.path)?;
+    interact::confirm_protected(&mut Terminal, profile_name, profile.meta.protected, yes)?;
+
+    let (program, args) = command.split_first().context("No command given")?;
+    let activation = Activation::new(profile_name, &profile);
+    let profile_env = |cmd: &mut Command| activation.apply(cmd);
+
+    let hooks = (!no_hooks)
+        .then(|| allowed_hooks(&resolved, &profile))
+        .transpose()?;
+    if let Some(hooks) = &hooks {
+        hooks.run(Stage::PreActivate, &profile_env)?;
+    }
+
+    let mut cmd = activation.command(program);
+    cmd.args(args);
+    if redact {
+        cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
+    }
+    let mut child = cmd
+        .spawn()
+        .with_context(|| format!("Failed to run command: {program}"))?;
+    let mut audit_record = Record::new(Event::Exec, &resolved.reference(), &resolved.path);
+    audit::log(&audit_record);
+    let status = if redact {
+        wait_redacted(&mut child, &Redactor::new(&profile.vars))
+    } else {
+        child.wait()
+    }
+    .context("Failed to wait for command")?;
+    audit_record.finish(status.code());
+    audit::log(&audit_record);
+
+    if let Some(hooks) = &hooks {
+        if let Err(err) = hooks.run(Stage::PostDeactivate, &profile_env) {
+            eprintln!("kraven: {err:#}");
+        }
+    }
+
+    std::process::exit(status.code().unwrap_or(1));
+}
+
+/// Waits for `child`, copying its piped output through `redactor`.
+fn wait_redacted(child: &mut Child, redactor: &Redactor) -> io::Result<ExitStatus> {
+    let stdout = child.stdout.take();
+    let stderr = child.stderr.take();
+    std::thread::scope(|scope| {
+        let stdout = stdout.map(|pipe| {
+            scope.spawn(move || {
+                Stream::new(redactor, io::stdout())
+                    .copy_from(pipe)
+                    .map(drop)
+            })
+        });
+        let stderr = stderr.map(|pipe| {
+            scope.spawn(move || {
+                Stream::new(redactor, io::stderr())
+                    .copy_from(pipe)
+                    .map(drop)
+            })
+        });
+        for copier in [stdout, stderr].into_iter().flatten() {
+            // A closed stdout (e.g. `| head`) must not hide the exit status
+            if let Err(err) = copier.join().expect("output thread panicked") {
+                if err.kind() != io::ErrorKind::BrokenPipe {
+                    eprintln!("kraven: failed to copy output: {err}");
+                }
+            }
+        }
+        child.wait()
+    })
+}
@@ -0,0 +1,27 @@
+use anyhow::Result;
+
+use crate::commands::load_profile;
+use crate::config::get_profile_path;
+use crate::settings::Settings;
+use crate::shell::Shell;
+
+/// Print statements that export (or unset) the profile's variables in the current shell.
+pub fn run(profile_name: &str, shell: Option<Shell>, unset: bool) -> Result<()> {
+    let profile_path = get_profile_path(profile_name)?;
+    let profile = load_profile(profile_name, &profile_path)?;
+
+    let shell = match shell {
+        Some(shell) => shell,
+        None => Shell::from_program(&Settings::load()?.shell(None)),
+    };
+
+    for (key, value) in &profile.vars {
+        if unset {
+            println!("{}", shell.unset(key));
+        } else {
+            println!("{}", shell.export(key, value));
+        }
+    }
+
+    Ok(())
+}
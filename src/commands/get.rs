@@ -0,0 +1,28 @@
+use anyhow::{bail, Result};
+
+use crate::commands::load_profile;
+use crate::config::get_profile_path;
+
+/// Print the raw values of variables, for use in scripts.
+///
+/// Values are printed as they are, without quoting, joined by `separator`.
+pub fn run(
+    profile_name: &str,
+    keys: &[String],
+    default: Option<&str>,
+    separator: &str,
+) -> Result<()> {
+    let profile_path = get_profile_path(profile_name)?;
+    let profile = load_profile(profile_name, &profile_path)?;
+
+    let mut values = Vec::with_capacity(keys.len());
+    for key in keys {
+        match profile.vars.get(key).map(String::as_str).or(default) {
+            Some(value) => values.push(value),
+            None => bail!("Key '{key}' not found in profile '{profile_name}'."),
+        }
+    }
+    println!("{}", values.join(separator));
+
+    Ok(())
+}
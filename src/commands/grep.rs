@@ -0,0 +1,62 @@
+use anyhow::{bail, Result};
+
+use crate::config::all_profiles;
+use crate::mask;
+use crate::pattern;
+use crate::profile::Profile;
+use crate::settings::Settings;
+
+/// Print the variables of all profiles whose key or value matches `pattern`,
+/// with the values masked. `keys` and `values` restrict the search to one of them.
+pub fn run(pattern: &str, keys: bool, values: bool) -> Result<()> {
+    let strategy = Settings::load()?.mask_strategy(None)?;
+    let mut found = false;
+
+    for profile in all_profiles()? {
+        let reference = profile.reference();
+        let parsed = match Profile::load(&profile.name, &profile.path) {
+            Ok(parsed) => parsed,
+            Err(err) => {
+                eprintln!("Warning: skipped profile '{reference}': {err:#}");
+                continue;
+            }
+        };
+
+        for (key, value) in &parsed.vars {
+            let hit_key = !values && matches(pattern, key);
+            let hit_value = !keys && matches(pattern, value);
+            if hit_key || hit_value {
+                found = true;
+                println!("{reference}: {key}={}", mask::mask(value, strategy));
+            }
+        }
+    }
+
+    if !found {
+        bail!("No variable matches '{pattern}'.");
+    }
+    Ok(())
+}
+
+/// Globs match the whole text, other patterns any part of it.
+fn matches(pattern: &str, text: &str) -> bool {
+    if pattern::is_glob(pattern) {
+        pattern::matches(pattern, text)
+    } else {
+        text.contains(pattern)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches() {
+        assert!(matches("TOKEN", "GITHUB_TOKEN"));
+        assert!(matches("*_TOKEN", "GITHUB_TOKEN"));
+        assert!(!matches("TOKEN_*", "GITHUB_TOKEN"));
+        assert!(matches("example.com", "https://example.com/api"));
+        assert!(!matches("token", "GITHUB_TOKEN"));
+    }
+}
@@ -1,55 +1,170 @@
-use anyhow::{Context, Result};
-use std::fs;
+use anyhow::{bail, Result};
 
-use crate::config::{get_profile_dir, KRAVEN_ACTIVE};
+use crate::config::{
+    discover_profiles, list_profiles, profile_stores, resolve_profile, ProfileSource,
+    KRAVEN_ACTIVE, NAMESPACE_SEPARATOR,
+};
 
-pub fn run() -> Result<()> {
-    let profile_dir = get_profile_dir()?;
+pub fn run(namespace: Option<&str>) -> Result<()> {
+    let stores = profile_stores()?;
 
-    if !profile_dir.exists() {
+    if !stores.iter().any(|store| store.dir.exists()) {
         println!("No profiles found. Profile directory does not exist yet.");
         println!("Use 'kraven edit <name>' to create your first profile.");
         return Ok(());
     }
 
-    let mut profiles: Vec<String> = fs::read_dir(&profile_dir)
-        .with_context(|| {
-            format!(
-                "Failed to read profile directory: {}",
-                profile_dir.display()
-            )
-        })?
-        .filter_map(|entry| {
-            let path = entry.ok()?.path();
-            if !path.is_file() {
-                return None;
+    // Check which profile is currently active
+    let active = std::env::var(KRAVEN_ACTIVE)
+        .ok()
+        .and_then(|reference| resolve_profile(&reference).ok());
+
+    // Only label the sources when a project directory contributes profiles
+    let show_sources = stores.len() > 1;
+    let global_profiles = list_profiles(&stores[0].dir)?;
+    let prefix =
+        namespace.map(|namespace| format!("{}/", namespace.trim_end_matches(NAMESPACE_SEPARATOR)));
+    let in_namespace = |name: &str| {
+        prefix
+            .as_ref()
+            .is_none_or(|prefix| name.starts_with(prefix))
+    };
+    let mut found = false;
+    let mut invalid = Vec::new();
+
+    for store in &stores {
+        let discovery = discover_profiles(&store.dir)?;
+        let mut profiles = discovery.profiles;
+        profiles.retain(|name| in_namespace(name));
+        for entry in discovery.invalid {
+            if in_namespace(&entry.name) {
+                let path = store.dir.join(&entry.name);
+                invalid.push(format!("{}: {}", path.display(), entry.reason));
             }
-            let name = path.file_name()?.to_str()?;
-            if name.starts_with('.') {
-                return None;
+        }
+
+        if profiles.is_empty() {
+            continue;
+        }
+        found = true;
+
+        let label = |name: &str| {
+            let is_active = active
+                .as_ref()
+                .is_some_and(|active| active.source == store.source && active.name == name);
+            let is_shadowed = store.source == ProfileSource::Project
+                && global_profiles.iter().any(|global| global == name);
+            match (is_active, is_shadowed) {
+                (true, _) => Some("active".to_string()),
+                (false, true) => Some(format!("shadowed, use project:{name}")),
+                (false, false) => None,
             }
-            Some(name.to_string())
-        })
-        .collect();
+        };
 
-    if profiles.is_empty() {
+        if show_sources {
+            println!("{} ({}):", store.source.qualifier(), store.dir.display());
+            for line in render_tree(&profiles, label) {
+                println!("  {line}");
+            }
+        } else {
+            for line in render_tree(&profiles, label) {
+                println!("{line}");
+            }
+        }
+    }
+
+    if !invalid.is_empty() {
+        eprintln!("\nSkipped files that are not valid profile names:");
+        for entry in &invalid {
+            eprintln!("  {entry}");
+        }
+        eprintln!("Rename them, or list them in a .kravenignore file to hide them.");
+    }
+
+    if !found {
+        if let Some(namespace) = namespace {
+            bail!("No profiles found in namespace '{namespace}'.");
+        }
         println!("No profiles found.");
         println!("Use 'kraven edit <name>' to create your first profile.");
-        return Ok(());
     }
 
-    profiles.sort();
+    Ok(())
+}
 
-    // Check which profile is currently active
-    let active = std::env::var(KRAVEN_ACTIVE).ok();
+/// Renders sorted profile names as an indented tree, one line per namespace or profile.
+///
+/// `label` returns an optional annotation shown in parentheses after a profile.
+fn render_tree(profiles: &[String], label: impl Fn(&str) -> Option<String>) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut previous: Vec<&str> = Vec::new();
 
     for profile in profiles {
-        if Some(&profile) == active.as_ref() {
-            println!("{profile} (active)");
+        let segments: Vec<&str> = profile.split(NAMESPACE_SEPARATOR).collect();
+        let (leaf, namespaces) = segments.split_last().expect("split yields a segment");
+
+        // Print only the namespaces not already opened by the previous profile
+        let shared = namespaces
+            .iter()
+            .zip(&previous)
+            .take_while(|(a, b)| a == b)
+            .count();
+        for (depth, namespace) in namespaces.iter().enumerate().skip(shared) {
+            lines.push(format!("{}{namespace}/", "  ".repeat(depth)));
+        }
+
+        let indent = "  ".repeat(namespaces.len());
+        if let Some(label) = label(profile) {
+            lines.push(format!("{indent}{leaf} ({label})"));
         } else {
-            println!("{profile}");
+            lines.push(format!("{indent}{leaf}"));
         }
+
+        previous = namespaces.to_vec();
     }
 
-    Ok(())
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn test_render_flat() {
+        let lines = render_tree(&names(&["dev", "prod"]), |name| {
+            (name == "prod").then(|| "active".to_string())
+        });
+        assert_eq!(lines, ["dev", "prod (active)"]);
+    }
+
+    #[test]
+    fn test_render_namespaces() {
+        let profiles = names(&[
+            "dev",
+            "work/aws/prod",
+            "work/aws/staging",
+            "work/gcp",
+            "zeta",
+        ]);
+        let lines = render_tree(&profiles, |name| {
+            (name == "work/gcp").then(|| "active".to_string())
+        });
+        assert_eq!(
+            lines,
+            [
+                "dev",
+                "work/",
+                "  aws/",
+                "    prod",
+                "    staging",
+                "  gcp (active)",
+                "zeta",
+            ]
+        );
+    }
 }
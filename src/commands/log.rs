@@ -0,0 +1,37 @@
+use anyhow::Result;
+
+use crate::audit::{self, format_record};
+use crate::config::{get_audit_log_path, split_qualifier, ProfileSource};
+
+/// Print audit records, oldest first.
+pub fn run(profile: Option<&str>, since: Option<&str>) -> Result<()> {
+    let since = since
+        .map(|since| audit::parse_since(since, audit::now()))
+        .transpose()?;
+
+    // Records name global profiles without their qualifier
+    let profile = profile.map(|profile| match split_qualifier(profile) {
+        (Some(ProfileSource::Global), name) => name,
+        _ => profile,
+    });
+
+    let records = audit::read(&get_audit_log_path()?)?;
+    let matching: Vec<_> = match profile {
+        Some(profile) => audit::history(&records, profile),
+        None => records.iter().collect(),
+    }
+    .into_iter()
+    .filter(|record| since.is_none_or(|since| record.start >= since))
+    .collect();
+
+    if matching.is_empty() {
+        println!("No audit records found.");
+        return Ok(());
+    }
+
+    for record in matching {
+        println!("{}", format_record(record));
+    }
+
+    Ok(())
+}
@@ -1,8 +1,65 @@
 pub mod activate;
+pub mod allow;
+pub mod bundle;
 pub mod completions;
+pub mod config;
+pub mod copy;
 pub mod current;
 pub mod deactivate;
+pub mod doctor;
 pub mod edit;
+pub mod exec;
+pub mod export;
+pub mod get;
+pub mod grep;
 pub mod list;
+pub mod log;
+pub mod new;
 pub mod remove;
+pub mod rename;
+pub mod replace_value;
 pub mod show;
+pub mod store;
+pub mod sync;
+
+use anyhow::{bail, Result};
+use std::path::Path;
+
+use crate::config::{ProfileSource, ResolvedProfile};
+use crate::hooks::Hooks;
+use crate::profile::Profile;
+use crate::trust;
+
+/// Loads a profile, warning about the lines of it that were ignored.
+pub fn load_profile(name: &str, path: &Path) -> kraven::Result<Profile> {
+    let profile = Profile::load(name, path)?;
+    for warning in &profile.warnings {
+        eprintln!(
+            "Warning: {}:{}: {}, ignored.",
+            path.display(),
+            warning.line,
+            warning.kind
+        );
+    }
+    Ok(profile)
+}
+
+/// The hooks of a profile, refusing to run those of a project store until the
+/// user has allowed them with `kraven allow`.
+///
+/// Project stores come with whatever repository is checked out, so their hooks
+/// are arbitrary code; global profiles are the user's own.
+pub fn allowed_hooks<'a>(resolved: &ResolvedProfile, profile: &'a Profile) -> Result<Hooks<'a>> {
+    let hooks = Hooks::new(&profile.meta.hooks, resolved.hooks_dir());
+    if resolved.source == ProfileSource::Project
+        && !hooks.is_empty()
+        && !trust::is_allowed(&resolved.path, &hooks)?
+    {
+        let reference = resolved.reference();
+        bail!(
+            "The hooks of '{reference}' come from a project and have not been allowed.\n\
+             Review and allow them with `kraven allow {reference}`, or pass --no-hooks to skip them."
+        );
+    }
+    Ok(hooks)
+}
@@ -0,0 +1,61 @@
+use anyhow::{bail, Context, Result};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+
+use crate::audit::{self, Event, Record};
+use crate::config::{ensure_profile_dir_exists, resolve_profile, resolve_template};
+use crate::interact::Terminal;
+use crate::profile::Profile;
+use crate::store;
+use crate::template;
+
+/// Create a profile from a template, asking for each of its placeholders.
+pub fn run(profile_name: &str, template_name: &str) -> Result<()> {
+    let profile = resolve_profile(profile_name)?;
+    if profile.path.exists() {
+        bail!("Profile '{profile_name}' already exists. Use 'kraven edit {profile_name}' to change it.");
+    }
+
+    let template_path = resolve_template(template_name)?;
+    let content = fs::read_to_string(&template_path)
+        .with_context(|| format!("Failed to read template '{template_name}'"))?;
+    let rendered = template::render(&content, &template_path, &mut Terminal)?;
+
+    // Catch template mistakes before anything is written
+    Profile::parse(&rendered, &profile.path)
+        .with_context(|| format!("Template '{template_name}' produced an invalid profile"))?;
+
+    ensure_profile_dir_exists()?;
+    if let Some(parent) = profile.path.parent() {
+        fs::create_dir_all(parent).with_context(|| {
+            format!("Failed to create namespace directory: {}", parent.display())
+        })?;
+    }
+
+    // The answers may include secrets, so keep the file private
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options
+        .open(&profile.path)
+        .and_then(|mut file| file.write_all(rendered.as_bytes()))
+        .with_context(|| format!("Failed to write profile '{profile_name}'"))?;
+
+    audit::log(&Record {
+        from: Some(format!("template:{template_name}")),
+        ..Record::new(Event::Create, &profile.reference(), &profile.path)
+    });
+
+    store::commit(
+        &profile.root,
+        &format!(
+            "Create profile '{}' from template '{template_name}'",
+            profile.name
+        ),
+    );
+
+    println!("Profile '{profile_name}' created from template '{template_name}'.");
+
+    Ok(())
+}
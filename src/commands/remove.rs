@@ -1,14 +1,20 @@
 use anyhow::{bail, Context, Result};
 use std::fs;
-use std::io::{self, Write};
 
-use crate::config::{get_profile_path, KRAVEN_ACTIVE};
+use crate::audit::{self, Event, Record};
+use crate::config::{prune_empty_namespaces, resolve_profile, KRAVEN_ACTIVE};
+use crate::exit::KravenError;
+use crate::interact::{self, Terminal};
+use crate::store;
 
 pub fn run(profile_name: &str, force: bool) -> Result<()> {
-    let profile_path = get_profile_path(profile_name)?;
+    let profile = resolve_profile(profile_name)?;
+    let profile_path = &profile.path;
 
-    if !profile_path.exists() {
-        bail!("Profile '{profile_name}' does not exist.");
+    if !profile_path.is_file() {
+        bail!(KravenError::NotFound {
+            name: profile_name.to_string(),
+        });
     }
 
     // Warn if removing the currently active profile
@@ -18,22 +24,17 @@ pub fn run(profile_name: &str, force: bool) -> Result<()> {
         }
     }
 
-    if !force {
-        print!("Remove profile '{profile_name}'? [y/N] ");
-        io::stdout().flush()?;
-
-        let mut input = String::new();
-        io::stdin().read_line(&mut input)?;
-
-        let input = input.trim().to_lowercase();
-        if input != "y" && input != "yes" {
-            println!("Aborted.");
-            return Ok(());
-        }
+    if !force && !interact::confirm(&mut Terminal, &format!("Remove profile '{profile_name}'?"))? {
+        println!("Aborted.");
+        return Ok(());
     }
 
-    fs::remove_file(&profile_path)
+    let audit_record = Record::new(Event::Remove, &profile.reference(), profile_path);
+    fs::remove_file(profile_path)
         .with_context(|| format!("Failed to remove profile '{profile_name}'"))?;
+    audit::log(&audit_record);
+    prune_empty_namespaces(&profile);
+    store::commit(&profile.root, &format!("Remove profile '{}'", profile.name));
 
     println!("Profile '{profile_name}' removed.");
 
@@ -0,0 +1,80 @@
+use anyhow::{bail, Context, Result};
+use std::fs;
+
+use crate::audit::{self, Event, Record};
+use crate::config::{
+    ensure_profile_dir_exists, prune_empty_namespaces, resolve_destination, resolve_profile,
+    ResolvedProfile, KRAVEN_ACTIVE,
+};
+use crate::exit::KravenError;
+use crate::files;
+use crate::hooks;
+use crate::store;
+
+pub fn run(profile_name: &str, destination_name: &str, force: bool) -> Result<()> {
+    let (source, destination) = resolve_pair(profile_name, destination_name, force)?;
+
+    // Warn if renaming the currently active profile
+    if let Ok(active) = std::env::var(KRAVEN_ACTIVE) {
+        if active == profile_name {
+            eprintln!(
+                "Warning: '{profile_name}' is the currently active profile. \
+                 The session keeps its old name."
+            );
+        }
+    }
+
+    files::move_file(&source.path, &destination.path)
+        .with_context(|| format!("Failed to rename profile '{profile_name}'"))?;
+    hooks::move_scripts(&source.hooks_dir(), &destination.hooks_dir())?;
+    prune_empty_namespaces(&source);
+
+    audit::log(&Record {
+        from: Some(source.reference()),
+        ..Record::new(Event::Rename, &destination.reference(), &destination.path)
+    });
+
+    let message = format!("Rename profile '{}' to '{}'", source.name, destination.name);
+    store::commit(&source.root, &message);
+    if destination.root != source.root {
+        store::commit(&destination.root, &message);
+    }
+
+    println!("Profile '{profile_name}' renamed to '{destination_name}'.");
+
+    Ok(())
+}
+
+/// Resolves the profiles of a rename or copy, checking that the destination can be written.
+pub fn resolve_pair(
+    profile_name: &str,
+    destination_name: &str,
+    force: bool,
+) -> Result<(ResolvedProfile, ResolvedProfile)> {
+    let source = resolve_profile(profile_name)?;
+    if !source.path.is_file() {
+        bail!(KravenError::NotFound {
+            name: profile_name.to_string(),
+        });
+    }
+
+    let destination = resolve_destination(&source, destination_name)?;
+    if destination.path == source.path {
+        bail!("'{profile_name}' and '{destination_name}' are the same profile.");
+    }
+    if destination.path.is_dir() {
+        bail!("'{destination_name}' is a namespace, not a profile.");
+    }
+    if destination.path.exists() && !force {
+        bail!("Profile '{destination_name}' already exists. Use --force to overwrite it.");
+    }
+
+    ensure_profile_dir_exists()?;
+    if let Some(parent) = destination.path.parent() {
+        fs::create_dir_all(parent).with_context(|| {
+            format!("Failed to create namespace directory: {}", parent.display())
+        })?;
+    }
+
+    Ok((source, destination))
+}
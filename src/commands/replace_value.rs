@@ -0,0 +1,284 @@
+use anyhow::{bail, Context, Result};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+use crate::audit::{self, Event, Record};
+use crate::config::all_profiles;
+use crate::interact::{self, Prompter, Terminal};
+use crate::mask;
+use crate::pattern;
+use crate::profile::{self, Profile};
+use crate::settings::Settings;
+use crate::store;
+
+/// Suffix of the new content of a profile, written next to it before the switch.
+const STAGED_SUFFIX: &str = ".kraven-replace";
+
+#[derive(clap::Args)]
+pub struct Options {
+    /// Read the new value from stdin instead of asking for it
+    #[arg(long)]
+    pub from_stdin: bool,
+
+    /// Profiles to change (comma-separated names or globs like 'team-*'), all by default
+    #[arg(long, value_delimiter = ',')]
+    pub profiles: Vec<String>,
+
+    /// Skip the confirmation
+    #[arg(short, long)]
+    pub yes: bool,
+}
+
+/// A profile whose value of the key changes.
+struct Change {
+    /// The profile name, qualified for project profiles.
+    reference: String,
+    name: String,
+    root: PathBuf,
+    path: PathBuf,
+    /// The file to replace: the profile, or the file it links to.
+    target: PathBuf,
+    old_value: String,
+    original: String,
+    updated: String,
+}
+
+/// Set `key` to a new value in every selected profile that sets it, all at once.
+pub fn run(key: &str, options: &Options) -> Result<()> {
+    let mut prompter = Terminal;
+    let value = if options.from_stdin {
+        read_stdin()?
+    } else if prompter.is_interactive() {
+        prompter.read_secret(&format!("New value of {key}: "))?
+    } else {
+        bail!("Pass --from-stdin to read the value from a pipe.");
+    };
+
+    let mut changes = Vec::new();
+    let mut up_to_date = 0;
+    let mut without_key = 0;
+    for profile in all_profiles()? {
+        let reference = profile.reference();
+        let selected = options.profiles.is_empty()
+            || options.profiles.iter().any(|glob| {
+                pattern::matches(glob, &profile.name) || pattern::matches(glob, &reference)
+            });
+        if !selected {
+            continue;
+        }
+
+        let original = fs::read_to_string(&profile.path)
+            .with_context(|| format!("Failed to read profile '{reference}'"))?;
+        let Ok(parsed) = Profile::check(&original) else {
+            eprintln!("Warning: skipped profile '{reference}', which has errors.");
+            continue;
+        };
+        match parsed.vars.get(key) {
+            None => without_key += 1,
+            Some(old_value) if *old_value == value => up_to_date += 1,
+            Some(old_value) => changes.push(Change {
+                // A symlinked profile is changed in its target, keeping the link
+                target: fs::canonicalize(&profile.path)
+                    .with_context(|| format!("Failed to resolve {}", profile.path.display()))?,
+                reference,
+                name: profile.name,
+                root: profile.root,
+                path: profile.path,
+                old_value: old_value.clone(),
+                updated: profile::replace_value(&original, key, &value),
+                original,
+            }),
+        }
+    }
+
+    if changes.is_empty() && up_to_date == 0 {
+        bail!("No selected profile sets {key}.");
+    }
+    if up_to_date > 0 || without_key > 0 {
+        println!(
+            "Skipping {up_to_date} profile(s) already up to date and {without_key} without {key}."
+        );
+    }
+    if changes.is_empty() {
+        println!("Nothing to change.");
+        return Ok(());
+    }
+
+    let strategy = Settings::load()?.mask_strategy(None)?;
+    println!("{key} changes in {} profile(s):", changes.len());
+    for change in &changes {
+        println!(
+            "  {}: {} -> {}",
+            change.reference,
+            mask::mask(&change.old_value, strategy),
+            mask::mask(&value, strategy)
+        );
+    }
+
+    if !options.yes {
+        if !prompter.is_interactive() {
+            bail!("Pass --yes to apply the changes non-interactively.");
+        }
+        if !interact::confirm(&mut prompter, "Apply the changes?")? {
+            println!("Aborted.");
+            return Ok(());
+        }
+    }
+
+    write_all(&changes)?;
+
+    let mut roots: Vec<(&Path, Vec<&str>)> = Vec::new();
+    for change in &changes {
+        audit::log(&Record::new(
+            Event::ReplaceValue,
+            &change.reference,
+            &change.path,
+        ));
+        match roots.iter_mut().find(|(root, _)| *root == change.root) {
+            Some((_, names)) => names.push(&change.name),
+            None => roots.push((&change.root, vec![&change.name])),
+        }
+    }
+    for (root, names) in roots {
+        store::commit(root, &format!("Replace {key} in {}", names.join(", ")));
+    }
+
+    println!("Updated {key} in {} profile(s).", changes.len());
+    Ok(())
+}
+
+/// Reads the value from stdin, without the final newline.
+fn read_stdin() -> Result<String> {
+    let mut value = String::new();
+    io::stdin()
+        .read_to_string(&mut value)
+        .context("Failed to read the value from stdin")?;
+    let value = value.strip_suffix('\n').unwrap_or(&value);
+    let value = value.strip_suffix('\r').unwrap_or(value);
+    if value.is_empty() {
+        bail!("No value on stdin.");
+    }
+    Ok(value.to_string())
+}
+
+/// Writes the updated content of every profile, or of none if any fails.
+///
+/// The new contents are written next to the profiles first, then renamed over
+/// them; a failed rename puts back the profiles already replaced.
+fn write_all(changes: &[Change]) -> Result<()> {
+    let mut staged = Vec::new();
+    for change in changes {
+        match stage(&change.target, &change.updated) {
+            Ok(path) => staged.push(path),
+            Err(err) => {
+                remove_all(&staged);
+                return Err(err);
+            }
+        }
+    }
+
+    for (i, (change, staged_path)) in changes.iter().zip(&staged).enumerate() {
+        if let Err(err) = fs::rename(staged_path, &change.target) {
+            for done in &changes[..i] {
+                let _ = fs::write(&done.target, &done.original);
+            }
+            remove_all(&staged[i..]);
+            return Err(err).with_context(|| {
+                format!(
+                    "Failed to write {}; no profile was changed",
+                    change.target.display()
+                )
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Writes `content` next to the profile at `path`, with the profile's permissions.
+fn stage(path: &Path, content: &str) -> Result<PathBuf> {
+    let file_name = path
+        .file_name()
+        .context("Profile path has no file name")?
+        .to_string_lossy();
+    let staged = path.with_file_name(format!(".{file_name}{STAGED_SUFFIX}"));
+
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let written = options.open(&staged).and_then(|mut file| {
+        file.write_all(content.as_bytes())?;
+        file.sync_all()?;
+        fs::set_permissions(&staged, fs::metadata(path)?.permissions())
+    });
+    if let Err(err) = written {
+        let _ = fs::remove_file(&staged);
+        return Err(err).with_context(|| format!("Failed to write {}", staged.display()));
+    }
+    Ok(staged)
+}
+
+fn remove_all(paths: &[PathBuf]) {
+    for path in paths {
+        let _ = fs::remove_file(path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    fn change(dir: &Path, name: &str) -> Change {
+        Change {
+            reference: name.to_string(),
+            name: name.to_string(),
+            root: dir.to_path_buf(),
+            path: dir.join(name),
+            target: dir.join(name),
+            old_value: "old".to_string(),
+            original: "TOKEN=old\n".to_string(),
+            updated: "TOKEN=new\n".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_write_all_or_nothing() {
+        let dir = TempDir::new("replace");
+        for name in ["a", "b"] {
+            fs::write(dir.join(name), "TOKEN=old\n").unwrap();
+        }
+
+        // The missing namespace of the last profile fails its staging
+        let changes = [change(&dir, "a"), change(&dir, "b"), change(&dir, "ns/c")];
+        assert!(write_all(&changes).is_err());
+        assert_eq!(fs::read_to_string(dir.join("a")).unwrap(), "TOKEN=old\n");
+        let mut entries: Vec<_> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        entries.sort();
+        assert_eq!(entries, ["a", "b"]);
+
+        write_all(&changes[..2]).unwrap();
+        assert_eq!(fs::read_to_string(dir.join("a")).unwrap(), "TOKEN=new\n");
+        assert_eq!(fs::read_to_string(dir.join("b")).unwrap(), "TOKEN=new\n");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_stage_keeps_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = TempDir::new("stage");
+        let path = dir.join("dev");
+        fs::write(&path, "A=1\n").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o640)).unwrap();
+
+        let staged = stage(&path, "A=2\n").unwrap();
+        assert_eq!(staged, dir.join(".dev.kraven-replace"));
+        let mode = fs::metadata(&staged).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o640);
+    }
+}
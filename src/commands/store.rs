@@ -0,0 +1,33 @@
+use anyhow::{bail, Result};
+
+use crate::config::ensure_profile_dir_exists;
+use crate::store::Repo;
+
+/// Create the profile directory, optionally versioning it with git.
+pub fn init(git: bool, remote: Option<&str>) -> Result<()> {
+    let dir = ensure_profile_dir_exists()?;
+    if !git {
+        println!("Profile directory: {}", dir.display());
+        return Ok(());
+    }
+
+    let repo = match Repo::open(&dir) {
+        Some(repo) if remote.is_some() => repo,
+        Some(_) => bail!(
+            "The profile directory is already a git repository: {}",
+            dir.display()
+        ),
+        None => {
+            let repo = Repo::init(&dir)?;
+            println!("Initialized a git repository in {}.", dir.display());
+            repo
+        }
+    };
+
+    if let Some(url) = remote {
+        repo.set_remote(url)?;
+        println!("Profiles sync with {url}. Run 'kraven sync' to pull and push them.");
+    }
+
+    Ok(())
+}
@@ -0,0 +1,222 @@
+use anyhow::{bail, Context, Result};
+use std::fs;
+use std::path::Path;
+
+use crate::commands::edit::launch_editor;
+use crate::config::get_profile_dir;
+use crate::interact::{Prompter, Terminal};
+use crate::store::{self, Repo, Side};
+
+/// Pull profile changes from the store's remote and push local ones to it.
+pub fn run(abort: bool) -> Result<()> {
+    let dir = get_profile_dir()?;
+    let Some(repo) = Repo::open(&dir) else {
+        bail!(
+            "The profile directory is not a git repository. \
+             Set it up with 'kraven store init --git --remote <url>'."
+        );
+    };
+
+    if abort {
+        if !repo.is_merging() {
+            bail!("No sync is in progress.");
+        }
+        repo.abort_merge()?;
+        println!("Sync aborted. Your profiles are as they were before it.");
+        return Ok(());
+    }
+
+    let Some(url) = repo.remote_url() else {
+        bail!(
+            "The profile store has no remote. \
+             Add one with 'kraven store init --git --remote <url>'."
+        );
+    };
+    let branch = repo.branch()?;
+
+    let resumed = repo.is_merging();
+    let mut merged = resumed;
+    if !resumed {
+        // Changes made outside of kraven are synced too
+        repo.commit_all("Update profiles")?;
+        repo.fetch()?;
+        if let Some(upstream) = repo.upstream(&branch)? {
+            if repo.count_between("HEAD", &upstream)? > 0 {
+                merged = true;
+                repo.merge(&upstream)?;
+            }
+        }
+    }
+
+    if repo.is_merging() {
+        resolve_conflicts(&repo, &mut Terminal, resumed)?;
+        repo.finish_merge()?;
+    }
+
+    if merged {
+        // Git doesn't keep permissions, so files it brings in are made private
+        let changed = repo.changed_files("ORIG_HEAD", "HEAD")?;
+        for path in &changed {
+            restrict_permissions(&repo.dir().join(path))?;
+        }
+        for path in &changed {
+            println!("  updated  {}", describe(path));
+        }
+    }
+
+    let outgoing = match repo.upstream(&branch)? {
+        Some(upstream) => repo.count_between(&upstream, "HEAD")?,
+        None => 1,
+    };
+    if outgoing > 0 {
+        repo.push(&branch)?;
+    }
+
+    println!("Profiles are in sync with {url}.");
+    Ok(())
+}
+
+/// Walks the user through the conflicting files of a merge.
+///
+/// Files edited by hand since the sync stopped count as resolved once their
+/// conflict markers are gone. Fails if any conflict is left.
+fn resolve_conflicts(repo: &Repo, prompter: &mut dyn Prompter, resumed: bool) -> Result<()> {
+    if resumed {
+        for path in repo.conflicts()? {
+            let content = fs::read_to_string(repo.dir().join(&path)).unwrap_or_default();
+            if !store::has_conflict_markers(&content) {
+                repo.resolve(&path)?;
+            }
+        }
+    }
+
+    if prompter.is_interactive() {
+        for path in repo.conflicts()? {
+            resolve_interactively(repo, prompter, &path)?;
+        }
+    }
+
+    let remaining = repo.conflicts()?;
+    if !remaining.is_empty() {
+        let mut message =
+            String::from("Sync stopped on changes made both here and on the remote to:");
+        for path in &remaining {
+            message.push_str("\n  ");
+            message.push_str(&describe(path));
+        }
+        message.push_str(
+            "\nEdit them to keep the right values and remove the conflict markers \
+             (e.g. with 'kraven edit'), then run 'kraven sync' again. \
+             Run 'kraven sync --abort' to undo the sync.",
+        );
+        bail!(message);
+    }
+
+    Ok(())
+}
+
+fn resolve_interactively(repo: &Repo, prompter: &mut dyn Prompter, path: &str) -> Result<()> {
+    let file = repo.dir().join(path);
+    eprintln!("\nConflict in {}", describe(path));
+    let content = fs::read_to_string(&file).unwrap_or_default();
+    let keys = conflicting_keys(&content);
+    if keys.is_empty() {
+        eprintln!("  It was deleted on one side and changed on the other.");
+    } else {
+        eprintln!("  Changed on both sides: {}", keys.join(", "));
+    }
+
+    loop {
+        let answer = prompter.read_line("Keep [l]ocal, keep [r]emote, [e]dit, or [s]kip? ")?;
+        match answer.trim().to_lowercase().as_str() {
+            "l" | "local" => return repo.take(path, Side::Local),
+            "r" | "remote" => return repo.take(path, Side::Remote),
+            "e" | "edit" => {
+                launch_editor(&file)?;
+                let content = fs::read_to_string(&file)
+                    .with_context(|| format!("Failed to read {}", file.display()))?;
+                if store::has_conflict_markers(&content) {
+                    eprintln!("The conflict markers are still there.");
+                } else {
+                    return repo.resolve(path);
+                }
+            }
+            "s" | "skip" => return Ok(()),
+            _ => {}
+        }
+    }
+}
+
+/// Names of the variables inside the conflict markers of a profile.
+fn conflicting_keys(content: &str) -> Vec<String> {
+    let mut keys: Vec<String> = Vec::new();
+    let mut in_conflict = false;
+    for line in content.lines() {
+        if line.starts_with("<<<<<<< ") {
+            in_conflict = true;
+        } else if line.starts_with(">>>>>>> ") {
+            in_conflict = false;
+        } else if in_conflict {
+            let line = line.trim();
+            let line = line.strip_prefix("export ").unwrap_or(line);
+            if let Some((key, _)) = line.split_once('=') {
+                let key = key.trim();
+                if !key.is_empty() && !line.starts_with('#') && !keys.iter().any(|k| k == key) {
+                    keys.push(key.to_string());
+                }
+            }
+        }
+    }
+    keys
+}
+
+/// Describes a file of the store by what it is to kraven.
+fn describe(path: &str) -> String {
+    match path.strip_prefix("hooks/") {
+        Some(hook) => format!("hook {hook}"),
+        None if path.starts_with('.') || path == "templates" || path.starts_with("templates/") => {
+            path.to_string()
+        }
+        None => format!("profile '{path}'"),
+    }
+}
+
+/// Removes the permissions of the group and other users from `path`, if it exists.
+#[cfg(unix)]
+pub fn restrict_permissions(path: &Path) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    let Ok(meta) = fs::metadata(path) else {
+        return Ok(());
+    };
+    let mode = meta.permissions().mode();
+    if mode & 0o077 != 0 {
+        fs::set_permissions(path, fs::Permissions::from_mode(mode & 0o700))
+            .with_context(|| format!("Failed to set permissions of {}", path.display()))?;
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn restrict_permissions(_path: &Path) -> Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_conflicting_keys() {
+        let content = "A=1\n<<<<<<< HEAD\nB=2\nexport C=3\n=======\nB=4\n# note=x\n>>>>>>> origin/main\nD=5\n";
+        assert_eq!(conflicting_keys(content), ["B", "C"]);
+        assert!(conflicting_keys("A=1\n").is_empty());
+    }
+
+    #[test]
+    fn test_describe() {
+        assert_eq!(describe("work/aws"), "profile 'work/aws'");
+        assert_eq!(describe("hooks/dev/pre-activate"), "hook dev/pre-activate");
+        assert_eq!(describe("templates/aws"), "templates/aws");
+        assert_eq!(describe(".gitignore"), ".gitignore");
+    }
+}
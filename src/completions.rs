@@ -3,6 +3,7 @@
 use clap::CommandFactory;
 use clap_complete::engine::{ArgValueCompleter, CompletionCandidate};
 use clap_complete::CompleteEnv;
+use std::collections::BTreeSet;
 use std::ffi::OsStr;
 
 use crate::config;
@@ -20,46 +21,131 @@ pub fn init() {
 fn build_cli() -> clap::Command {
     Cli::command()
         .mut_subcommand("activate", add_profile_completer)
+        .mut_subcommand("new", add_template_completer)
         .mut_subcommand("edit", add_profile_completer)
+        .mut_subcommand("exec", add_profile_completer)
+        .mut_subcommand("allow", add_profile_completer)
+        .mut_subcommand("export", add_profile_completer)
         .mut_subcommand("show", add_profile_completer)
+        .mut_subcommand("get", add_profile_completer)
         .mut_subcommand("remove", add_profile_completer)
+        .mut_subcommand("rename", add_destination_completer)
+        .mut_subcommand("copy", add_destination_completer)
 }
 
 /// Add profile completer to a subcommand's "profile" argument.
 fn add_profile_completer(cmd: clap::Command) -> clap::Command {
-    cmd.mut_arg("profile", |arg| {
-        arg.add(ArgValueCompleter::new(complete_profiles))
+    add_completer(cmd, "profile", complete_profiles)
+}
+
+/// Add profile completers to both arguments of `rename` and `copy`.
+fn add_destination_completer(cmd: clap::Command) -> clap::Command {
+    add_completer(add_profile_completer(cmd), "destination", complete_profiles)
+}
+
+/// Add profile and template completers to `new`.
+fn add_template_completer(cmd: clap::Command) -> clap::Command {
+    add_completer(add_profile_completer(cmd), "template", complete_templates)
+}
+
+/// Add a completer to the argument `id` of a subcommand.
+///
+/// `Command::mut_arg` would move the argument after the others, changing the
+/// order of positional arguments, so the argument is changed in place.
+fn add_completer(
+    cmd: clap::Command,
+    id: &str,
+    completer: fn(&OsStr) -> Vec<CompletionCandidate>,
+) -> clap::Command {
+    cmd.mut_args(|arg| {
+        if arg.get_id() == id {
+            arg.add(ArgValueCompleter::new(completer))
+        } else {
+            arg
+        }
     })
 }
 
-/// Complete profile names from the profile directory.
+/// Complete template names from the `templates/` directories of the profile stores.
+fn complete_templates(current: &OsStr) -> Vec<CompletionCandidate> {
+    let current = current.to_string_lossy();
+    let Ok(dirs) = config::template_dirs() else {
+        return vec![];
+    };
+
+    let templates: BTreeSet<String> = dirs
+        .iter()
+        .filter_map(|dir| config::list_profiles(dir).ok())
+        .flatten()
+        .filter(|name| name.starts_with(&*current))
+        .collect();
+
+    templates
+        .into_iter()
+        .map(CompletionCandidate::new)
+        .collect()
+}
+
+/// Complete profile names from the profile stores, one namespace segment at a time.
+///
+/// Only the profiles `list` shows are offered, so backups and files with invalid
+/// names are left out.
+/// Namespaces are offered with a trailing `/` so the next segment can be completed
+/// from inside them. A `global:` or `project:` qualifier restricts completion to
+/// that store.
 fn complete_profiles(current: &OsStr) -> Vec<CompletionCandidate> {
     let current_str = current.to_string_lossy();
+    let (qualifier, reference) = config::split_qualifier(&current_str);
 
-    let Ok(profile_dir) = config::get_profile_dir() else {
-        return vec![];
+    // Split "work/aw" into the namespace to read ("work/") and the partial segment ("aw")
+    let (namespace, partial) = match reference.rfind(config::NAMESPACE_SEPARATOR) {
+        Some(pos) => reference.split_at(pos + 1),
+        None => ("", reference),
     };
+    let prefix = qualifier.map_or_else(String::new, |source| format!("{}:", source.qualifier()));
 
-    let Ok(entries) = std::fs::read_dir(&profile_dir) else {
+    let Ok(stores) = config::profile_stores() else {
         return vec![];
     };
 
-    entries
-        .flatten()
-        .filter_map(|entry| {
-            let path = entry.path();
-            if !path.is_file() {
-                return None;
-            }
-            let name = path.file_name()?.to_str()?;
-            if name.starts_with('.') {
-                return None;
+    let mut candidates = BTreeSet::new();
+
+    for store in &stores {
+        if qualifier.is_some_and(|source| source != store.source) {
+            continue;
+        }
+
+        let Ok(profiles) = config::list_profiles(&store.dir) else {
+            continue;
+        };
+
+        for profile in &profiles {
+            let Some(rest) = profile.strip_prefix(namespace) else {
+                continue;
+            };
+            if !rest.starts_with(partial) {
+                continue;
             }
-            if name.starts_with(&*current_str) {
-                Some(CompletionCandidate::new(name))
-            } else {
-                None
+            // Offer the next segment only, as a namespace if more segments follow
+            match rest.split_once(config::NAMESPACE_SEPARATOR) {
+                Some((segment, _)) => candidates.insert(format!("{prefix}{namespace}{segment}/")),
+                None => candidates.insert(format!("{prefix}{namespace}{rest}")),
+            };
+        }
+    }
+
+    // Offer the qualifiers themselves once the user starts typing one
+    if qualifier.is_none() && !current_str.is_empty() {
+        for store in &stores {
+            let option = format!("{}:", store.source.qualifier());
+            if option.starts_with(&*current_str) {
+                candidates.insert(option);
             }
-        })
+        }
+    }
+
+    candidates
+        .into_iter()
+        .map(CompletionCandidate::new)
         .collect()
 }
@@ -1,56 +1,400 @@
 use anyhow::{bail, Context, Result};
-use std::path::PathBuf;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::error::Error;
+use crate::pattern;
 
 /// Environment variable marking an active kraven session.
 pub const KRAVEN_ACTIVE: &str = "KRAVEN_ACTIVE";
 
+/// Separator between namespace segments in a profile name (e.g. `work/aws-prod`).
+pub const NAMESPACE_SEPARATOR: char = '/';
+
 const ENV_PROFILE_DIR: &str = "KRAVEN_PROFILE_DIR";
+const ENV_CONFIG_FILE: &str = "KRAVEN_CONFIG";
+const ENV_AUDIT_LOG: &str = "KRAVEN_AUDIT_LOG";
+const ENV_ALLOWED_HOOKS: &str = "KRAVEN_ALLOWED_HOOKS";
 const DEFAULT_PROFILE_SUBDIR: &str = "kraven";
+const PROJECT_PROFILE_DIR: &str = ".kraven/profiles";
+const CONFIG_FILE: &str = "config.toml";
+const AUDIT_LOG_FILE: &str = "audit.log";
+const ALLOWED_HOOKS_FILE: &str = "allowed-hooks";
+const MASK_KEY_FILE: &str = "mask-key";
+const HOOKS_DIR: &str = "hooks";
+const TEMPLATES_DIR: &str = "templates";
+
+/// File of a profile store whose content seeds the profiles created by `edit`.
+pub const DEFAULT_TEMPLATE: &str = "default-template";
+
+/// File of a profile directory listing patterns of files that are not profiles.
+const IGNORE_FILE: &str = ".kravenignore";
+
+/// Patterns of files that are never profiles, such as editor backups.
+const DEFAULT_IGNORE: &[&str] = &["*~", "*.bak", "*.orig", "*.rej"];
+
+/// Top-level entries of the profile directory that kraven uses for itself.
+const RESERVED_NAMES: &[&str] = &[
+    CONFIG_FILE,
+    MASK_KEY_FILE,
+    HOOKS_DIR,
+    TEMPLATES_DIR,
+    DEFAULT_TEMPLATE,
+];
 
 /// Validates that a profile name is safe (no path traversal or shell injection).
-/// Allowed: alphanumeric, underscore, hyphen, and dot (but not `.` or `..` alone).
-fn validate_profile_name(name: &str) -> Result<()> {
+///
+/// A name is one or more `/`-separated segments. Each segment may contain
+/// alphanumeric, underscore, hyphen, and dot (but not `.` or `..` alone).
+pub fn validate_profile_name(name: &str) -> Result<(), Error> {
+    let invalid = |reason: &str| Error::InvalidName {
+        name: name.to_string(),
+        reason: reason.to_string(),
+    };
+
     if name.is_empty() {
-        bail!("Profile name cannot be empty");
+        return Err(invalid("cannot be empty"));
+    }
+
+    if name.starts_with(NAMESPACE_SEPARATOR) {
+        return Err(invalid("cannot be an absolute path"));
+    }
+
+    let first_segment = name.split(NAMESPACE_SEPARATOR).next().unwrap_or(name);
+    if is_reserved(first_segment) {
+        return Err(invalid("reserved by kraven"));
+    }
+
+    for segment in name.split(NAMESPACE_SEPARATOR) {
+        validate_segment(segment).map_err(|reason| invalid(&reason))?;
+    }
+
+    Ok(())
+}
+
+/// Validates a single namespace segment of a name, returning what is wrong with it.
+fn validate_segment(segment: &str) -> Result<(), String> {
+    if segment.is_empty() {
+        return Err("contains an empty segment".to_string());
     }
 
-    if name == "." || name == ".." {
-        bail!("Invalid profile name: '{name}'");
+    if segment == "." || segment == ".." {
+        return Err(format!("'{segment}' is not a valid segment"));
     }
 
-    if name.starts_with('-') {
-        bail!("Profile name cannot start with '-': '{name}'");
+    if segment.starts_with('-') {
+        return Err("cannot start with '-'".to_string());
     }
 
     let is_valid_char = |c: char| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.');
-    if let Some(c) = name.chars().find(|c| !is_valid_char(*c)) {
-        bail!("Profile name contains invalid character '{c}': '{name}'");
+    if let Some(c) = segment.chars().find(|c| !is_valid_char(*c)) {
+        return Err(format!("contains invalid character '{c}'"));
     }
 
     Ok(())
 }
 
-/// Returns the directory where profiles are stored.
-pub fn get_profile_dir() -> Result<PathBuf> {
+/// Returns the directory where global profiles are stored.
+pub fn get_profile_dir() -> Result<PathBuf, Error> {
     if let Ok(custom_dir) = std::env::var(ENV_PROFILE_DIR) {
         return Ok(PathBuf::from(custom_dir));
     }
-    let config_dir = dirs::config_dir().context("Could not determine config directory")?;
+    let config_dir = dirs::config_dir().ok_or(Error::NoProfileDir)?;
     Ok(config_dir.join(DEFAULT_PROFILE_SUBDIR))
 }
 
+/// Returns whether a top-level entry of the profile directory is reserved by kraven.
+#[must_use]
+pub fn is_reserved(name: &str) -> bool {
+    RESERVED_NAMES.contains(&name)
+}
+
+/// Returns the path of the kraven configuration file.
+pub fn get_config_path() -> Result<PathBuf> {
+    if let Ok(custom_path) = std::env::var(ENV_CONFIG_FILE) {
+        return Ok(PathBuf::from(custom_path));
+    }
+    Ok(get_profile_dir()?.join(CONFIG_FILE))
+}
+
+/// Returns the path of the key that masked value fingerprints are computed with.
+///
+/// It lives next to the configuration file, and like it stays on this machine.
+pub fn get_mask_key_path() -> Result<PathBuf> {
+    Ok(get_config_path()?.with_file_name(MASK_KEY_FILE))
+}
+
+/// Returns the path of the audit log.
+pub fn get_audit_log_path() -> Result<PathBuf> {
+    if let Ok(custom_path) = std::env::var(ENV_AUDIT_LOG) {
+        return Ok(PathBuf::from(custom_path));
+    }
+    let data_dir = dirs::data_dir().context("Could not determine data directory")?;
+    Ok(data_dir.join(DEFAULT_PROFILE_SUBDIR).join(AUDIT_LOG_FILE))
+}
+
+/// Returns the path of the list of project hooks the user has allowed.
+///
+/// It lives in the data directory rather than in the profile directory, so
+/// the decision to trust a repository stays on this machine.
+pub fn get_allowed_hooks_path() -> Result<PathBuf> {
+    if let Ok(custom_path) = std::env::var(ENV_ALLOWED_HOOKS) {
+        return Ok(PathBuf::from(custom_path));
+    }
+    let data_dir = dirs::data_dir().context("Could not determine data directory")?;
+    Ok(data_dir
+        .join(DEFAULT_PROFILE_SUBDIR)
+        .join(ALLOWED_HOOKS_FILE))
+}
+
+/// Where a profile is stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ProfileSource {
+    /// The user's profile directory (see [`get_profile_dir`]).
+    Global,
+    /// A `.kraven/profiles` directory found above the current directory.
+    Project,
+}
+
+impl ProfileSource {
+    /// The qualifier used to refer to this source explicitly (e.g. `project:dev`).
+    #[must_use]
+    pub fn qualifier(self) -> &'static str {
+        match self {
+            Self::Global => "global",
+            Self::Project => "project",
+        }
+    }
+}
+
+/// A directory holding profiles.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct ProfileStore {
+    pub source: ProfileSource,
+    pub dir: PathBuf,
+}
+
+/// Searches upward from the current directory for a project profile directory.
+#[must_use]
+pub fn find_project_profile_dir() -> Option<PathBuf> {
+    let cwd = std::env::current_dir().ok()?;
+    cwd.ancestors()
+        .map(|dir| dir.join(PROJECT_PROFILE_DIR))
+        .find(|dir| dir.is_dir())
+}
+
+/// Returns the available profile stores in precedence order.
+///
+/// Global profiles take precedence over project profiles with the same name, so a
+/// checked-out repository can never shadow a personal profile. Use the `project:`
+/// qualifier to reach a shadowed project profile.
+pub fn profile_stores() -> Result<Vec<ProfileStore>, Error> {
+    let mut stores = vec![ProfileStore {
+        source: ProfileSource::Global,
+        dir: get_profile_dir()?,
+    }];
+    if let Some(dir) = find_project_profile_dir() {
+        stores.push(ProfileStore {
+            source: ProfileSource::Project,
+            dir,
+        });
+    }
+    Ok(stores)
+}
+
+/// Splits an optional `global:`/`project:` qualifier off a profile reference.
+#[must_use]
+pub fn split_qualifier(reference: &str) -> (Option<ProfileSource>, &str) {
+    for source in [ProfileSource::Global, ProfileSource::Project] {
+        if let Some(name) = reference
+            .strip_prefix(source.qualifier())
+            .and_then(|rest| rest.strip_prefix(':'))
+        {
+            return (Some(source), name);
+        }
+    }
+    (None, reference)
+}
+
+/// A profile reference resolved to the store that holds it.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct ResolvedProfile {
+    pub source: ProfileSource,
+    /// The profile name without qualifier.
+    pub name: String,
+    /// The root of the store holding the profile.
+    pub root: PathBuf,
+    pub path: PathBuf,
+}
+
+impl ResolvedProfile {
+    /// A reference resolving to this profile: its name, qualified for project profiles.
+    #[must_use]
+    pub fn reference(&self) -> String {
+        match self.source {
+            ProfileSource::Global => self.name.clone(),
+            ProfileSource::Project => format!("{}:{}", self.source.qualifier(), self.name),
+        }
+    }
+
+    /// Directory holding the profile's hook scripts (`<store>/hooks/<name>/`).
+    #[must_use]
+    pub fn hooks_dir(&self) -> PathBuf {
+        self.root.join(HOOKS_DIR).join(&self.name)
+    }
+}
+
+/// Resolves a possibly qualified profile reference, validating the profile name.
+///
+/// Unqualified names resolve to the first store that has the profile. Profiles
+/// that don't exist yet resolve to the global store.
+pub fn resolve_profile(reference: &str) -> Result<ResolvedProfile, Error> {
+    let (qualifier, name) = split_qualifier(reference);
+    validate_profile_name(name)?;
+
+    let mut stores = profile_stores()?;
+    let index = if let Some(source) = qualifier {
+        stores
+            .iter()
+            .position(|store| store.source == source)
+            .ok_or(Error::NoProjectStore)?
+    } else {
+        stores
+            .iter()
+            .position(|store| store.dir.join(name).is_file())
+            .unwrap_or(0)
+    };
+    let store = stores.swap_remove(index);
+
+    let path = store.dir.join(name);
+    ensure_within(&store.dir, &path).map_err(|err| match err {
+        EscapeError::Escapes => Error::InvalidName {
+            name: reference.to_string(),
+            reason: "escapes the profile directory".to_string(),
+        },
+        EscapeError::Io { path, source } => Error::Io { path, source },
+    })?;
+    Ok(ResolvedProfile {
+        source: store.source,
+        name: name.to_string(),
+        root: store.dir,
+        path,
+    })
+}
+
+/// Returns the profiles of every store, global first, for commands working on many.
+///
+/// See [`discover_profiles`] for which files are profiles.
+pub fn all_profiles() -> Result<Vec<ResolvedProfile>> {
+    let mut profiles = Vec::new();
+    for store in profile_stores()? {
+        for name in list_profiles(&store.dir)? {
+            profiles.push(ResolvedProfile {
+                source: store.source,
+                path: store.dir.join(&name),
+                root: store.dir.clone(),
+                name,
+            });
+        }
+    }
+    Ok(profiles)
+}
+
+/// Resolves the destination of a rename or copy of `source`.
+///
+/// Unqualified destinations stay in the store of `source`.
+pub fn resolve_destination(
+    source: &ResolvedProfile,
+    reference: &str,
+) -> Result<ResolvedProfile, Error> {
+    if split_qualifier(reference).0.is_some() {
+        return resolve_profile(reference);
+    }
+    resolve_profile(&format!("{}:{reference}", source.source.qualifier()))
+}
+
+/// Returns the template directories of the profile stores, global first.
+pub fn template_dirs() -> Result<Vec<PathBuf>> {
+    Ok(profile_stores()?
+        .into_iter()
+        .map(|store| store.dir.join(TEMPLATES_DIR))
+        .collect())
+}
+
+/// Finds a template by name in the `templates/` directories of the profile stores.
+pub fn resolve_template(name: &str) -> Result<PathBuf> {
+    if name.is_empty() {
+        bail!("Template name cannot be empty");
+    }
+    for segment in name.split(NAMESPACE_SEPARATOR) {
+        if let Err(reason) = validate_segment(segment) {
+            bail!("Invalid template name '{name}': {reason}");
+        }
+    }
+
+    for dir in template_dirs()? {
+        let path = dir.join(name);
+        if path.is_file() {
+            if let Err(EscapeError::Escapes) = ensure_within(&dir, &path) {
+                bail!("Invalid template name '{name}': escapes the template directory");
+            }
+            return Ok(path);
+        }
+    }
+
+    bail!("Template '{name}' does not exist.");
+}
+
 /// Returns the full path to a profile file, validating the profile name.
-pub fn get_profile_path(profile_name: &str) -> Result<PathBuf> {
-    validate_profile_name(profile_name)?;
-    let profile_dir = get_profile_dir()?;
-    Ok(profile_dir.join(profile_name))
+pub fn get_profile_path(reference: &str) -> Result<PathBuf, Error> {
+    Ok(resolve_profile(reference)?.path)
+}
+
+/// Ensures `path` does not resolve outside of `root` through symlinks.
+///
+/// The check runs against the deepest existing ancestor of `path`, so it also
+/// covers profiles that are about to be created inside a symlinked namespace.
+fn ensure_within(root: &Path, path: &Path) -> Result<(), EscapeError> {
+    let Ok(root) = root.canonicalize() else {
+        // Nothing exists yet, so nothing can escape.
+        return Ok(());
+    };
+
+    let Some(existing) = path.ancestors().find(|p| p.exists()) else {
+        return Ok(());
+    };
+
+    let resolved = existing.canonicalize().map_err(|source| EscapeError::Io {
+        path: existing.to_path_buf(),
+        source,
+    })?;
+    if !resolved.starts_with(&root) {
+        return Err(EscapeError::Escapes);
+    }
+
+    Ok(())
+}
+
+/// Why [`ensure_within`] rejected a path.
+#[derive(Debug)]
+enum EscapeError {
+    /// The path resolves outside of the root.
+    Escapes,
+    /// The path could not be resolved.
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
 }
 
 /// Ensures the profile directory exists, creating it if necessary.
 pub fn ensure_profile_dir_exists() -> Result<PathBuf> {
     let profile_dir = get_profile_dir()?;
     if !profile_dir.exists() {
-        std::fs::create_dir_all(&profile_dir).with_context(|| {
+        fs::create_dir_all(&profile_dir).with_context(|| {
             format!(
                 "Failed to create profile directory: {}",
                 profile_dir.display()
@@ -60,9 +404,170 @@ pub fn ensure_profile_dir_exists() -> Result<PathBuf> {
     Ok(profile_dir)
 }
 
+/// Profiles found in a profile directory by [`discover_profiles`].
+#[derive(Debug, Default)]
+#[non_exhaustive]
+pub struct Discovery {
+    /// Names of the profiles, sorted.
+    pub profiles: Vec<String>,
+    /// Files that aren't ignored but can't be used as profiles, sorted by name.
+    pub invalid: Vec<InvalidEntry>,
+}
+
+/// A file of a profile directory whose name is not a valid profile name.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[non_exhaustive]
+pub struct InvalidEntry {
+    pub name: String,
+    pub reason: String,
+}
+
+/// Finds the profiles of a profile directory.
+///
+/// Namespaced profiles are returned with their full name (e.g. `work/aws-prod`).
+/// Hidden files and directories, kraven's own files, editor backups and the
+/// entries matching the patterns of the directory's `.kravenignore` are skipped.
+/// Symlinked directories are not followed. Every other file is a profile if
+/// [`validate_profile_name`] accepts its name, and an invalid entry otherwise.
+pub fn discover_profiles(profile_dir: &Path) -> Result<Discovery> {
+    let mut discovery = Discovery::default();
+    if profile_dir.exists() {
+        let ignore = IgnorePatterns::load(profile_dir);
+        collect_profiles(profile_dir, "", &ignore, &mut discovery)?;
+    }
+    discovery.profiles.sort();
+    discovery.invalid.sort();
+    Ok(discovery)
+}
+
+/// Returns the names of the profiles in a profile directory, sorted.
+///
+/// See [`discover_profiles`] for which files are profiles.
+pub fn list_profiles(profile_dir: &Path) -> Result<Vec<String>> {
+    Ok(discover_profiles(profile_dir)?.profiles)
+}
+
+fn collect_profiles(
+    dir: &Path,
+    prefix: &str,
+    ignore: &IgnorePatterns,
+    discovery: &mut Discovery,
+) -> Result<()> {
+    let entries = fs::read_dir(dir)
+        .with_context(|| format!("Failed to read profile directory: {}", dir.display()))?;
+
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if name.starts_with('.') || (prefix.is_empty() && is_reserved(&name)) {
+            continue;
+        }
+        let full_name = format!("{prefix}{name}");
+        if ignore.matches(&full_name) {
+            continue;
+        }
+
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+        if file_type.is_dir() {
+            collect_profiles(&entry.path(), &format!("{full_name}/"), ignore, discovery)?;
+        } else if entry.path().is_file() {
+            match validate_profile_name(&full_name) {
+                Ok(()) => discovery.profiles.push(full_name),
+                Err(Error::InvalidName { reason, .. }) => {
+                    discovery.invalid.push(InvalidEntry {
+                        name: full_name,
+                        reason,
+                    });
+                }
+                Err(err) => unreachable!("name validation failed with: {err}"),
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the patterns of the entries of `dir` that are not profiles: editor
+/// backups and those listed in its `.kravenignore` file.
+///
+/// A pattern with a `/` matches full names from the root of `dir`, one without
+/// the last segment of names at any depth.
+#[must_use]
+pub fn ignore_patterns(dir: &Path) -> Vec<String> {
+    IgnorePatterns::load(dir).0
+}
+
+/// Glob patterns of the entries of a profile directory that are not profiles.
+///
+/// A pattern with a `/` matches full names from the root of the directory
+/// (`/dev` only matches the top-level `dev`); one without matches the last
+/// segment, at any depth.
+struct IgnorePatterns(Vec<String>);
+
+impl IgnorePatterns {
+    /// Reads the `.kravenignore` file of `dir`, one pattern per line. Empty lines
+    /// and lines starting with `#` are skipped.
+    fn load(dir: &Path) -> Self {
+        let content = fs::read_to_string(dir.join(IGNORE_FILE)).unwrap_or_default();
+        Self::parse(&content)
+    }
+
+    fn parse(content: &str) -> Self {
+        let patterns = DEFAULT_IGNORE
+            .iter()
+            .map(|pattern| (*pattern).to_string())
+            .chain(
+                content
+                    .lines()
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                    .map(|line| line.trim_end_matches(NAMESPACE_SEPARATOR).to_string()),
+            )
+            .collect();
+        Self(patterns)
+    }
+
+    fn matches(&self, full_name: &str) -> bool {
+        let last_segment = full_name
+            .rsplit(NAMESPACE_SEPARATOR)
+            .next()
+            .unwrap_or(full_name);
+        self.0
+            .iter()
+            .any(|pattern| match pattern.strip_prefix(NAMESPACE_SEPARATOR) {
+                Some(anchored) => pattern::matches(anchored, full_name),
+                None if pattern.contains(NAMESPACE_SEPARATOR) => {
+                    pattern::matches(pattern, full_name)
+                }
+                None => pattern::matches(pattern, last_segment),
+            })
+    }
+}
+
+/// Removes empty namespace directories left behind by a profile and its hooks.
+pub fn prune_empty_namespaces(profile: &ResolvedProfile) {
+    prune_empty_dirs(&profile.path, &profile.root);
+    prune_empty_dirs(&profile.hooks_dir(), &profile.root.join(HOOKS_DIR));
+}
+
+/// Removes empty directories between `path` and `root`.
+fn prune_empty_dirs(path: &Path, root: &Path) {
+    for dir in path.ancestors().skip(1) {
+        if dir == root || !dir.starts_with(root) {
+            break;
+        }
+        // Stops at the first directory that still has entries.
+        if fs::remove_dir(dir).is_err() {
+            break;
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::testing::TempDir;
 
     #[test]
     fn test_valid_profile_names() {
@@ -73,6 +578,13 @@ mod tests {
         assert!(validate_profile_name("AWS_PROD").is_ok());
     }
 
+    #[test]
+    fn test_namespaced_profile_names() {
+        assert!(validate_profile_name("work/aws-prod").is_ok());
+        assert!(validate_profile_name("work/aws/prod").is_ok());
+        assert!(validate_profile_name("client.a/v1.2").is_ok());
+    }
+
     #[test]
     fn test_empty_profile_name() {
         assert!(validate_profile_name("").is_err());
@@ -83,13 +595,32 @@ mod tests {
         assert!(validate_profile_name(".").is_err());
         assert!(validate_profile_name("..").is_err());
         assert!(validate_profile_name("../etc").is_err());
-        assert!(validate_profile_name("foo/bar").is_err());
+        assert!(validate_profile_name("work/../../etc").is_err());
+        assert!(validate_profile_name("work/./dev").is_err());
+        assert!(validate_profile_name("/etc/passwd").is_err());
+    }
+
+    #[test]
+    fn test_reserved_names_blocked() {
+        assert!(validate_profile_name("config.toml").is_err());
+        assert!(validate_profile_name("config.toml/dev").is_err());
+        assert!(validate_profile_name("work/config.toml").is_ok());
+        assert!(validate_profile_name("hooks/dev").is_err());
+        assert!(validate_profile_name("templates").is_err());
+        assert!(validate_profile_name("default-template").is_err());
+    }
+
+    #[test]
+    fn test_empty_segments_blocked() {
+        assert!(validate_profile_name("work/").is_err());
+        assert!(validate_profile_name("work//dev").is_err());
     }
 
     #[test]
     fn test_leading_dash_blocked() {
         assert!(validate_profile_name("-flag").is_err());
         assert!(validate_profile_name("--help").is_err());
+        assert!(validate_profile_name("work/-flag").is_err());
     }
 
     #[test]
@@ -97,5 +628,80 @@ mod tests {
         assert!(validate_profile_name("foo bar").is_err());
         assert!(validate_profile_name("foo;bar").is_err());
         assert!(validate_profile_name("$(whoami)").is_err());
+        assert!(validate_profile_name("foo\\bar").is_err());
+    }
+
+    #[test]
+    fn test_split_qualifier() {
+        assert_eq!(
+            split_qualifier("project:dev"),
+            (Some(ProfileSource::Project), "dev")
+        );
+        assert_eq!(
+            split_qualifier("global:work/aws"),
+            (Some(ProfileSource::Global), "work/aws")
+        );
+        assert_eq!(split_qualifier("dev"), (None, "dev"));
+        assert_eq!(split_qualifier("projects:dev"), (None, "projects:dev"));
+    }
+
+    #[test]
+    fn test_ignore_patterns() {
+        let ignore = IgnorePatterns::parse("# backups\n\nold-*\n/scratch\nwork/tmp/\n");
+        assert!(ignore.matches("dev~"));
+        assert!(ignore.matches("work/dev.bak"));
+        assert!(ignore.matches("old-dev"));
+        assert!(ignore.matches("work/old-dev"));
+        assert!(ignore.matches("scratch"));
+        assert!(!ignore.matches("work/scratch"));
+        assert!(ignore.matches("work/tmp"));
+        assert!(!ignore.matches("tmp"));
+        assert!(!ignore.matches("dev"));
+        assert!(!ignore.matches("# backups"));
+    }
+
+    #[test]
+    fn test_discover_profiles() {
+        let dir = TempDir::new("discover");
+        for name in [
+            "dev",
+            "dev~",
+            "dev.bak",
+            ".dev.swp",
+            "config.toml",
+            "my profile",
+            "work/aws",
+            "work/-flag",
+            "old/dev",
+        ] {
+            let path = dir.join(name);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "A=1\n").unwrap();
+        }
+        fs::write(dir.join(IGNORE_FILE), "old\n").unwrap();
+
+        let discovery = discover_profiles(&dir).unwrap();
+        assert_eq!(discovery.profiles, ["dev", "work/aws"]);
+        let invalid: Vec<_> = discovery
+            .invalid
+            .iter()
+            .map(|entry| entry.name.as_str())
+            .collect();
+        assert_eq!(invalid, ["my profile", "work/-flag"]);
+        assert_eq!(discovery.invalid[1].reason, "cannot start with '-'");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlink_escape_blocked() {
+        let root = TempDir::new("symlink-escape");
+        let outside = root.join("outside");
+        let profiles = root.join("profiles");
+        fs::create_dir_all(&outside).unwrap();
+        fs::create_dir_all(&profiles).unwrap();
+        std::os::unix::fs::symlink(&outside, profiles.join("escape")).unwrap();
+
+        assert!(ensure_within(&profiles, &profiles.join("escape/secret")).is_err());
+        assert!(ensure_within(&profiles, &profiles.join("work/dev")).is_ok());
     }
 }
@@ -0,0 +1,185 @@
+//! Problems found in profile files, and their rendering with source excerpts.
+
+use std::fmt::Write;
+use std::path::Path;
+
+use crate::error::ParseErrorKind;
+
+/// Prefix of the comment lines that [`annotate`] adds to a profile.
+///
+/// It names kraven, so that comments the user wrote are never taken for one.
+pub const ANNOTATION_PREFIX: &str = "# kraven error: ";
+
+/// A problem with a line of a profile file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Diagnostic {
+    /// 1-indexed line of the problem.
+    pub line: usize,
+    /// 1-indexed column, in characters, where the offending text starts.
+    pub column: usize,
+    /// Length of the offending text in characters, at least 1.
+    pub len: usize,
+    pub kind: ParseErrorKind,
+}
+
+impl Diagnostic {
+    /// A diagnostic pointing at `part`, which must be a slice of `line`.
+    pub(crate) fn new(line_num: usize, line: &str, part: &str, kind: ParseErrorKind) -> Self {
+        let offset = (part.as_ptr() as usize)
+            .checked_sub(line.as_ptr() as usize)
+            .filter(|offset| offset + part.len() <= line.len())
+            .expect("part is a slice of the line");
+        Self {
+            line: line_num,
+            column: line[..offset].chars().count() + 1,
+            len: part.chars().count().max(1),
+            kind,
+        }
+    }
+}
+
+/// Renders diagnostics of the file at `path` the way compilers do, each with
+/// its message, location, the offending line and carets under the problem.
+#[must_use]
+pub fn render(diagnostics: &[Diagnostic], content: &str, path: &Path) -> String {
+    let lines: Vec<&str> = content.lines().collect();
+    let gutter = diagnostics
+        .iter()
+        .map(|diagnostic| diagnostic.line.to_string().len())
+        .max()
+        .unwrap_or(1);
+
+    let mut rendered = String::new();
+    for (i, diagnostic) in diagnostics.iter().enumerate() {
+        if i > 0 {
+            rendered.push('\n');
+        }
+        let source = lines.get(diagnostic.line - 1).copied().unwrap_or_default();
+        // Tabs are kept so the carets line up with the text above them
+        let indent: String = source
+            .chars()
+            .take(diagnostic.column - 1)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        let _ = writeln!(rendered, "error: {}", diagnostic.kind);
+        let _ = writeln!(
+            rendered,
+            "{:gutter$}--> {}:{}:{}",
+            "",
+            path.display(),
+            diagnostic.line,
+            diagnostic.column
+        );
+        let _ = writeln!(rendered, "{:gutter$} |", "");
+        let _ = writeln!(rendered, "{:>gutter$} | {source}", diagnostic.line);
+        let _ = writeln!(
+            rendered,
+            "{:gutter$} | {indent}{}",
+            "",
+            "^".repeat(diagnostic.len)
+        );
+    }
+    rendered
+}
+
+/// Inserts a `# kraven error:` comment line above each line with a problem, for the
+/// user to read while fixing the file in an editor.
+#[must_use]
+pub fn annotate(diagnostics: &[Diagnostic], content: &str) -> String {
+    let mut annotated = String::with_capacity(content.len());
+    for (i, line) in content.lines().enumerate() {
+        for diagnostic in diagnostics.iter().filter(|d| d.line == i + 1) {
+            let _ = writeln!(
+                annotated,
+                "{ANNOTATION_PREFIX}{} (column {})",
+                diagnostic.kind, diagnostic.column
+            );
+        }
+        annotated.push_str(line);
+        annotated.push('\n');
+    }
+    annotated
+}
+
+/// Removes the lines added by [`annotate`].
+#[must_use]
+pub fn strip_annotations(content: &str) -> String {
+    if !content
+        .lines()
+        .any(|line| line.starts_with(ANNOTATION_PREFIX))
+    {
+        return content.to_string();
+    }
+    let mut stripped = String::with_capacity(content.len());
+    for line in content.lines() {
+        if !line.starts_with(ANNOTATION_PREFIX) {
+            stripped.push_str(line);
+            stripped.push('\n');
+        }
+    }
+    stripped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn invalid_key(key: &str) -> ParseErrorKind {
+        ParseErrorKind::InvalidKey {
+            key: key.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_span_of_part() {
+        let line = "  9KEY=value";
+        let diagnostic = Diagnostic::new(3, line, &line[2..6], invalid_key("9KEY"));
+        assert_eq!(
+            (diagnostic.line, diagnostic.column, diagnostic.len),
+            (3, 3, 4)
+        );
+
+        // Columns count characters, not bytes
+        let line = "#@secret: é, 1X";
+        let diagnostic = Diagnostic::new(1, line, &line[14..], invalid_key("1X"));
+        assert_eq!((diagnostic.column, diagnostic.len), (14, 2));
+    }
+
+    #[test]
+    fn test_render() {
+        let content = "A=1\n\t9KEY=value\n";
+        let line = content.lines().nth(1).unwrap();
+        let diagnostics = [Diagnostic::new(2, line, &line[1..5], invalid_key("9KEY"))];
+        assert_eq!(
+            render(&diagnostics, content, Path::new("dev")),
+            "error: Invalid variable name '9KEY': must contain only alphanumeric characters \
+             and underscores, and not start with a digit\n \
+             --> dev:2:2\n  \
+             |\n\
+             2 | \t9KEY=value\n  \
+             | \t^^^^\n"
+        );
+    }
+
+    #[test]
+    fn test_annotate_roundtrip() {
+        let content = "A=1\n9X=2\nB=3\n";
+        let line = content.lines().nth(1).unwrap();
+        let diagnostics = [Diagnostic::new(2, line, &line[..2], invalid_key("9X"))];
+        let annotated = annotate(&diagnostics, content);
+        let lines: Vec<&str> = annotated.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert!(
+            lines[1].starts_with("# kraven error: Invalid variable name '9X'"),
+            "{annotated}"
+        );
+        assert!(lines[1].ends_with("(column 1)"), "{annotated}");
+        assert_eq!(lines[2], "9X=2");
+        assert_eq!(strip_annotations(&annotated), content);
+
+        // Comments of the user are kept
+        let content = "# ERROR: codes of the API\nA=1\n";
+        assert_eq!(strip_annotations(content), content);
+    }
+}
@@ -0,0 +1,66 @@
+//! Hashes of profiles and their values.
+
+use sha2::{Digest, Sha256};
+
+/// Block size of SHA-256, which HMAC pads its key to.
+const BLOCK_SIZE: usize = 64;
+
+/// Hashes content, such as a profile file, as `sha256:<hex>`.
+#[must_use]
+pub fn hash(content: &[u8]) -> String {
+    format!("sha256:{:x}", Sha256::digest(content))
+}
+
+/// Computes the HMAC-SHA256 of content with a secret key, as hex.
+///
+/// Unlike [`hash`], the result can't be checked against guesses of the
+/// content without the key, which matters for short values.
+#[must_use]
+pub fn keyed_hash(key: &[u8], content: &[u8]) -> String {
+    let mut block = [0u8; BLOCK_SIZE];
+    if key.len() > BLOCK_SIZE {
+        block[..32].copy_from_slice(&Sha256::digest(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+
+    let pad = |byte: u8| block.map(|b| b ^ byte);
+    let inner = Sha256::new()
+        .chain_update(pad(0x36))
+        .chain_update(content)
+        .finalize();
+    let outer = Sha256::new()
+        .chain_update(pad(0x5c))
+        .chain_update(inner)
+        .finalize();
+    format!("{outer:x}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hash_hides_content() {
+        let hashed = hash(b"SECRET=hunter2\n");
+        assert!(hashed.starts_with("sha256:"));
+        assert_eq!(hashed.len(), "sha256:".len() + 64);
+        assert!(!hashed.contains("hunter2"));
+    }
+
+    #[test]
+    fn test_keyed_hash() {
+        // RFC 4231, test cases 2 and 6
+        assert_eq!(
+            keyed_hash(b"Jefe", b"what do ya want for nothing?"),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+        assert_eq!(
+            keyed_hash(
+                &[0xaa; 131],
+                b"Test Using Larger Than Block-Size Key - Hash Key First"
+            ),
+            "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54"
+        );
+    }
+}
@@ -0,0 +1,100 @@
+//! Human-friendly durations such as `30m` or `1h30m`.
+
+use anyhow::{bail, Result};
+use std::time::Duration;
+
+const UNITS: &[(char, u64)] = &[('d', 86_400), ('h', 3_600), ('m', 60), ('s', 1)];
+
+/// Parses a duration made of `<number><unit>` parts, with units `d`, `h`, `m` and `s`.
+pub fn parse_duration(input: &str) -> Result<Duration> {
+    let input = input.trim();
+    if input.is_empty() {
+        bail!("Duration cannot be empty");
+    }
+
+    let mut seconds: u64 = 0;
+    let mut number = String::new();
+
+    for c in input.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+
+        let Some((_, factor)) = UNITS.iter().find(|(unit, _)| *unit == c) else {
+            bail!("Invalid duration '{input}': unknown unit '{c}' (use d, h, m or s)");
+        };
+        let Ok(value) = number.parse::<u64>() else {
+            bail!("Invalid duration '{input}': expected a number before '{c}'");
+        };
+        seconds = value
+            .checked_mul(*factor)
+            .and_then(|part| seconds.checked_add(part))
+            .ok_or_else(|| anyhow::anyhow!("Duration '{input}' is too large"))?;
+        number.clear();
+    }
+
+    if !number.is_empty() {
+        bail!("Invalid duration '{input}': missing unit after '{number}' (use d, h, m or s)");
+    }
+    if seconds == 0 {
+        bail!("Duration must be greater than zero: '{input}'");
+    }
+
+    Ok(Duration::from_secs(seconds))
+}
+
+/// Formats a duration with its two most significant units, e.g. `1h 5m` or `42s`.
+#[must_use]
+pub fn format_duration(duration: Duration) -> String {
+    let mut remaining = duration.as_secs();
+    let mut parts = Vec::new();
+
+    for (unit, factor) in UNITS {
+        let value = remaining / factor;
+        if value > 0 || (parts.is_empty() && *unit == 's') {
+            parts.push(format!("{value}{unit}"));
+        }
+        remaining %= factor;
+    }
+
+    parts.truncate(2);
+    parts.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_single_unit() {
+        assert_eq!(parse_duration("90s").unwrap(), Duration::from_secs(90));
+        assert_eq!(parse_duration("30m").unwrap(), Duration::from_secs(1_800));
+        assert_eq!(parse_duration("2h").unwrap(), Duration::from_secs(7_200));
+        assert_eq!(parse_duration("1d").unwrap(), Duration::from_secs(86_400));
+    }
+
+    #[test]
+    fn test_parse_combined_units() {
+        assert_eq!(parse_duration("1h30m").unwrap(), Duration::from_secs(5_400));
+        assert_eq!(parse_duration(" 1m5s ").unwrap(), Duration::from_secs(65));
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("30").is_err());
+        assert!(parse_duration("m").is_err());
+        assert!(parse_duration("5x").is_err());
+        assert!(parse_duration("0m").is_err());
+        assert!(parse_duration("-5m").is_err());
+    }
+
+    #[test]
+    fn test_format() {
+        assert_eq!(format_duration(Duration::from_secs(0)), "0s");
+        assert_eq!(format_duration(Duration::from_secs(42)), "42s");
+        assert_eq!(format_duration(Duration::from_secs(3_900)), "1h 5m");
+        assert_eq!(format_duration(Duration::from_secs(90_061)), "1d 1h");
+    }
+}
@@ -0,0 +1,108 @@
+//! Finding and starting the user's editor.
+//!
+//! The editor is resolved like git does: `$KRAVEN_EDITOR`, then `$VISUAL` when
+//! there is a terminal to show it on, then `$EDITOR`, then the `editor`
+//! setting, then `vi`. The command is split with shell quoting rules, so
+//! `"/Applications/Sublime Text/subl" -w` works.
+
+use anyhow::{Context, Result};
+use std::path::Path;
+use std::process::Command;
+
+/// Environment variable naming the editor for kraven only.
+pub const ENV_EDITOR: &str = "KRAVEN_EDITOR";
+
+const DEFAULT_EDITOR: &str = "vi";
+
+/// Picks the editor command.
+///
+/// `env` looks up environment variables, `terminal` tells whether a full-screen
+/// editor can be used, and `configured` is the `editor` setting. Empty values
+/// count as unset.
+pub fn resolve(
+    env: impl Fn(&str) -> Option<String>,
+    terminal: bool,
+    configured: Option<&str>,
+) -> String {
+    let env = |key: &str| env(key).filter(|value| !value.trim().is_empty());
+    env(ENV_EDITOR)
+        .or_else(|| terminal.then(|| env("VISUAL")).flatten())
+        .or_else(|| env("EDITOR"))
+        .or_else(|| configured.map(str::to_string))
+        .unwrap_or_else(|| DEFAULT_EDITOR.to_string())
+}
+
+/// Builds the command opening `path` in `editor`.
+pub fn command(editor: &str, path: &Path) -> Result<Command> {
+    let words = shell_words::split(editor).context("unbalanced quotes")?;
+    let (program, args) = words.split_first().context("empty command")?;
+    let mut cmd = Command::new(program);
+    cmd.args(args).arg(path);
+    Ok(cmd)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+    use std::collections::HashMap;
+    use std::fs;
+
+    fn resolve_with(vars: &[(&str, &str)], terminal: bool, configured: Option<&str>) -> String {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(key, value)| ((*key).to_string(), (*value).to_string()))
+            .collect();
+        resolve(|key| vars.get(key).cloned(), terminal, configured)
+    }
+
+    #[test]
+    fn test_precedence() {
+        let all = [
+            (ENV_EDITOR, "kraven-editor"),
+            ("VISUAL", "visual"),
+            ("EDITOR", "editor"),
+        ];
+        assert_eq!(resolve_with(&all, true, Some("nano")), "kraven-editor");
+        assert_eq!(resolve_with(&all[1..], true, Some("nano")), "visual");
+        // VISUAL needs a terminal
+        assert_eq!(resolve_with(&all[1..], false, Some("nano")), "editor");
+        assert_eq!(resolve_with(&all[2..], true, Some("nano")), "editor");
+        assert_eq!(resolve_with(&[], true, Some("nano")), "nano");
+        assert_eq!(resolve_with(&[], true, None), "vi");
+        assert_eq!(resolve_with(&[("EDITOR", " ")], true, None), "vi");
+    }
+
+    #[test]
+    fn test_command_splits_quoted_words() {
+        let cmd = command(r#""/Applications/Sublime Text/subl" -w"#, Path::new("dev")).unwrap();
+        assert_eq!(cmd.get_program(), "/Applications/Sublime Text/subl");
+        let args: Vec<_> = cmd.get_args().collect();
+        assert_eq!(args, ["-w", "dev"]);
+
+        assert!(command("'unbalanced", Path::new("dev")).is_err());
+        assert!(command("", Path::new("dev")).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_fake_editor_in_path_with_spaces() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp = TempDir::new("editor");
+        let dir = temp.join("My Editor");
+        fs::create_dir_all(&dir).unwrap();
+        let script = dir.join("edit");
+        fs::write(&script, "#!/bin/sh\nprintf '%s\\n' \"$@\" > \"$2\"\n").unwrap();
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+
+        let file = dir.join("profile with space");
+        let editor = format!("'{}' --wait", script.display());
+        let status = command(&editor, &file).unwrap().status().unwrap();
+        assert!(status.success());
+        assert_eq!(
+            fs::read_to_string(&file).unwrap(),
+            format!("--wait\n{}\n", file.display())
+        );
+    }
+}
@@ -0,0 +1,101 @@
+//! Errors of the kraven library API.
+
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+/// A result with a kraven [`Error`].
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// An error loading or resolving a profile.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// A profile name kraven doesn't accept, e.g. one escaping the profile directory.
+    InvalidName { name: String, reason: String },
+    /// No profile with this name exists.
+    NotFound { name: String },
+    /// A profile file has a syntax error.
+    Parse {
+        path: PathBuf,
+        /// 1-indexed line of the error.
+        line: usize,
+        kind: ParseErrorKind,
+    },
+    /// A `project:` profile was asked for outside of a project.
+    NoProjectStore,
+    /// There is no default profile directory and `KRAVEN_PROFILE_DIR` is unset.
+    NoProfileDir,
+    /// Reading a file failed.
+    Io { path: PathBuf, source: io::Error },
+}
+
+/// What is wrong with a line of a profile file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ParseErrorKind {
+    /// A line that is neither a comment nor `KEY=value`.
+    MissingEquals { line: String },
+    /// A line of the form `=value`.
+    EmptyKey { line: String },
+    /// A variable name that is not a valid environment variable name.
+    InvalidKey { key: String },
+    /// A quoted value without its closing quote.
+    UnclosedQuote { value: String },
+    /// A `#@name` directive given without the value it needs.
+    MissingDirectiveValue { directive: String },
+    /// A `#@name: value` directive with a value it doesn't accept.
+    InvalidDirectiveValue { directive: String, reason: String },
+    /// A `#@name` directive kraven doesn't know.
+    UnknownDirective { directive: String },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidName { name, reason } => {
+                write!(f, "Invalid profile name '{name}': {reason}")
+            }
+            Self::NotFound { name } => write!(f, "Profile '{name}' does not exist."),
+            Self::Parse { path, line, kind } => write!(f, "{}:{line}: {kind}", path.display()),
+            Self::NoProjectStore => write!(
+                f,
+                "No project profile directory found \
+                 (looked for .kraven/profiles above the current directory)"
+            ),
+            Self::NoProfileDir => write!(f, "Could not determine config directory"),
+            Self::Io { path, .. } => write!(f, "Failed to read {}", path.display()),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ParseErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingEquals { line } => write!(f, "Invalid line (missing '='): {line}"),
+            Self::EmptyKey { line } => write!(f, "Empty variable name: {line}"),
+            Self::InvalidKey { key } => write!(
+                f,
+                "Invalid variable name '{key}': must contain only alphanumeric characters \
+                 and underscores, and not start with a digit"
+            ),
+            Self::UnclosedQuote { value } => write!(f, "Unclosed quote in value: {value}"),
+            Self::MissingDirectiveValue { directive } => {
+                write!(f, "Directive '#@{directive}' requires a value")
+            }
+            Self::InvalidDirectiveValue { directive, reason } => {
+                write!(f, "Invalid '#@{directive}': {reason}")
+            }
+            Self::UnknownDirective { directive } => write!(f, "Unknown directive '#@{directive}'"),
+        }
+    }
+}
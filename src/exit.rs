@@ -0,0 +1,263 @@
+//! Exit codes and error reporting of the command line.
+//!
+//! Commands fail with `anyhow` errors. Those caused by a [`KravenError`], or by
+//! a library error it has a variant for, exit with the code of that variant so
+//! wrappers can tell failures apart; everything else exits with 1.
+
+use serde_json::{json, Value};
+use std::fmt;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use kraven::ParseErrorKind;
+
+/// Exit code of errors without a [`KravenError`] variant.
+const GENERAL_EXIT_CODE: u8 = 1;
+
+/// How errors are printed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum OutputFormat {
+    /// A message for people
+    #[default]
+    Text,
+    /// A JSON object on a single line, for scripts
+    Json,
+}
+
+/// A failure with its own exit code.
+///
+/// | Code | Variant            |
+/// |------|--------------------|
+/// | 1    | any other error    |
+/// | 2    | invalid command line (reported by clap) |
+/// | 3    | [`NotFound`](Self::NotFound) |
+/// | 4    | [`Parse`](Self::Parse) |
+/// | 5    | [`InvalidName`](Self::InvalidName) |
+/// | 6    | [`NestedSession`](Self::NestedSession) |
+/// | 7    | [`Io`](Self::Io) |
+/// | 8    | [`EditorFailed`](Self::EditorFailed) |
+///
+/// `activate` and `exec` exit with the status of their shell or command instead.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KravenError {
+    /// The profile does not exist.
+    NotFound { name: String },
+    /// A profile file has a syntax error.
+    Parse {
+        path: PathBuf,
+        line: usize,
+        kind: ParseErrorKind,
+    },
+    /// The profile name is not allowed.
+    InvalidName { name: String, reason: String },
+    /// `activate` ran inside a session while nesting is denied.
+    NestedSession { active: String },
+    /// Reading or writing a file failed.
+    Io { path: Option<PathBuf> },
+    /// The editor could not be started or exited with an error.
+    EditorFailed { editor: String, reason: String },
+}
+
+impl KravenError {
+    /// Finds the first error in the chain of `err` that has a variant.
+    pub fn find(err: &anyhow::Error) -> Option<Self> {
+        err.chain().find_map(|cause| {
+            if let Some(err) = cause.downcast_ref::<Self>() {
+                return Some(err.clone());
+            }
+            if let Some(err) = cause.downcast_ref::<kraven::Error>() {
+                return Self::from_library(err);
+            }
+            cause
+                .downcast_ref::<std::io::Error>()
+                .map(|_| Self::Io { path: None })
+        })
+    }
+
+    fn from_library(err: &kraven::Error) -> Option<Self> {
+        Some(match err {
+            kraven::Error::NotFound { name } => Self::NotFound { name: name.clone() },
+            kraven::Error::Parse { path, line, kind } => Self::Parse {
+                path: path.clone(),
+                line: *line,
+                kind: kind.clone(),
+            },
+            kraven::Error::InvalidName { name, reason } => Self::InvalidName {
+                name: name.clone(),
+                reason: reason.clone(),
+            },
+            kraven::Error::Io { path, .. } => Self::Io {
+                path: Some(path.clone()),
+            },
+            _ => return None,
+        })
+    }
+
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            Self::NotFound { .. } => 3,
+            Self::Parse { .. } => 4,
+            Self::InvalidName { .. } => 5,
+            Self::NestedSession { .. } => 6,
+            Self::Io { .. } => 7,
+            Self::EditorFailed { .. } => 8,
+        }
+    }
+
+    /// The name of the variant in JSON output.
+    fn kind(&self) -> &'static str {
+        match self {
+            Self::NotFound { .. } => "not_found",
+            Self::Parse { .. } => "parse",
+            Self::InvalidName { .. } => "invalid_name",
+            Self::NestedSession { .. } => "nested_session",
+            Self::Io { .. } => "io",
+            Self::EditorFailed { .. } => "editor_failed",
+        }
+    }
+
+    /// The fields of the variant in JSON output.
+    fn details(&self) -> Value {
+        match self {
+            Self::NotFound { name } => json!({ "profile": name }),
+            Self::Parse { path, line, kind } => json!({
+                "path": path.display().to_string(),
+                "line": line,
+                "detail": kind.to_string(),
+            }),
+            Self::InvalidName { name, reason } => json!({ "profile": name, "reason": reason }),
+            Self::NestedSession { active } => json!({ "active_profile": active }),
+            Self::Io { path } => {
+                json!({ "path": path.as_ref().map(|path| path.display().to_string()) })
+            }
+            Self::EditorFailed { editor, reason } => json!({ "editor": editor, "reason": reason }),
+        }
+    }
+}
+
+impl fmt::Display for KravenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotFound { name } => write!(f, "Profile '{name}' does not exist."),
+            Self::Parse { path, line, kind } => write!(f, "{}:{line}: {kind}", path.display()),
+            Self::InvalidName { name, reason } => {
+                write!(f, "Invalid profile name '{name}': {reason}")
+            }
+            Self::NestedSession { active } => write!(
+                f,
+                "Already in kraven session for profile '{active}'.\n\
+                 Exit the current session first with 'exit' or Ctrl+D."
+            ),
+            Self::Io { path: Some(path) } => write!(f, "Failed to access {}", path.display()),
+            Self::Io { path: None } => write!(f, "I/O error"),
+            Self::EditorFailed { editor, reason } => write!(f, "Editor '{editor}' {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for KravenError {}
+
+/// Prints `err` in the given format, returning the exit code for it.
+pub fn report(err: &anyhow::Error, format: OutputFormat) -> ExitCode {
+    let typed = KravenError::find(err);
+    let code = typed
+        .as_ref()
+        .map_or(GENERAL_EXIT_CODE, KravenError::exit_code);
+    match format {
+        OutputFormat::Text => eprintln!("Error: {err:?}"),
+        OutputFormat::Json => eprintln!("{}", to_json(err, typed.as_ref(), code)),
+    }
+    ExitCode::from(code)
+}
+
+fn to_json(err: &anyhow::Error, typed: Option<&KravenError>, code: u8) -> Value {
+    let mut error = json!({
+        "kind": typed.map_or("error", KravenError::kind),
+        "code": code,
+        "message": format!("{err:#}"),
+    });
+    if let (Some(Value::Object(details)), Some(fields)) =
+        (typed.map(KravenError::details), error.as_object_mut())
+    {
+        fields.extend(details);
+    }
+    json!({ "error": error })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+    use std::path::Path;
+
+    #[test]
+    fn test_find_through_context() {
+        let err = Err::<(), _>(kraven::Error::NotFound {
+            name: "dev".to_string(),
+        })
+        .context("Failed to show profile")
+        .unwrap_err();
+        let typed = KravenError::find(&err).unwrap();
+        assert_eq!(
+            typed,
+            KravenError::NotFound {
+                name: "dev".to_string()
+            }
+        );
+        assert_eq!(typed.exit_code(), 3);
+
+        assert_eq!(KravenError::find(&anyhow::anyhow!("other")), None);
+    }
+
+    #[test]
+    fn test_parse_error() {
+        let err = kraven::Profile::parse("A=1\nB\n", Path::new("dev")).unwrap_err();
+        let err = anyhow::Error::from(err);
+        let typed = KravenError::find(&err).unwrap();
+        assert_eq!(typed.exit_code(), 4);
+
+        let json = to_json(&err, Some(&typed), typed.exit_code());
+        assert_eq!(json["error"]["kind"], "parse");
+        assert_eq!(json["error"]["code"], 4);
+        assert_eq!(json["error"]["path"], "dev");
+        assert_eq!(json["error"]["line"], 2);
+        assert_eq!(
+            json["error"]["message"],
+            "dev:2: Invalid line (missing '='): B"
+        );
+    }
+
+    #[test]
+    fn test_exit_codes_are_distinct() {
+        let errors = [
+            KravenError::NotFound {
+                name: String::new(),
+            },
+            KravenError::Parse {
+                path: PathBuf::new(),
+                line: 1,
+                kind: ParseErrorKind::EmptyKey {
+                    line: String::new(),
+                },
+            },
+            KravenError::InvalidName {
+                name: String::new(),
+                reason: String::new(),
+            },
+            KravenError::NestedSession {
+                active: String::new(),
+            },
+            KravenError::Io { path: None },
+            KravenError::EditorFailed {
+                editor: String::new(),
+                reason: String::new(),
+            },
+        ];
+        let mut codes: Vec<u8> = errors.iter().map(KravenError::exit_code).collect();
+        codes.extend([GENERAL_EXIT_CODE, 2]);
+        let count = codes.len();
+        codes.sort_unstable();
+        codes.dedup();
+        assert_eq!(codes.len(), count);
+    }
+}
@@ -0,0 +1,47 @@
+//! File operations shared by the commands.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Moves a file, also to another filesystem.
+///
+/// The global and project profile stores are often on different ones: the
+/// config directory and a checkout. There, the file is copied and the
+/// original removed.
+pub fn move_file(from: &Path, to: &Path) -> io::Result<()> {
+    match fs::rename(from, to) {
+        Err(err) if err.kind() == io::ErrorKind::CrossesDevices => copy_and_remove(from, to),
+        result => result,
+    }
+}
+
+fn copy_and_remove(from: &Path, to: &Path) -> io::Result<()> {
+    if let Err(err) = fs::copy(from, to) {
+        // Don't leave half a file behind
+        let _ = fs::remove_file(to);
+        return Err(err);
+    }
+    fs::remove_file(from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    #[test]
+    fn test_copy_and_remove() {
+        let dir = TempDir::new("files");
+        let (from, to) = (dir.join("from"), dir.join("to"));
+        fs::write(&from, "A=1\n").unwrap();
+
+        copy_and_remove(&from, &to).unwrap();
+        assert!(!from.exists());
+        assert_eq!(fs::read_to_string(&to).unwrap(), "A=1\n");
+
+        // A failed copy keeps the original
+        assert!(copy_and_remove(&to, &dir.join("missing/to")).is_err());
+        assert!(to.is_file());
+    }
+}
@@ -0,0 +1,298 @@
+//! Commands run around the use of a profile.
+//!
+//! Hooks are declared in the profile with `#@pre-activate:` and
+//! `#@post-deactivate:` directives, or as executable scripts named after the
+//! stage in `<store>/hooks/<profile>/`.
+
+use anyhow::{bail, Context, Result};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus};
+
+use crate::files;
+
+/// When a hook runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Stage {
+    /// Before the shell or command starts; failures abort it.
+    PreActivate,
+    /// After the shell or command exits.
+    PostDeactivate,
+}
+
+impl Stage {
+    pub const ALL: [Self; 2] = [Self::PreActivate, Self::PostDeactivate];
+
+    /// The directive and script name of the stage.
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::PreActivate => "pre-activate",
+            Self::PostDeactivate => "post-deactivate",
+        }
+    }
+
+    #[must_use]
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|stage| stage.name() == name)
+    }
+}
+
+/// A hook command declared in a profile.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Hook {
+    pub stage: Stage,
+    /// Shell command, run with `sh -c`.
+    pub command: String,
+    /// Whether a failure is only reported (`#@pre-activate?: ...`).
+    pub optional: bool,
+}
+
+/// The hooks of one profile.
+pub struct Hooks<'a> {
+    declared: &'a [Hook],
+    scripts_dir: PathBuf,
+}
+
+impl<'a> Hooks<'a> {
+    #[must_use]
+    pub fn new(declared: &'a [Hook], scripts_dir: PathBuf) -> Self {
+        Self {
+            declared,
+            scripts_dir,
+        }
+    }
+
+    /// Runs the declared hooks of `stage` in order, then the stage's script if present.
+    ///
+    /// `env` applies the profile environment to each hook. Hook output goes to
+    /// stderr so it never mixes with the output of `exec`.
+    pub fn run(&self, stage: Stage, env: &dyn Fn(&mut Command)) -> Result<()> {
+        for hook in self.declared.iter().filter(|hook| hook.stage == stage) {
+            let mut cmd = Command::new("sh");
+            cmd.arg("-c").arg(&hook.command);
+            env(&mut cmd);
+
+            match run_hook(cmd) {
+                Ok(status) if status.success() => {}
+                Ok(status) if hook.optional => {
+                    eprintln!(
+                        "kraven: optional {} hook failed ({status}): {}",
+                        stage.name(),
+                        hook.command
+                    );
+                }
+                Ok(status) => bail!("{} hook failed ({status}): {}", stage.name(), hook.command),
+                Err(err) => {
+                    return Err(err).with_context(|| format!("Failed to run {} hook", stage.name()))
+                }
+            }
+        }
+
+        if let Some(script) = self.script(stage) {
+            let mut cmd = Command::new(&script);
+            env(&mut cmd);
+            let status = run_hook(cmd)
+                .with_context(|| format!("Failed to run hook script: {}", script.display()))?;
+            if !status.success() {
+                bail!("Hook script failed ({status}): {}", script.display());
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Whether there is nothing to run.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.declared.is_empty()
+            && Stage::ALL
+                .into_iter()
+                .all(|stage| self.script(stage).is_none())
+    }
+
+    /// What runs, one line per hook, for the user to review.
+    #[must_use]
+    pub fn describe(&self) -> Vec<String> {
+        let declared = self.declared.iter().map(|hook| {
+            let optional = if hook.optional { "?" } else { "" };
+            format!("{}{optional}: {}", hook.stage.name(), hook.command)
+        });
+        let scripts = Stage::ALL.into_iter().filter_map(|stage| {
+            self.script(stage)
+                .map(|script| format!("{}: {}", stage.name(), script.display()))
+        });
+        declared.chain(scripts).collect()
+    }
+
+    /// A hash of the hook commands and the content of the scripts, which
+    /// changes whenever any of them does.
+    #[must_use]
+    pub fn fingerprint(&self) -> String {
+        let mut hasher = Sha256::new();
+        let mut field = |bytes: &[u8]| {
+            hasher.update((bytes.len() as u64).to_le_bytes());
+            hasher.update(bytes);
+        };
+        field(&(self.declared.len() as u64).to_le_bytes());
+        for hook in self.declared {
+            field(hook.stage.name().as_bytes());
+            field(&[u8::from(hook.optional)]);
+            field(hook.command.as_bytes());
+        }
+        for stage in Stage::ALL {
+            if let Some(script) = self.script(stage) {
+                field(stage.name().as_bytes());
+                field(&fs::read(&script).unwrap_or_default());
+            }
+        }
+        format!("sha256:{:x}", hasher.finalize())
+    }
+
+    /// The script of `stage`, if there is one.
+    fn script(&self, stage: Stage) -> Option<PathBuf> {
+        let script = self.scripts_dir.join(stage.name());
+        script.is_file().then_some(script)
+    }
+}
+
+/// Moves the hook scripts in `from` to `to`, replacing the scripts there.
+pub fn move_scripts(from: &Path, to: &Path) -> Result<()> {
+    transfer_scripts(from, to, files::move_file)?;
+    // Only succeeds once the directory is empty
+    let _ = fs::remove_dir(from);
+    Ok(())
+}
+
+/// Copies the hook scripts in `from` to `to`, replacing the scripts there.
+pub fn copy_scripts(from: &Path, to: &Path) -> Result<()> {
+    transfer_scripts(from, to, |source, target| {
+        fs::copy(source, target).map(drop)
+    })
+}
+
+fn transfer_scripts(
+    from: &Path,
+    to: &Path,
+    transfer: impl Fn(&Path, &Path) -> io::Result<()>,
+) -> Result<()> {
+    for stage in Stage::ALL {
+        let source = from.join(stage.name());
+        let target = to.join(stage.name());
+
+        // Scripts of an overwritten profile must not survive it
+        if target.is_file() {
+            fs::remove_file(&target)
+                .with_context(|| format!("Failed to remove {}", target.display()))?;
+        }
+        if !source.is_file() {
+            continue;
+        }
+
+        fs::create_dir_all(to)
+            .with_context(|| format!("Failed to create directory: {}", to.display()))?;
+        transfer(&source, &target)
+            .with_context(|| format!("Failed to transfer hook script: {}", source.display()))?;
+    }
+
+    Ok(())
+}
+
+fn run_hook(mut cmd: Command) -> io::Result<ExitStatus> {
+    cmd.stdout(io::stderr()).status()
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    fn hook(stage: Stage, command: &str, optional: bool) -> Hook {
+        Hook {
+            stage,
+            command: command.to_string(),
+            optional,
+        }
+    }
+
+    fn no_env(_: &mut Command) {}
+
+    #[test]
+    fn test_stage_names() {
+        assert_eq!(Stage::from_name("pre-activate"), Some(Stage::PreActivate));
+        assert_eq!(
+            Stage::from_name("post-deactivate"),
+            Some(Stage::PostDeactivate)
+        );
+        assert_eq!(Stage::from_name("post-activate"), None);
+    }
+
+    #[test]
+    fn test_failing_hook_aborts() {
+        let declared = [hook(Stage::PreActivate, "exit 1", false)];
+        let hooks = Hooks::new(&declared, PathBuf::from("/nonexistent"));
+        assert!(hooks.run(Stage::PreActivate, &no_env).is_err());
+        // Hooks of other stages are not run
+        assert!(hooks.run(Stage::PostDeactivate, &no_env).is_ok());
+    }
+
+    #[test]
+    fn test_optional_hook_failure_is_ignored() {
+        let declared = [hook(Stage::PreActivate, "exit 1", true)];
+        let hooks = Hooks::new(&declared, PathBuf::from("/nonexistent"));
+        assert!(hooks.run(Stage::PreActivate, &no_env).is_ok());
+    }
+
+    #[test]
+    fn test_hooks_see_profile_env() {
+        let declared = [hook(Stage::PreActivate, r#"test "$HOOK_VAR" = set"#, false)];
+        let hooks = Hooks::new(&declared, PathBuf::from("/nonexistent"));
+        assert!(hooks.run(Stage::PreActivate, &no_env).is_err());
+        assert!(hooks
+            .run(Stage::PreActivate, &|cmd| {
+                cmd.env("HOOK_VAR", "set");
+            })
+            .is_ok());
+    }
+
+    #[test]
+    fn test_hook_script() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = TempDir::new("hooks");
+        let script = dir.join("post-deactivate");
+        fs::write(&script, "#!/bin/sh\nexit 2\n").unwrap();
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+
+        let hooks = Hooks::new(&[], dir.to_path_buf());
+        assert!(hooks.run(Stage::PreActivate, &no_env).is_ok());
+        assert!(hooks.run(Stage::PostDeactivate, &no_env).is_err());
+    }
+
+    #[test]
+    fn test_transfer_scripts() {
+        let dir = TempDir::new("hooks-mv");
+        let (from, to) = (dir.join("old"), dir.join("new"));
+        fs::create_dir_all(&from).unwrap();
+        fs::create_dir_all(&to).unwrap();
+        fs::write(from.join("pre-activate"), "echo old").unwrap();
+        fs::write(to.join("post-deactivate"), "echo overwritten").unwrap();
+
+        copy_scripts(&from, &to).unwrap();
+        assert!(from.join("pre-activate").is_file());
+        assert!(to.join("pre-activate").is_file());
+        assert!(!to.join("post-deactivate").exists());
+
+        fs::remove_dir_all(&to).unwrap();
+        move_scripts(&from, &to).unwrap();
+        assert!(!from.exists());
+        assert_eq!(
+            fs::read_to_string(to.join("pre-activate")).unwrap(),
+            "echo old"
+        );
+    }
+}
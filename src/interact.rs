@@ -0,0 +1,232 @@
+//! Questions asked on the terminal.
+//!
+//! Commands take a [`Prompter`] instead of reading stdin directly, so tests
+//! can script the answers.
+
+use anyhow::{bail, Context, Result};
+use std::io::{self, BufRead, IsTerminal, Write};
+
+/// Asks the user for input.
+pub trait Prompter {
+    /// Shows `prompt` and returns the line typed by the user, without the newline.
+    ///
+    /// Fails at the end of input, so callers that ask again stop there.
+    fn read_line(&mut self, prompt: &str) -> Result<String>;
+
+    /// Like [`Prompter::read_line`], without echoing the input.
+    fn read_secret(&mut self, prompt: &str) -> Result<String>;
+
+    /// Whether a user is there to answer.
+    fn is_interactive(&self) -> bool;
+}
+
+/// Prompts on stderr and reads answers from stdin.
+pub struct Terminal;
+
+impl Prompter for Terminal {
+    fn read_line(&mut self, prompt: &str) -> Result<String> {
+        eprint!("{prompt}");
+        io::stderr().flush()?;
+
+        let mut input = String::new();
+        let read = io::stdin()
+            .lock()
+            .read_line(&mut input)
+            .context("Failed to read from stdin")?;
+        if read == 0 {
+            // Ctrl-D or the end of piped input: there will never be an answer
+            if io::stdin().is_terminal() {
+                eprintln!();
+            }
+            bail!("No answer: end of input");
+        }
+        Ok(input.trim_end_matches(['\r', '\n']).to_string())
+    }
+
+    fn read_secret(&mut self, prompt: &str) -> Result<String> {
+        let echo_off = EchoOff::new();
+        let input = self.read_line(prompt);
+        if echo_off.is_some() {
+            // The newline typed by the user was not echoed either
+            eprintln!();
+        }
+        input
+    }
+
+    fn is_interactive(&self) -> bool {
+        io::stdin().is_terminal()
+    }
+}
+
+/// Turns off terminal echo on stdin until dropped.
+#[cfg(unix)]
+struct EchoOff(libc::termios);
+
+#[cfg(unix)]
+impl EchoOff {
+    /// Returns `None` when stdin is not a terminal.
+    fn new() -> Option<Self> {
+        let mut termios = std::mem::MaybeUninit::<libc::termios>::uninit();
+        // SAFETY: tcgetattr() fills in `termios` when it succeeds, and only then is it read.
+        let original = unsafe {
+            if libc::tcgetattr(libc::STDIN_FILENO, termios.as_mut_ptr()) != 0 {
+                return None;
+            }
+            termios.assume_init()
+        };
+
+        let mut silent = original;
+        silent.c_lflag &= !libc::ECHO;
+        // SAFETY: `silent` is a valid termios obtained from tcgetattr().
+        unsafe {
+            libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &raw const silent);
+        }
+        Some(Self(original))
+    }
+}
+
+#[cfg(unix)]
+impl Drop for EchoOff {
+    fn drop(&mut self) {
+        // SAFETY: restores the settings read by tcgetattr() in `new`.
+        unsafe {
+            libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &raw const self.0);
+        }
+    }
+}
+
+/// Echo cannot be turned off here, so secrets stay visible while typed.
+#[cfg(not(unix))]
+enum EchoOff {}
+
+#[cfg(not(unix))]
+impl EchoOff {
+    fn new() -> Option<Self> {
+        None
+    }
+}
+
+/// Asks a yes/no question that defaults to no.
+pub fn confirm(prompter: &mut dyn Prompter, question: &str) -> Result<bool> {
+    let input = prompter.read_line(&format!("{question} [y/N] "))?;
+    let input = input.trim().to_lowercase();
+    Ok(input == "y" || input == "yes")
+}
+
+/// Makes the user type the name of a protected profile before it is used.
+///
+/// `yes` skips the question, and is required when nobody can answer it.
+pub fn confirm_protected(
+    prompter: &mut dyn Prompter,
+    profile_name: &str,
+    protected: bool,
+    yes: bool,
+) -> Result<()> {
+    if !protected || yes {
+        return Ok(());
+    }
+
+    if !prompter.is_interactive() {
+        bail!("Profile '{profile_name}' is protected. Pass --yes to use it non-interactively.");
+    }
+
+    eprintln!("Profile '{profile_name}' is protected.");
+    let input = prompter.read_line("Type the profile name to continue: ")?;
+    if input.trim() != profile_name {
+        bail!("Aborted: the name did not match.");
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+pub mod testing {
+    use super::*;
+    use std::collections::VecDeque;
+
+    /// A prompter that replays canned answers.
+    pub struct Scripted {
+        pub answers: VecDeque<String>,
+        pub interactive: bool,
+        /// Prompts shown so far; those of secrets are prefixed with `secret: `.
+        pub prompts: Vec<String>,
+    }
+
+    impl Scripted {
+        pub fn new(answers: &[&str]) -> Self {
+            Self {
+                answers: answers.iter().map(ToString::to_string).collect(),
+                interactive: true,
+                prompts: Vec::new(),
+            }
+        }
+
+        #[must_use]
+        pub fn non_interactive() -> Self {
+            Self {
+                interactive: false,
+                ..Self::new(&[])
+            }
+        }
+    }
+
+    impl Prompter for Scripted {
+        fn read_line(&mut self, prompt: &str) -> Result<String> {
+            self.prompts.push(prompt.to_string());
+            self.answers.pop_front().context("No scripted answer left")
+        }
+
+        fn read_secret(&mut self, prompt: &str) -> Result<String> {
+            self.read_line(&format!("secret: {prompt}"))
+        }
+
+        fn is_interactive(&self) -> bool {
+            self.interactive
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::testing::Scripted;
+    use super::*;
+
+    #[test]
+    fn test_confirm() {
+        for (answer, expected) in [
+            ("y", true),
+            ("YES", true),
+            (" y ", true),
+            ("", false),
+            ("n", false),
+        ] {
+            let mut prompter = Scripted::new(&[answer]);
+            assert_eq!(confirm(&mut prompter, "Proceed?").unwrap(), expected);
+            assert_eq!(prompter.prompts, ["Proceed? [y/N] "]);
+        }
+    }
+
+    #[test]
+    fn test_unprotected_needs_no_confirmation() {
+        let mut prompter = Scripted::non_interactive();
+        assert!(confirm_protected(&mut prompter, "dev", false, false).is_ok());
+        assert!(prompter.prompts.is_empty());
+    }
+
+    #[test]
+    fn test_protected_requires_name() {
+        let mut prompter = Scripted::new(&["prod"]);
+        assert!(confirm_protected(&mut prompter, "prod", true, false).is_ok());
+
+        let mut prompter = Scripted::new(&["y"]);
+        assert!(confirm_protected(&mut prompter, "prod", true, false).is_err());
+    }
+
+    #[test]
+    fn test_protected_non_interactive_requires_yes() {
+        let mut prompter = Scripted::non_interactive();
+        assert!(confirm_protected(&mut prompter, "prod", true, false).is_err());
+        assert!(confirm_protected(&mut prompter, "prod", true, true).is_ok());
+        assert!(prompter.prompts.is_empty());
+    }
+}
@@ -0,0 +1,106 @@
+//! kraven's profile model as a library.
+//!
+//! This is the code behind the `kraven` command, for tools that want to read
+//! the same profiles: parsing profile files, finding them in the global and
+//! project profile directories, and running processes with their variables.
+//!
+//! ```no_run
+//! # fn main() -> kraven::Result<()> {
+//! let resolved = kraven::resolve_profile("work/aws-prod")?;
+//! let profile = kraven::Profile::load(&resolved.name, &resolved.path)?;
+//! for (key, value) in &profile.vars {
+//!     println!("{key} has {} bytes", value.len());
+//! }
+//!
+//! let status = kraven::Activation::new(&resolved.name, &profile)
+//!     .command("terraform")
+//!     .arg("plan")
+//!     .status();
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The items re-exported at the crate root and the [`diagnostic`] module follow
+//! semantic versioning. Everything else is internal to the command-line tool.
+
+mod activation;
+pub mod diagnostic;
+mod error;
+#[cfg(test)]
+mod testing;
+
+// Reachable through `__private` only, and hidden like it so that the lints for
+// documented public API leave them alone
+#[doc(hidden)]
+mod audit;
+#[doc(hidden)]
+mod bundle;
+#[doc(hidden)]
+mod config;
+#[doc(hidden)]
+mod digest;
+#[doc(hidden)]
+mod duration;
+#[doc(hidden)]
+mod editor;
+#[doc(hidden)]
+mod files;
+#[doc(hidden)]
+mod hooks;
+#[doc(hidden)]
+mod interact;
+#[doc(hidden)]
+mod mask;
+#[doc(hidden)]
+mod pattern;
+#[doc(hidden)]
+mod profile;
+#[doc(hidden)]
+mod prompt;
+#[doc(hidden)]
+mod redact;
+#[doc(hidden)]
+mod session;
+#[doc(hidden)]
+mod settings;
+#[doc(hidden)]
+mod shell;
+#[doc(hidden)]
+mod store;
+#[doc(hidden)]
+mod template;
+#[doc(hidden)]
+mod trust;
+
+pub use activation::Activation;
+pub use config::{
+    get_profile_dir as profile_dir, profile_stores, resolve_profile, ProfileSource, ProfileStore,
+    ResolvedProfile,
+};
+pub use diagnostic::Diagnostic;
+pub use error::{Error, ParseErrorKind, Result};
+pub use hooks::{Hook, Stage};
+pub use profile::{Metadata, Profile};
+
+/// Makes the internal modules reachable from `__private`.
+macro_rules! internal {
+    ($($module:ident),* $(,)?) => {
+        $(
+            pub mod $module {
+                pub use crate::$module::*;
+            }
+        )*
+    };
+}
+
+/// The internals of the `kraven` command.
+///
+/// Not part of the library: anything in here may change or disappear in any
+/// release, patch releases included.
+#[doc(hidden)]
+pub mod __private {
+    internal!(
+        audit, bundle, config, digest, duration, editor, files, hooks, interact, mask, pattern,
+        profile, prompt, redact, session, settings, shell, store, template, trust,
+    );
+}
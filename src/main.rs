@@ -1,12 +1,27 @@
 //! kraven - Manage named environment variable profiles.
 
 use anyhow::Result;
+use clap::builder::PossibleValuesParser;
 use clap::{Parser, Subcommand};
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::Duration;
+
+use kraven::__private::{
+    audit, bundle, config, digest, duration, editor, files, hooks, interact, mask, pattern,
+    profile, prompt, redact, session, settings, shell, store, template, trust,
+};
+
+use duration::parse_duration;
+use exit::OutputFormat;
+use settings::{MaskMode, MaskStrategy, NestingPolicy};
+use shell::Shell;
 
 mod commands;
 mod completions;
-mod config;
-mod profile;
+mod exit;
+#[cfg(test)]
+mod testing;
 
 /// CLI for managing named environment variable profiles.
 #[derive(Parser)]
@@ -16,6 +31,10 @@ mod profile;
 pub struct Cli {
     #[command(subcommand)]
     command: Commands,
+
+    /// How to print errors (given before the command); exit codes are listed in the README
+    #[arg(long, value_enum, default_value_t)]
+    output: OutputFormat,
 }
 
 #[derive(Subcommand)]
@@ -24,6 +43,38 @@ enum Commands {
     Activate {
         /// Name of the profile to activate
         profile: String,
+
+        /// Shell to spawn instead of the configured one
+        #[arg(long)]
+        shell: Option<String>,
+
+        /// Prompt prefix template, e.g. '[{profile}{stack}] ' (colours: {red}, {reset}, ...)
+        #[arg(long, conflicts_with = "no_prompt")]
+        prompt: Option<String>,
+
+        /// Leave the shell prompt untouched
+        #[arg(long)]
+        no_prompt: bool,
+
+        /// Start from an empty environment, keeping only the allowlisted variables
+        #[arg(long)]
+        isolate: bool,
+
+        /// Whether activating inside another kraven session is allowed
+        #[arg(long, value_enum)]
+        nesting: Option<NestingPolicy>,
+
+        /// Close the session after this long, e.g. 30m or 1h30m (overrides #@ttl)
+        #[arg(long, value_parser = parse_duration)]
+        ttl: Option<Duration>,
+
+        /// Skip the confirmation of protected profiles
+        #[arg(short, long)]
+        yes: bool,
+
+        /// Skip the profile's pre-activate and post-deactivate hooks
+        #[arg(long)]
+        no_hooks: bool,
     },
 
     /// Show how to exit the current kraven session
@@ -31,22 +82,138 @@ enum Commands {
 
     /// List available profiles
     #[command(visible_alias = "ls")]
-    List,
+    List {
+        /// Only list profiles inside this namespace (e.g. `work`)
+        #[arg(short, long)]
+        namespace: Option<String>,
+    },
 
-    /// Create or edit a profile using $EDITOR
+    /// Create a profile from a template, asking for its placeholder values
+    New {
+        /// Name of the profile to create
+        profile: String,
+
+        /// Template to fill in, from the templates/ directory
+        #[arg(short, long, visible_alias = "from-template")]
+        template: String,
+    },
+
+    /// Create or edit a profile in your editor
     Edit {
         /// Name of the profile to edit
         profile: String,
     },
 
+    /// Run a command with a profile's env vars
+    Exec {
+        /// Name of the profile to use
+        profile: String,
+
+        /// Skip the confirmation of protected profiles
+        #[arg(short, long)]
+        yes: bool,
+
+        /// Skip the profile's pre-activate and post-deactivate hooks
+        #[arg(long)]
+        no_hooks: bool,
+
+        /// Replace the profile's values with ***KEY*** in the command's output
+        #[arg(long)]
+        redact: bool,
+
+        /// Command to run, with its arguments
+        #[arg(last = true, required = true)]
+        command: Vec<String>,
+    },
+
+    /// Allow the hooks of a project profile to run, as they are now
+    Allow {
+        /// Name of the profile whose hooks to allow (e.g. `project:dev`)
+        profile: String,
+
+        /// Skip the confirmation
+        #[arg(short, long)]
+        yes: bool,
+    },
+
+    /// Print shell statements that export a profile's variables
+    Export {
+        /// Name of the profile to export
+        profile: String,
+
+        /// Shell syntax to generate (defaults to the configured shell)
+        #[arg(long, value_enum)]
+        shell: Option<Shell>,
+
+        /// Print statements that unset the variables instead
+        #[arg(long)]
+        unset: bool,
+    },
+
     /// Display profile contents
     Show {
         /// Name of the profile to show
         profile: String,
 
-        /// Mask sensitive values
+        /// Only show these variables (names or globs like 'AWS_*')
+        keys: Vec<String>,
+
+        /// Mask values: all of them (the default), only secret-looking keys (auto), or none
+        #[arg(
+            short,
+            long,
+            value_enum,
+            num_args = 0..=1,
+            require_equals = true,
+            default_missing_value = "always"
+        )]
+        mask: Option<MaskMode>,
+
+        /// How to mask values
+        #[arg(long, value_enum)]
+        mask_strategy: Option<MaskStrategy>,
+    },
+
+    /// Print the raw values of variables, for use in scripts
+    Get {
+        /// Name of the profile to read
+        profile: String,
+
+        /// Variables to print
+        #[arg(required = true)]
+        keys: Vec<String>,
+
+        /// Value printed for variables the profile doesn't have
         #[arg(short, long)]
-        mask: bool,
+        default: Option<String>,
+
+        /// Printed between the values of several variables
+        #[arg(short, long, default_value = "\n", hide_default_value = true)]
+        separator: String,
+    },
+
+    /// Find the variables of all profiles whose key or value contains a text or matches a glob
+    Grep {
+        /// Text to look for, or a glob like '*_TOKEN' matching the whole key or value
+        pattern: String,
+
+        /// Only search keys
+        #[arg(long, conflicts_with = "values")]
+        keys: bool,
+
+        /// Only search values
+        #[arg(long)]
+        values: bool,
+    },
+
+    /// Set a variable to a new value in many profiles at once, e.g. a rotated token
+    ReplaceValue {
+        /// Variable to change; only profiles that set it are changed
+        #[arg(long)]
+        key: String,
+
+        #[command(flatten)]
+        options: commands::replace_value::Options,
     },
 
     /// Remove a profile
@@ -60,26 +227,292 @@ enum Commands {
         force: bool,
     },
 
+    /// Rename a profile, along with its hooks
+    #[command(visible_alias = "mv")]
+    Rename {
+        /// Name of the profile to rename
+        profile: String,
+
+        /// New name for the profile
+        destination: String,
+
+        /// Overwrite the destination if it exists
+        #[arg(short, long)]
+        force: bool,
+    },
+
+    /// Copy a profile, along with its hooks
+    #[command(visible_alias = "cp")]
+    Copy {
+        /// Name of the profile to copy
+        profile: String,
+
+        /// Name of the new profile
+        destination: String,
+
+        /// Overwrite the destination if it exists
+        #[arg(short, long)]
+        force: bool,
+    },
+
     /// Show the currently active profile
     Current,
 
     /// Print shell completion setup instructions
     Completions,
+
+    /// Check the kraven setup for problems
+    Doctor {
+        /// Apply the fixes that can't lose data (permissions, missing directory)
+        #[arg(long)]
+        fix: bool,
+    },
+
+    /// Show the audit log of activations and profile changes
+    Log {
+        /// Only show records for this profile (`project:<name>` for a project profile)
+        #[arg(short, long)]
+        profile: Option<String>,
+
+        /// Only show records since a duration ago (e.g. 7d) or a date (YYYY-MM-DD, UTC)
+        #[arg(short, long)]
+        since: Option<String>,
+    },
+
+    /// Manage kraven settings
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+
+    /// Share profiles between machines as a single bundle file
+    Bundle {
+        #[command(subcommand)]
+        action: BundleAction,
+    },
+
+    /// Set up the profile directory
+    Store {
+        #[command(subcommand)]
+        action: StoreAction,
+    },
+
+    /// Pull profile changes from the store's git remote and push local ones
+    Sync {
+        /// Undo a sync that stopped on conflicts
+        #[arg(long)]
+        abort: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum ConfigAction {
+    /// Print the configured value of a setting
+    Get {
+        #[arg(value_parser = PossibleValuesParser::new(settings::KEYS))]
+        key: String,
+    },
+
+    /// Set a setting (lists are comma-separated)
+    Set {
+        #[arg(value_parser = PossibleValuesParser::new(settings::KEYS))]
+        key: String,
+        value: String,
+    },
+
+    /// Remove a setting, restoring its default
+    Unset {
+        #[arg(value_parser = PossibleValuesParser::new(settings::KEYS))]
+        key: String,
+    },
+
+    /// List all settings
+    List,
+
+    /// Print the path of the configuration file
+    Path,
+}
+
+impl ConfigAction {
+    fn run(self) -> Result<()> {
+        match self {
+            Self::Get { key } => commands::config::get(&key),
+            Self::Set { key, value } => commands::config::set(&key, &value),
+            Self::Unset { key } => commands::config::unset(&key),
+            Self::List => commands::config::list(),
+            Self::Path => commands::config::path(),
+        }
+    }
+}
+
+#[derive(Subcommand)]
+enum BundleAction {
+    /// Write profiles (all global profiles by default) to a bundle
+    Export {
+        /// Profiles to include
+        profiles: Vec<String>,
+
+        /// Bundle file to write
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Encrypt the bundle with a passphrase (read from `KRAVEN_BUNDLE_PASSPHRASE` if set)
+        #[arg(short, long)]
+        encrypt: bool,
+
+        /// Empty the values marked with #@secret, to share a safe skeleton
+        #[arg(long)]
+        exclude_secrets: bool,
+    },
+
+    /// Add the profiles of a bundle to the profile directory
+    Import {
+        /// Bundle file to read
+        file: PathBuf,
+
+        /// Overwrite existing profiles that differ from the bundled ones
+        #[arg(short, long, conflicts_with = "skip_existing")]
+        force: bool,
+
+        /// Import only the profiles that don't exist yet
+        #[arg(long)]
+        skip_existing: bool,
+    },
 }
 
-fn main() -> Result<()> {
+impl BundleAction {
+    fn run(self) -> Result<()> {
+        match self {
+            Self::Export {
+                profiles,
+                output,
+                encrypt,
+                exclude_secrets,
+            } => commands::bundle::export(&profiles, &output, encrypt, exclude_secrets),
+            Self::Import {
+                file,
+                force,
+                skip_existing,
+            } => commands::bundle::import(&file, force, skip_existing),
+        }
+    }
+}
+
+#[derive(Subcommand)]
+enum StoreAction {
+    /// Create the profile directory
+    Init {
+        /// Version the profiles with git, committing every change made by kraven
+        #[arg(long)]
+        git: bool,
+
+        /// Remote repository to sync with
+        #[arg(long, requires = "git")]
+        remote: Option<String>,
+    },
+}
+
+fn main() -> ExitCode {
     completions::init();
 
     let cli = Cli::parse();
 
-    match cli.command {
-        Commands::Activate { profile } => commands::activate::run(&profile),
+    match run(cli.command) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => exit::report(&err, cli.output),
+    }
+}
+
+fn run(command: Commands) -> Result<()> {
+    match command {
+        Commands::Activate {
+            profile,
+            shell,
+            prompt,
+            no_prompt,
+            isolate,
+            nesting,
+            ttl,
+            yes,
+            no_hooks,
+        } => commands::activate::run(
+            &profile,
+            &commands::activate::Options {
+                shell,
+                prompt,
+                no_prompt,
+                isolate,
+                nesting,
+                ttl,
+                yes,
+                no_hooks,
+            },
+        ),
         Commands::Deactivate => commands::deactivate::run(),
-        Commands::List => commands::list::run(),
+        Commands::List { namespace } => commands::list::run(namespace.as_deref()),
+        Commands::New { profile, template } => commands::new::run(&profile, &template),
         Commands::Edit { profile } => commands::edit::run(&profile),
-        Commands::Show { profile, mask } => commands::show::run(&profile, mask),
+        Commands::Exec {
+            profile,
+            yes,
+            no_hooks,
+            redact,
+            command,
+        } => commands::exec::run(
+            &profile,
+            &command,
+            &commands::exec::Options {
+                yes,
+                no_hooks,
+                redact,
+            },
+        ),
+        Commands::Allow { profile, yes } => commands::allow::run(&profile, yes),
+        Commands::Export {
+            profile,
+            shell,
+            unset,
+        } => commands::export::run(&profile, shell, unset),
+        Commands::Show {
+            profile,
+            keys,
+            mask,
+            mask_strategy,
+        } => commands::show::run(&profile, &keys, mask, mask_strategy),
+        Commands::Get {
+            profile,
+            keys,
+            default,
+            separator,
+        } => commands::get::run(&profile, &keys, default.as_deref(), &separator),
+        Commands::Grep {
+            pattern,
+            keys,
+            values,
+        } => commands::grep::run(&pattern, keys, values),
+        Commands::ReplaceValue { key, options } => commands::replace_value::run(&key, &options),
         Commands::Remove { profile, force } => commands::remove::run(&profile, force),
+        Commands::Rename {
+            profile,
+            destination,
+            force,
+        } => commands::rename::run(&profile, &destination, force),
+        Commands::Copy {
+            profile,
+            destination,
+            force,
+        } => commands::copy::run(&profile, &destination, force),
         Commands::Current => commands::current::run(),
         Commands::Completions => commands::completions::run(),
+        Commands::Doctor { fix } => commands::doctor::run(fix),
+        Commands::Log { profile, since } => {
+            commands::log::run(profile.as_deref(), since.as_deref())
+        }
+        Commands::Config { action } => action.run(),
+        Commands::Bundle { action } => action.run(),
+        Commands::Store { action } => match action {
+            StoreAction::Init { git, remote } => commands::store::init(git, remote.as_deref()),
+        },
+        Commands::Sync { abort } => commands::sync::run(abort),
     }
 }
@@ -0,0 +1,250 @@
+//! Hiding variable values on screen.
+
+use anyhow::{anyhow, bail, Context, Result};
+use std::fmt::Write as _;
+use std::fs;
+use std::io::{ErrorKind, Write};
+use std::path::Path;
+use std::sync::OnceLock;
+
+use crate::config;
+use crate::digest;
+use crate::pattern;
+use crate::settings::{MaskMode, MaskStrategy};
+
+/// Keys masked by [`MaskMode::Auto`], matched case-insensitively.
+pub const SECRET_KEY_PATTERNS: &[&str] = &["*_TOKEN", "*_SECRET", "*PASSWORD*", "*_KEY"];
+
+/// Values shorter than this are masked completely by [`MaskStrategy::Partial`].
+const PARTIAL_MIN_LEN: usize = 12;
+
+/// Characters shown at each end by [`MaskStrategy::Partial`].
+const PARTIAL_SHOWN: usize = 2;
+
+/// Hex digits of the fingerprint shown by [`MaskStrategy::Hash`].
+const FINGERPRINT_LEN: usize = 8;
+
+/// Random bytes in a new fingerprint key.
+const KEY_LEN: usize = 32;
+
+/// Decides which values of a profile are masked.
+#[derive(Debug, Clone, Copy)]
+pub struct Policy<'a> {
+    pub mode: MaskMode,
+    pub strategy: MaskStrategy,
+    /// Keys marked with `#@secret` in the profile.
+    pub secrets: &'a [String],
+}
+
+impl Policy<'_> {
+    /// Whether the value of `key` is masked.
+    #[must_use]
+    pub fn masks(&self, key: &str) -> bool {
+        match self.mode {
+            MaskMode::Never => false,
+            MaskMode::Always => true,
+            MaskMode::Auto => self.secrets.iter().any(|secret| secret == key) || is_secret_key(key),
+        }
+    }
+
+    /// Returns `value` masked if the policy masks `key`.
+    #[must_use]
+    pub fn apply(&self, key: &str, value: &str) -> Option<String> {
+        self.masks(key).then(|| mask(value, self.strategy))
+    }
+}
+
+/// Whether `key` looks like it holds a secret.
+#[must_use]
+pub fn is_secret_key(key: &str) -> bool {
+    let key = key.to_ascii_uppercase();
+    SECRET_KEY_PATTERNS
+        .iter()
+        .any(|pattern| pattern::matches(pattern, &key))
+}
+
+/// Masks a value with the given strategy.
+#[must_use]
+pub fn mask(value: &str, strategy: MaskStrategy) -> String {
+    let char_count = value.chars().count();
+    match strategy {
+        // A fixed width, so not even the length shows
+        MaskStrategy::Full => "********".to_string(),
+        MaskStrategy::Partial if char_count < PARTIAL_MIN_LEN => "*".repeat(char_count),
+        MaskStrategy::Partial => {
+            let start: String = value.chars().take(PARTIAL_SHOWN).collect();
+            let end: String = value.chars().skip(char_count - PARTIAL_SHOWN).collect();
+            let middle = "*".repeat(char_count - 2 * PARTIAL_SHOWN);
+            format!("{start}{middle}{end}")
+        }
+        MaskStrategy::Length => format!("<{char_count} chars>"),
+        // Equal values have equal fingerprints, so profiles can be compared
+        MaskStrategy::Hash => match fingerprint_key() {
+            Some(key) => fingerprint(key, value),
+            None => mask(value, MaskStrategy::Full),
+        },
+    }
+}
+
+/// Fingerprints a value with a secret key, so short values can't be guessed from it.
+fn fingerprint(key: &[u8], value: &str) -> String {
+    let hash = digest::keyed_hash(key, value.as_bytes());
+    format!("hmac:{}…", &hash[..FINGERPRINT_LEN])
+}
+
+/// The user's fingerprint key, read once per run.
+///
+/// Without a key, values are masked completely rather than fingerprinted.
+fn fingerprint_key() -> Option<&'static [u8]> {
+    static KEY: OnceLock<Option<Vec<u8>>> = OnceLock::new();
+    KEY.get_or_init(|| {
+        config::get_mask_key_path()
+            .and_then(|path| load_or_create_key(&path))
+            .inspect_err(|err| eprintln!("Warning: {err:#}; masking values completely"))
+            .ok()
+    })
+    .as_deref()
+}
+
+/// Reads the key at `path`, creating a random one readable only by the user first.
+fn load_or_create_key(path: &Path) -> Result<Vec<u8>> {
+    match fs::read(path) {
+        Ok(key) if key.len() >= KEY_LEN => return Ok(key),
+        Ok(_) => bail!("The mask key {} is too short", path.display()),
+        Err(err) if err.kind() == ErrorKind::NotFound => {}
+        Err(err) => return Err(err).with_context(|| format!("Failed to read {}", path.display())),
+    }
+
+    let mut bytes = [0u8; KEY_LEN];
+    getrandom::getrandom(&mut bytes)
+        .map_err(|err| anyhow!("Failed to generate a mask key: {err}"))?;
+    let key = bytes.iter().fold(String::new(), |mut key, byte| {
+        let _ = write!(key, "{byte:02x}");
+        key
+    });
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create directory: {}", parent.display()))?;
+    }
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    match options.open(path) {
+        Ok(mut file) => file
+            .write_all(key.as_bytes())
+            .with_context(|| format!("Failed to write {}", path.display()))?,
+        // Another kraven created it first
+        Err(err) if err.kind() == ErrorKind::AlreadyExists => {
+            return fs::read(path).with_context(|| format!("Failed to read {}", path.display()))
+        }
+        Err(err) => return Err(err).with_context(|| format!("Failed to write {}", path.display())),
+    }
+    Ok(key.into_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    #[test]
+    fn test_partial_empty() {
+        assert_eq!(mask("", MaskStrategy::Partial), "");
+    }
+
+    #[test]
+    fn test_partial_short() {
+        // Short secrets show nothing but their length
+        assert_eq!(mask("ab", MaskStrategy::Partial), "**");
+        assert_eq!(mask("abcdef", MaskStrategy::Partial), "******");
+        assert_eq!(mask("hunter2hunt", MaskStrategy::Partial), "***********");
+    }
+
+    #[test]
+    fn test_partial_long() {
+        assert_eq!(mask("secretkey123", MaskStrategy::Partial), "se********23");
+    }
+
+    #[test]
+    fn test_partial_unicode() {
+        // This would panic with byte indexing
+        assert_eq!(
+            mask("🔑secret-café🔒", MaskStrategy::Partial),
+            "🔑s*********é🔒"
+        );
+    }
+
+    #[test]
+    fn test_other_strategies() {
+        assert_eq!(mask("ab", MaskStrategy::Full), "********");
+        assert_eq!(mask("secretkey123", MaskStrategy::Full), "********");
+        assert_eq!(mask("café", MaskStrategy::Length), "<4 chars>");
+    }
+
+    #[test]
+    fn test_fingerprint() {
+        let key = [7u8; KEY_LEN];
+        let hunter2 = fingerprint(&key, "hunter2");
+        assert!(hunter2.starts_with("hmac:"), "{hunter2}");
+        assert_eq!(hunter2.chars().count(), "hmac:".len() + 8 + 1);
+        assert_eq!(hunter2, fingerprint(&key, "hunter2"));
+        assert_ne!(hunter2, fingerprint(&key, "hunter3"));
+
+        // Without the key, the fingerprint can't be checked against a guess
+        assert_ne!(hunter2, fingerprint(&[8u8; KEY_LEN], "hunter2"));
+        let unkeyed = digest::hash(b"hunter2");
+        assert!(!unkeyed.contains(&hunter2["hmac:".len()..hunter2.len() - "…".len()]));
+    }
+
+    #[test]
+    fn test_key_is_created_once() {
+        let dir = TempDir::new("mask-key");
+        let path = dir.join("config/mask-key");
+
+        let key = load_or_create_key(&path).unwrap();
+        assert_eq!(key.len(), 2 * KEY_LEN);
+        assert_eq!(load_or_create_key(&path).unwrap(), key);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        fs::write(&path, "short").unwrap();
+        assert!(load_or_create_key(&path).is_err());
+    }
+
+    #[test]
+    fn test_secret_keys() {
+        assert!(is_secret_key("GITHUB_TOKEN"));
+        assert!(is_secret_key("aws_secret_access_key"));
+        assert!(is_secret_key("DB_PASSWORD"));
+        assert!(is_secret_key("PASSWORD"));
+        assert!(is_secret_key("CLIENT_SECRET"));
+        assert!(!is_secret_key("AWS_REGION"));
+        assert!(!is_secret_key("KEYBOARD"));
+    }
+
+    #[test]
+    fn test_policy() {
+        let secrets = vec!["DSN".to_string()];
+        let policy = |mode| Policy {
+            mode,
+            strategy: MaskStrategy::Full,
+            secrets: &secrets,
+        };
+
+        let auto = policy(MaskMode::Auto);
+        assert!(auto.masks("API_KEY"));
+        assert!(auto.masks("DSN"));
+        assert!(!auto.masks("REGION"));
+        assert_eq!(auto.apply("REGION", "eu"), None);
+        assert_eq!(auto.apply("DSN", "x").as_deref(), Some("********"));
+
+        assert!(policy(MaskMode::Always).masks("REGION"));
+        assert!(!policy(MaskMode::Never).masks("API_KEY"));
+    }
+}
@@ -0,0 +1,69 @@
+//! Glob patterns for matching variable and profile names.
+
+/// Whether `text` matches a glob `pattern`, where `*` matches any run of
+/// characters and `?` any single character.
+#[must_use]
+pub fn matches(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+
+    // Iterative matching with backtracking to the last `*`
+    let (mut p, mut t) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match star {
+                Some((star_p, star_t)) => {
+                    p = star_p + 1;
+                    t = star_t + 1;
+                    star = Some((star_p, star_t + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Whether `pattern` has glob characters in it.
+#[must_use]
+pub fn is_glob(pattern: &str) -> bool {
+    pattern.contains(['*', '?'])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches() {
+        assert!(matches("AWS_*", "AWS_REGION"));
+        assert!(matches("AWS_*", "AWS_"));
+        assert!(!matches("AWS_*", "XAWS_REGION"));
+        assert!(matches("*_TOKEN", "GITHUB_TOKEN"));
+        assert!(!matches("*_TOKEN", "TOKEN"));
+        assert!(matches("*PASSWORD*", "DB_PASSWORD_FILE"));
+        assert!(matches("team-?", "team-a"));
+        assert!(!matches("team-?", "team-ab"));
+        assert!(matches("a*b*c", "axxbyyc"));
+        assert!(!matches("a*b*c", "axxbyy"));
+        assert!(matches("exact", "exact"));
+        assert!(!matches("exact", "exactly"));
+        assert!(matches("*", ""));
+    }
+
+    #[test]
+    fn test_is_glob() {
+        assert!(is_glob("AWS_*"));
+        assert!(is_glob("team-?"));
+        assert!(!is_glob("AWS_REGION"));
+    }
+}
@@ -1,34 +1,102 @@
-use anyhow::{bail, Context, Result};
 use std::collections::BTreeMap;
 use std::fs;
+use std::io::ErrorKind;
 use std::path::Path;
+use std::time::Duration;
 
+use crate::diagnostic::Diagnostic;
+use crate::duration::parse_duration;
+use crate::error::{Error, ParseErrorKind, Result};
+use crate::hooks::{Hook, Stage};
+
+/// Prefix of comment lines that carry profile metadata (e.g. `#@ttl: 30m`).
+const DIRECTIVE_PREFIX: &str = "#@";
+
+/// A parsed profile: its variables and the settings of its directives.
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub struct Profile {
     pub vars: BTreeMap<String, String>,
+    pub meta: Metadata,
+    /// Lines that were ignored, such as unknown directives; worth reporting.
+    pub warnings: Vec<Diagnostic>,
+}
+
+/// Settings declared in a profile through `#@name: value` directive comments.
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct Metadata {
+    /// Maximum lifetime of an activated session (`#@ttl: 30m`).
+    pub ttl: Option<Duration>,
+    /// Whether using the profile must be confirmed (`#@protected` or `#@confirm: true`).
+    pub protected: bool,
+    /// Commands run before and after use (`#@pre-activate: ...`, `#@post-deactivate?: ...`).
+    pub hooks: Vec<Hook>,
+    /// Variables holding secret values (`#@secret: KEY, OTHER_KEY`).
+    pub secrets: Vec<String>,
 }
 
 impl Profile {
     /// Parse profile content from a string.
+    ///
+    /// Fails with the first problem of the file; [`Profile::check`] reports all of them.
     pub fn parse(content: &str, path: &Path) -> Result<Self> {
-        let vars = parse_env_file(content, path)?;
-        Ok(Self { vars })
+        Self::check(content).map_err(|diagnostics| {
+            let first = diagnostics
+                .into_iter()
+                .next()
+                .expect("at least one diagnostic");
+            Error::Parse {
+                path: path.to_path_buf(),
+                line: first.line,
+                kind: first.kind,
+            }
+        })
+    }
+
+    /// Parse profile content, collecting every problem of the file.
+    ///
+    /// The diagnostics are sorted by position, and never empty.
+    pub fn check(content: &str) -> std::result::Result<Self, Vec<Diagnostic>> {
+        let mut diagnostics = Vec::new();
+        let mut warnings = Vec::new();
+        let vars = parse_env_file(content, &mut diagnostics);
+        let meta = parse_metadata(content, &mut diagnostics, &mut warnings);
+        if diagnostics.is_empty() {
+            Ok(Self {
+                vars,
+                meta,
+                warnings,
+            })
+        } else {
+            diagnostics.sort_by_key(|diagnostic| (diagnostic.line, diagnostic.column));
+            Err(diagnostics)
+        }
     }
 
     /// Load a profile from the given path.
     pub fn load(name: &str, path: &Path) -> Result<Self> {
-        let content =
-            fs::read_to_string(path).with_context(|| format!("Failed to read profile '{name}'"))?;
+        let content = fs::read_to_string(path).map_err(|source| match source.kind() {
+            ErrorKind::NotFound => Error::NotFound {
+                name: name.to_string(),
+            },
+            _ => Error::Io {
+                path: path.to_path_buf(),
+                source,
+            },
+        })?;
         Self::parse(&content, path)
     }
 }
 
-fn parse_env_file(content: &str, path: &Path) -> Result<BTreeMap<String, String>> {
+fn parse_env_file(content: &str, diagnostics: &mut Vec<Diagnostic>) -> BTreeMap<String, String> {
     let mut vars = BTreeMap::new();
 
-    for (line_num, line) in content.lines().enumerate() {
+    for (line_num, raw_line) in content.lines().enumerate() {
         let line_num = line_num + 1; // 1-indexed for error messages
-        let line = line.trim();
+        let line = raw_line.trim();
+        let mut report =
+            |part: &str, kind| diagnostics.push(Diagnostic::new(line_num, raw_line, part, kind));
 
         // Skip empty lines and comments
         if line.is_empty() || line.starts_with('#') {
@@ -37,10 +105,13 @@ fn parse_env_file(content: &str, path: &Path) -> Result<BTreeMap<String, String>
 
         // Find the first '=' to split key and value
         let Some(eq_pos) = line.find('=') else {
-            bail!(
-                "{}:{line_num}: Invalid line (missing '='): {line}",
-                path.display(),
+            report(
+                line,
+                ParseErrorKind::MissingEquals {
+                    line: line.to_string(),
+                },
             );
+            continue;
         };
 
         let (key, value) = line.split_at(eq_pos);
@@ -49,23 +120,226 @@ fn parse_env_file(content: &str, path: &Path) -> Result<BTreeMap<String, String>
 
         // Validate key
         if key.is_empty() {
-            bail!("{}:{line_num}: Empty variable name: {line}", path.display(),);
+            report(
+                &line[eq_pos..=eq_pos],
+                ParseErrorKind::EmptyKey {
+                    line: line.to_string(),
+                },
+            );
+            continue;
         }
 
         if !is_valid_env_name(key) {
-            bail!(
-                "{}:{line_num}: Invalid variable name '{key}': must contain only alphanumeric characters and underscores, and not start with a digit",
-                path.display(),
+            report(
+                key,
+                ParseErrorKind::InvalidKey {
+                    key: key.to_string(),
+                },
             );
+            continue;
         }
 
         // Parse value (handle quotes)
-        let parsed_value = parse_value(value, path, line_num)?;
+        match parse_value(value) {
+            Ok(parsed_value) => {
+                vars.insert(key.to_string(), parsed_value);
+            }
+            Err(kind) => report(value, kind),
+        }
+    }
+
+    vars
+}
 
-        vars.insert(key.to_string(), parsed_value);
+/// Reads the directives of a profile. Unknown directives only produce a
+/// warning, so comments such as `#@todo rotate` don't break older profiles.
+fn parse_metadata(
+    content: &str,
+    diagnostics: &mut Vec<Diagnostic>,
+    warnings: &mut Vec<Diagnostic>,
+) -> Metadata {
+    let mut meta = Metadata::default();
+
+    for (line_num, raw_line) in content.lines().enumerate() {
+        let line_num = line_num + 1; // 1-indexed for error messages
+        let Some(directive) = raw_line.trim().strip_prefix(DIRECTIVE_PREFIX) else {
+            continue;
+        };
+
+        let (name, value) = match directive.split_once(':') {
+            Some((name, value)) => (name.trim(), Some(value.trim())),
+            None => (directive.trim(), None),
+        };
+        let mut report =
+            |part: &str, kind| diagnostics.push(Diagnostic::new(line_num, raw_line, part, kind));
+        let missing_value = || ParseErrorKind::MissingDirectiveValue {
+            directive: name.to_string(),
+        };
+        let invalid_value = |reason: String| ParseErrorKind::InvalidDirectiveValue {
+            directive: name.to_string(),
+            reason,
+        };
+        // A trailing '?' marks a hook whose failure is only reported
+        let (hook_name, optional) = match name.strip_suffix('?') {
+            Some(hook_name) => (hook_name, true),
+            None => (name, false),
+        };
+        let required = value.filter(|v| !v.is_empty());
+        if let Some(stage) = Stage::from_name(hook_name) {
+            match required {
+                Some(command) => meta.hooks.push(Hook {
+                    stage,
+                    command: command.to_string(),
+                    optional,
+                }),
+                None => report(name, missing_value()),
+            }
+            continue;
+        }
+
+        match (name, required) {
+            ("ttl" | "confirm" | "secret", None) => report(name, missing_value()),
+            ("ttl", Some(value)) => match parse_duration(value) {
+                Ok(ttl) => meta.ttl = Some(ttl),
+                Err(err) => report(value, invalid_value(format!("{err:#}"))),
+            },
+            ("protected", None) => meta.protected = true,
+            ("protected" | "confirm", Some(value)) => match parse_bool(value) {
+                Ok(protected) => meta.protected = protected,
+                Err(reason) => report(value, invalid_value(reason)),
+            },
+            ("secret", Some(value)) => {
+                for key in value.split(',').map(str::trim) {
+                    if is_valid_env_name(key) {
+                        meta.secrets.push(key.to_string());
+                    } else {
+                        report(key, invalid_value(format!("invalid variable name '{key}'")));
+                    }
+                }
+            }
+            _ => warnings.push(Diagnostic::new(
+                line_num,
+                raw_line,
+                name,
+                ParseErrorKind::UnknownDirective {
+                    directive: name.to_string(),
+                },
+            )),
+        }
+    }
+
+    meta
+}
+
+/// Empties the values of `keys`, keeping everything else of the profile as written.
+///
+/// Commented-out assignments of the keys are emptied too, as they often hold
+/// older values of the same secret.
+#[must_use]
+pub fn strip_values(content: &str, keys: &[String]) -> String {
+    let mut stripped = String::with_capacity(content.len());
+
+    for line in content.lines() {
+        let assignment = line.trim_start().trim_start_matches('#').trim_start();
+        let key = assignment
+            .split_once('=')
+            .map(|(key, _)| key.trim())
+            .filter(|key| keys.iter().any(|secret| secret == key));
+        match key {
+            Some(key) => {
+                let commented = assignment.len() < line.trim_start().len();
+                if commented {
+                    stripped.push_str(&line[..line.len() - assignment.len()]);
+                }
+                stripped.push_str(key);
+                stripped.push('=');
+            }
+            None => stripped.push_str(line),
+        }
+        stripped.push('\n');
     }
 
-    Ok(vars)
+    stripped
+}
+
+/// Quotes a value for a profile file unless it is made of plain characters only.
+#[must_use]
+pub fn quote_value(value: &str) -> String {
+    let is_plain = |c: char| c.is_ascii_alphanumeric() || "_-./:@,+%".contains(c);
+    if !value.is_empty() && value.chars().all(is_plain) {
+        return value.to_string();
+    }
+
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            _ => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Sets the value of `key` on every line that sets it, keeping the rest of the
+/// profile as written.
+#[must_use]
+pub fn replace_value(content: &str, key: &str, value: &str) -> String {
+    let mut replaced = String::with_capacity(content.len());
+
+    for line in content.lines() {
+        let trimmed = line.trim();
+        let line_key = trimmed
+            .split_once('=')
+            .filter(|_| !trimmed.starts_with('#'))
+            .map(|(line_key, _)| line_key.trim());
+        if line_key == Some(key) {
+            replaced.push_str(key);
+            replaced.push('=');
+            replaced.push_str(&quote_value(value));
+        } else {
+            replaced.push_str(line);
+        }
+        replaced.push('\n');
+    }
+
+    replaced
+}
+
+/// Returns the variables set more than once, with the 1-indexed lines setting them.
+///
+/// Parsing keeps the last value, so the earlier lines have no effect.
+#[must_use]
+pub fn duplicate_keys(content: &str) -> Vec<(String, Vec<usize>)> {
+    let mut lines: BTreeMap<&str, Vec<usize>> = BTreeMap::new();
+
+    for (line_num, line) in content.lines().enumerate() {
+        let trimmed = line.trim();
+        if trimmed.starts_with('#') {
+            continue;
+        }
+        if let Some((key, _)) = trimmed.split_once('=') {
+            lines.entry(key.trim()).or_default().push(line_num + 1);
+        }
+    }
+
+    lines
+        .into_iter()
+        .filter(|(key, lines)| !key.is_empty() && lines.len() > 1)
+        .map(|(key, lines)| (key.to_string(), lines))
+        .collect()
+}
+
+fn parse_bool(value: &str) -> Result<bool, String> {
+    match value {
+        "true" | "yes" => Ok(true),
+        "false" | "no" => Ok(false),
+        _ => Err(format!("expected true or false, got '{value}'")),
+    }
 }
 
 fn is_valid_env_name(name: &str) -> bool {
@@ -80,7 +354,7 @@ fn is_valid_env_name(name: &str) -> bool {
     is_valid_first && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
 }
 
-fn parse_value(value: &str, path: &Path, line_num: usize) -> Result<String> {
+fn parse_value(value: &str) -> Result<String, ParseErrorKind> {
     if value.is_empty() {
         return Ok(String::new());
     }
@@ -101,10 +375,9 @@ fn parse_value(value: &str, path: &Path, line_num: usize) -> Result<String> {
 
     // Check for unclosed quotes
     if first_char == '"' || first_char == '\'' {
-        bail!(
-            "{}:{line_num}: Unclosed quote in value: {value}",
-            path.display(),
-        );
+        return Err(ParseErrorKind::UnclosedQuote {
+            value: value.to_string(),
+        });
     }
 
     // Unquoted value
@@ -156,10 +429,22 @@ mod tests {
         PathBuf::from("test.env")
     }
 
+    fn vars(content: &str) -> Result<BTreeMap<String, String>, Vec<Diagnostic>> {
+        let mut diagnostics = Vec::new();
+        let vars = parse_env_file(content, &mut diagnostics);
+        diagnostics.is_empty().then_some(vars).ok_or(diagnostics)
+    }
+
+    fn metadata(content: &str) -> Result<Metadata, Vec<Diagnostic>> {
+        let mut diagnostics = Vec::new();
+        let meta = parse_metadata(content, &mut diagnostics, &mut Vec::new());
+        diagnostics.is_empty().then_some(meta).ok_or(diagnostics)
+    }
+
     #[test]
     fn test_parse_simple_values() {
         let content = "KEY=value\nANOTHER=123";
-        let vars = parse_env_file(content, &test_path()).unwrap();
+        let vars = vars(content).unwrap();
         assert_eq!(vars.get("KEY"), Some(&"value".to_string()));
         assert_eq!(vars.get("ANOTHER"), Some(&"123".to_string()));
     }
@@ -167,7 +452,7 @@ mod tests {
     #[test]
     fn test_parse_comments_and_empty_lines() {
         let content = "# This is a comment\nKEY=value\n\n# Another comment\nKEY2=value2";
-        let vars = parse_env_file(content, &test_path()).unwrap();
+        let vars = vars(content).unwrap();
         assert_eq!(vars.len(), 2);
         assert_eq!(vars.get("KEY"), Some(&"value".to_string()));
         assert_eq!(vars.get("KEY2"), Some(&"value2".to_string()));
@@ -176,42 +461,42 @@ mod tests {
     #[test]
     fn test_parse_double_quoted_value() {
         let content = "KEY=\"value with spaces\"";
-        let vars = parse_env_file(content, &test_path()).unwrap();
+        let vars = vars(content).unwrap();
         assert_eq!(vars.get("KEY"), Some(&"value with spaces".to_string()));
     }
 
     #[test]
     fn test_parse_single_quoted_value() {
         let content = "KEY='literal $value'";
-        let vars = parse_env_file(content, &test_path()).unwrap();
+        let vars = vars(content).unwrap();
         assert_eq!(vars.get("KEY"), Some(&"literal $value".to_string()));
     }
 
     #[test]
     fn test_parse_empty_value() {
         let content = "EMPTY=";
-        let vars = parse_env_file(content, &test_path()).unwrap();
+        let vars = vars(content).unwrap();
         assert_eq!(vars.get("EMPTY"), Some(&String::new()));
     }
 
     #[test]
     fn test_invalid_line_missing_equals() {
         let content = "INVALID_LINE";
-        let result = parse_env_file(content, &test_path());
+        let result = vars(content);
         assert!(result.is_err());
     }
 
     #[test]
     fn test_invalid_var_name_starts_with_digit() {
         let content = "1INVALID=value";
-        let result = parse_env_file(content, &test_path());
+        let result = vars(content);
         assert!(result.is_err());
     }
 
     #[test]
     fn test_valid_var_name_with_underscore() {
         let content = "_VALID=value\nALSO_VALID=value2";
-        let vars = parse_env_file(content, &test_path()).unwrap();
+        let vars = vars(content).unwrap();
         assert_eq!(vars.get("_VALID"), Some(&"value".to_string()));
         assert_eq!(vars.get("ALSO_VALID"), Some(&"value2".to_string()));
     }
@@ -219,14 +504,14 @@ mod tests {
     #[test]
     fn test_unclosed_quote() {
         let content = "KEY=\"unclosed";
-        let result = parse_env_file(content, &test_path());
+        let result = vars(content);
         assert!(result.is_err());
     }
 
     #[test]
     fn test_escaped_quotes_in_double_quoted() {
         let content = r#"KEY="value with \" escaped quote""#;
-        let vars = parse_env_file(content, &test_path()).unwrap();
+        let vars = vars(content).unwrap();
         assert_eq!(
             vars.get("KEY"),
             Some(&"value with \" escaped quote".to_string())
@@ -236,7 +521,7 @@ mod tests {
     #[test]
     fn test_escape_sequences() {
         let content = r#"KEY="line1\nline2\ttabbed\\backslash""#;
-        let vars = parse_env_file(content, &test_path()).unwrap();
+        let vars = vars(content).unwrap();
         assert_eq!(
             vars.get("KEY"),
             Some(&"line1\nline2\ttabbed\\backslash".to_string())
@@ -247,17 +532,202 @@ mod tests {
     fn test_single_quotes_no_escape() {
         // Single quotes should not process escapes
         let content = r"KEY='literal \n not newline'";
-        let vars = parse_env_file(content, &test_path()).unwrap();
+        let vars = vars(content).unwrap();
         assert_eq!(
             vars.get("KEY"),
             Some(&r"literal \n not newline".to_string())
         );
     }
 
+    #[test]
+    fn test_parse_ttl_directive() {
+        let content = "#@ttl: 1h30m\nKEY=value";
+        let meta = metadata(content).unwrap();
+        assert_eq!(meta.ttl, Some(Duration::from_secs(5_400)));
+
+        let vars = vars(content).unwrap();
+        assert_eq!(vars.len(), 1);
+    }
+
+    #[test]
+    fn test_parse_protected_directives() {
+        let parse = |content| metadata(content).unwrap().protected;
+        assert!(!parse("KEY=value"));
+        assert!(parse("#@protected"));
+        assert!(parse("#@confirm: true"));
+        assert!(!parse("#@confirm: false"));
+        assert!(!parse("#@protected: no"));
+    }
+
+    #[test]
+    fn test_parse_hook_directives() {
+        let content = "#@pre-activate: aws sso login\n#@post-deactivate?: echo bye";
+        let meta = metadata(content).unwrap();
+        assert_eq!(
+            meta.hooks,
+            [
+                Hook {
+                    stage: Stage::PreActivate,
+                    command: "aws sso login".to_string(),
+                    optional: false,
+                },
+                Hook {
+                    stage: Stage::PostDeactivate,
+                    command: "echo bye".to_string(),
+                    optional: true,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_secret_directive() {
+        let content = "#@secret: API_KEY, TOKEN\n#@secret: PASSWORD";
+        let meta = metadata(content).unwrap();
+        assert_eq!(meta.secrets, ["API_KEY", "TOKEN", "PASSWORD"]);
+    }
+
+    #[test]
+    fn test_strip_values() {
+        let content = "#@secret: TOKEN\n# TOKEN=in a comment\nTOKEN=\"abc\"\n\
+                       ##TOKEN = old\n# TOKENS=kept\nURL=https://example.com";
+        let secrets = ["TOKEN".to_string()];
+        assert_eq!(
+            strip_values(content, &secrets),
+            "#@secret: TOKEN\n# TOKEN=\nTOKEN=\n##TOKEN=\n# TOKENS=kept\nURL=https://example.com\n"
+        );
+    }
+
+    #[test]
+    fn test_quote_value() {
+        assert_eq!(quote_value("us-east-1"), "us-east-1");
+        assert_eq!(quote_value(""), "\"\"");
+        assert_eq!(quote_value("a b"), "\"a b\"");
+        assert_eq!(quote_value("'x'"), "\"'x'\"");
+    }
+
+    #[test]
+    fn test_replace_value() {
+        let content =
+            "#@secret: TOKEN\n# TOKEN=old\nTOKEN=old\nURL=x\n  TOKEN = older\nTOKENS=keep";
+        let replaced = replace_value(content, "TOKEN", "new \"one\"\n");
+        assert_eq!(
+            replaced,
+            "#@secret: TOKEN\n# TOKEN=old\nTOKEN=\"new \\\"one\\\"\\n\"\nURL=x\nTOKEN=\"new \\\"one\\\"\\n\"\nTOKENS=keep\n"
+        );
+        let profile = Profile::parse(&replaced, &test_path()).unwrap();
+        assert_eq!(profile.vars["TOKEN"], "new \"one\"\n");
+        assert_eq!(profile.vars["TOKENS"], "keep");
+    }
+
+    #[test]
+    fn test_duplicate_keys() {
+        let content = "A=1\nB=2\n# A=in a comment\n  A = 3\nB=2\nC=4\n";
+        assert_eq!(
+            duplicate_keys(content),
+            [("A".to_string(), vec![1, 4]), ("B".to_string(), vec![2, 5])]
+        );
+        assert!(duplicate_keys("A=1\nB=1\n").is_empty());
+    }
+
+    #[test]
+    fn test_invalid_directives() {
+        assert!(metadata("#@secret: 1KEY").is_err());
+        assert!(metadata("#@pre-activate:").is_err());
+        assert!(metadata("#@ttl").is_err());
+        assert!(metadata("#@confirm").is_err());
+        assert!(metadata("#@confirm: maybe").is_err());
+        assert!(metadata("#@ttl: soon").is_err());
+        // A space after '#' makes it a regular comment
+        assert!(metadata("# @unknown: 1").is_ok());
+    }
+
+    #[test]
+    fn test_unknown_directives_are_warnings() {
+        let profile = Profile::check(
+            "#@todo rotate
+A=1
+#@ttl?: 5m
+",
+        )
+        .unwrap();
+        assert_eq!(profile.vars["A"], "1");
+        assert_eq!(profile.meta.ttl, None);
+        let lines: Vec<_> = profile
+            .warnings
+            .iter()
+            .map(|warning| (warning.line, warning.kind.to_string()))
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                (1, "Unknown directive '#@todo rotate'".to_string()),
+                (3, "Unknown directive '#@ttl?'".to_string())
+            ]
+        );
+        assert!(Profile::check(
+            "A=1
+"
+        )
+        .unwrap()
+        .warnings
+        .is_empty());
+    }
+
+    #[test]
+    fn test_check_reports_every_problem() {
+        let content = "#@ttl: soon\nA=1\nBROKEN\n9X=1\n#@secret: A, 1B\nC=\"open\n";
+        let diagnostics = Profile::check(content).unwrap_err();
+        let positions: Vec<_> = diagnostics
+            .iter()
+            .map(|diagnostic| (diagnostic.line, diagnostic.column, diagnostic.len))
+            .collect();
+        assert_eq!(
+            positions,
+            [(1, 8, 4), (3, 1, 6), (4, 1, 2), (5, 14, 2), (6, 3, 5)]
+        );
+        assert_eq!(
+            diagnostics[2].kind,
+            ParseErrorKind::InvalidKey {
+                key: "9X".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_error_kinds() {
+        let kind = |content: &str| match Profile::parse(content, &test_path()) {
+            Err(Error::Parse { line, kind, .. }) => (line, kind),
+            other => panic!("expected a parse error, got {other:?}"),
+        };
+        assert_eq!(
+            kind("A=1\nB\n"),
+            (
+                2,
+                ParseErrorKind::MissingEquals {
+                    line: "B".to_string()
+                }
+            )
+        );
+        assert_eq!(
+            kind("#@confirm: maybe\n"),
+            (
+                1,
+                ParseErrorKind::InvalidDirectiveValue {
+                    directive: "confirm".to_string(),
+                    reason: "expected true or false, got 'maybe'".to_string(),
+                }
+            )
+        );
+
+        let err = Profile::load("gone", &test_path().join("gone")).unwrap_err();
+        assert!(matches!(err, Error::NotFound { name } if name == "gone"));
+    }
+
     #[test]
     fn test_utf8_in_values() {
         let content = "KEY=café\nKEY2=\"日本語\"";
-        let vars = parse_env_file(content, &test_path()).unwrap();
+        let vars = vars(content).unwrap();
         assert_eq!(vars.get("KEY"), Some(&"café".to_string()));
         assert_eq!(vars.get("KEY2"), Some(&"日本語".to_string()));
     }
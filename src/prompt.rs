@@ -0,0 +1,178 @@
+//! Prompt templates for activated sessions.
+//!
+//! A template such as `[{profile}{stack}] ` is rendered into a prefix that is
+//! prepended to the user's own prompt (see [`crate::shell::Shell::inject_prompt`]).
+
+use anyhow::{bail, Result};
+
+/// Environment variable holding the outer profiles of a nested session, outermost first.
+pub const KRAVEN_STACK: &str = "KRAVEN_STACK";
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const RED: &str = "\x1b[31m";
+
+const COLORS: &[(&str, &str)] = &[
+    ("reset", RESET),
+    ("bold", BOLD),
+    ("dim", "\x1b[2m"),
+    ("black", "\x1b[30m"),
+    ("red", RED),
+    ("green", "\x1b[32m"),
+    ("yellow", "\x1b[33m"),
+    ("blue", "\x1b[34m"),
+    ("magenta", "\x1b[35m"),
+    ("cyan", "\x1b[36m"),
+    ("white", "\x1b[37m"),
+];
+
+/// Values substituted into a prompt template.
+pub struct PromptContext<'a> {
+    /// The profile being activated.
+    pub profile: &'a str,
+    /// Profiles of the enclosing sessions, outermost first.
+    pub stack: &'a [String],
+}
+
+/// How a shell expects non-printing sequences (colours) to be marked in its prompt.
+///
+/// Unmarked escape sequences make bash and zsh miscount the prompt width.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Escapes {
+    Raw,
+    Bash,
+    Zsh,
+}
+
+impl Escapes {
+    fn wrap(self, sequence: &str) -> String {
+        match self {
+            Self::Raw => sequence.to_string(),
+            Self::Bash => format!("\\[{sequence}\\]"),
+            Self::Zsh => format!("%{{{sequence}%}}"),
+        }
+    }
+}
+
+/// Renders a prompt template.
+///
+/// Supported placeholders are `{profile}`, `{stack}` (the enclosing profiles as
+/// ` < outer`, empty outside nested sessions) and the colours `{red}`, `{green}`,
+/// `{yellow}`, `{blue}`, `{magenta}`, `{cyan}`, `{white}`, `{black}`, `{bold}`,
+/// `{dim}` and `{reset}`.
+pub fn render(template: &str, context: &PromptContext, escapes: Escapes) -> Result<String> {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        rendered.push_str(&rest[..start]);
+        let Some(len) = rest[start..].find('}') else {
+            bail!("Unclosed '{{' in prompt template: {template}");
+        };
+        let name = &rest[start + 1..start + len];
+
+        match name {
+            "profile" => rendered.push_str(context.profile),
+            "stack" => {
+                for outer in context.stack.iter().rev() {
+                    rendered.push_str(" < ");
+                    rendered.push_str(outer);
+                }
+            }
+            _ => {
+                let Some((_, sequence)) = COLORS.iter().find(|(color, _)| *color == name) else {
+                    bail!("Unknown placeholder '{{{name}}}' in prompt template");
+                };
+                rendered.push_str(&escapes.wrap(sequence));
+            }
+        }
+
+        rest = &rest[start + len + 1..];
+    }
+
+    rendered.push_str(rest);
+    Ok(rendered)
+}
+
+/// Highlights a rendered prefix in the warning colour, as used for protected profiles.
+#[must_use]
+pub fn warning(prefix: &str, escapes: Escapes) -> String {
+    format!(
+        "{}{prefix}{}",
+        escapes.wrap(&format!("{BOLD}{RED}")),
+        escapes.wrap(RESET)
+    )
+}
+
+/// Returns the profiles of the enclosing sessions, outermost first.
+#[must_use]
+pub fn current_stack() -> Vec<String> {
+    std::env::var(KRAVEN_STACK)
+        .map(|stack| stack.split_whitespace().map(ToString::to_string).collect())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context<'a>(profile: &'a str, stack: &'a [String]) -> PromptContext<'a> {
+        PromptContext { profile, stack }
+    }
+
+    #[test]
+    fn test_render_profile() {
+        let rendered = render("({profile}) ", &context("dev", &[]), Escapes::Raw).unwrap();
+        assert_eq!(rendered, "(dev) ");
+    }
+
+    #[test]
+    fn test_render_stack() {
+        let stack = vec!["base".to_string(), "staging".to_string()];
+        let rendered = render(
+            "[{profile}{stack}] ",
+            &context("prod", &stack),
+            Escapes::Raw,
+        );
+        assert_eq!(rendered.unwrap(), "[prod < staging < base] ");
+
+        let rendered = render("[{profile}{stack}] ", &context("prod", &[]), Escapes::Raw);
+        assert_eq!(rendered.unwrap(), "[prod] ");
+    }
+
+    #[test]
+    fn test_render_colors() {
+        let ctx = context("dev", &[]);
+        assert_eq!(
+            render("{red}{profile}{reset}", &ctx, Escapes::Raw).unwrap(),
+            "\x1b[31mdev\x1b[0m"
+        );
+        assert_eq!(
+            render("{bold}x", &ctx, Escapes::Bash).unwrap(),
+            "\\[\x1b[1m\\]x"
+        );
+        assert_eq!(
+            render("{bold}x", &ctx, Escapes::Zsh).unwrap(),
+            "%{\x1b[1m%}x"
+        );
+    }
+
+    #[test]
+    fn test_warning() {
+        assert_eq!(
+            warning("(prod) ", Escapes::Raw),
+            "\x1b[1m\x1b[31m(prod) \x1b[0m"
+        );
+        assert_eq!(
+            warning("(prod) ", Escapes::Bash),
+            "\\[\x1b[1m\x1b[31m\\](prod) \\[\x1b[0m\\]"
+        );
+    }
+
+    #[test]
+    fn test_render_invalid() {
+        let ctx = context("dev", &[]);
+        assert!(render("{colour}", &ctx, Escapes::Raw).is_err());
+        assert!(render("({profile", &ctx, Escapes::Raw).is_err());
+    }
+}
@@ -0,0 +1,208 @@
+//! Hiding profile values in the output of commands.
+//!
+//! A [`Redactor`] replaces every occurrence of a profile value with the name of
+//! its variable, e.g. `***API_TOKEN***`. [`Stream`] applies it to output that
+//! arrives in chunks, holding back the end of a chunk while it could still be
+//! the start of a value, so values split across reads are caught too.
+
+use std::collections::BTreeMap;
+use std::io::{self, Read, Write};
+
+/// Values shorter than this are left alone: redacting `1` or `true` would
+/// mangle the output without hiding anything secret.
+pub const MIN_VALUE_LEN: usize = 6;
+
+const READ_BUFFER_SIZE: usize = 8192;
+
+/// The values to hide and what to replace them with.
+#[derive(Debug, Clone)]
+pub struct Redactor {
+    /// Longest value first, so a value containing another one wins.
+    secrets: Vec<(Vec<u8>, Vec<u8>)>,
+}
+
+impl Redactor {
+    /// Builds a redactor for the values of `vars` of at least [`MIN_VALUE_LEN`] bytes.
+    ///
+    /// A value shared by several variables is replaced with the first name.
+    #[must_use]
+    pub fn new(vars: &BTreeMap<String, String>) -> Self {
+        let mut secrets: Vec<(Vec<u8>, Vec<u8>)> = Vec::new();
+        for (key, value) in vars {
+            let value = value.as_bytes();
+            if value.len() < MIN_VALUE_LEN || secrets.iter().any(|(known, _)| known == value) {
+                continue;
+            }
+            secrets.push((value.to_vec(), format!("***{key}***").into_bytes()));
+        }
+        // Stable, so the first name keeps winning among values of equal length
+        secrets.sort_by_key(|(value, _)| std::cmp::Reverse(value.len()));
+        Self { secrets }
+    }
+
+    /// Redacts `data`, leaving a trailing partial match unprocessed.
+    ///
+    /// Returns the redacted output and how many bytes of `data` it covers. With
+    /// `last` set, the whole input is processed.
+    fn redact(&self, data: &[u8], last: bool) -> (Vec<u8>, usize) {
+        let mut output = Vec::with_capacity(data.len());
+        let mut start = 0;
+        'scan: while start < data.len() {
+            let rest = &data[start..];
+            // The rest of the data may be completed into a value by the next chunk,
+            // which must win over a shorter value matching already
+            if !last
+                && self
+                    .secrets
+                    .iter()
+                    .any(|(value, _)| value.len() > rest.len() && value.starts_with(rest))
+            {
+                break;
+            }
+            for (value, replacement) in &self.secrets {
+                if rest.starts_with(value) {
+                    output.extend_from_slice(replacement);
+                    start += value.len();
+                    continue 'scan;
+                }
+            }
+            output.push(data[start]);
+            start += 1;
+        }
+        (output, start)
+    }
+}
+
+/// Applies a [`Redactor`] to output written in chunks.
+pub struct Stream<'a, W: Write> {
+    redactor: &'a Redactor,
+    out: W,
+    /// Bytes that may be the start of a value, waiting for the next chunk.
+    pending: Vec<u8>,
+}
+
+impl<'a, W: Write> Stream<'a, W> {
+    pub fn new(redactor: &'a Redactor, out: W) -> Self {
+        Self {
+            redactor,
+            out,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Writes a chunk, holding back a possible partial value.
+    pub fn write(&mut self, chunk: &[u8]) -> io::Result<()> {
+        self.pending.extend_from_slice(chunk);
+        let (output, consumed) = self.redactor.redact(&self.pending, false);
+        self.pending.drain(..consumed);
+        self.out.write_all(&output)?;
+        self.out.flush()
+    }
+
+    /// Writes whatever was held back; the output has ended.
+    pub fn finish(mut self) -> io::Result<W> {
+        let (output, _) = self.redactor.redact(&self.pending, true);
+        self.out.write_all(&output)?;
+        self.out.flush()?;
+        Ok(self.out)
+    }
+
+    /// Copies `input` to the output until it ends.
+    pub fn copy_from(mut self, mut input: impl Read) -> io::Result<W> {
+        let mut buffer = [0u8; READ_BUFFER_SIZE];
+        loop {
+            match input.read(&mut buffer) {
+                Ok(0) => return self.finish(),
+                Ok(n) => self.write(&buffer[..n])?,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn redactor(vars: &[(&str, &str)]) -> Redactor {
+        Redactor::new(
+            &vars
+                .iter()
+                .map(|(key, value)| ((*key).to_string(), (*value).to_string()))
+                .collect(),
+        )
+    }
+
+    fn redact_chunks(redactor: &Redactor, chunks: &[&str]) -> String {
+        let mut stream = Stream::new(redactor, Vec::new());
+        for chunk in chunks {
+            stream.write(chunk.as_bytes()).unwrap();
+        }
+        String::from_utf8(stream.finish().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_replaces_values_with_key() {
+        let redactor = redactor(&[("TOKEN", "hunter22"), ("REGION", "eu"), ("USER", "admin1")]);
+        assert_eq!(
+            redact_chunks(&redactor, &["login admin1:hunter22 in eu\n"]),
+            "login ***USER***:***TOKEN*** in eu\n"
+        );
+    }
+
+    #[test]
+    fn test_value_split_across_chunks() {
+        let redactor = redactor(&[("TOKEN", "hunter22")]);
+        assert_eq!(
+            redact_chunks(&redactor, &["token=hun", "ter", "22 done"]),
+            "token=***TOKEN*** done"
+        );
+        // Every split point of the value
+        for split in 1.."hunter22".len() {
+            let (head, tail) = "xhunter22x".split_at(split + 1);
+            assert_eq!(redact_chunks(&redactor, &[head, tail]), "x***TOKEN***x");
+        }
+    }
+
+    #[test]
+    fn test_partial_match_is_released() {
+        let redactor = redactor(&[("TOKEN", "hunter22")]);
+        assert_eq!(redact_chunks(&redactor, &["hunt", "ing"]), "hunting");
+        assert_eq!(
+            redact_chunks(&redactor, &["ends with hunter2"]),
+            "ends with hunter2"
+        );
+    }
+
+    #[test]
+    fn test_longest_value_wins() {
+        let redactor = redactor(&[("SHORT", "secret"), ("LONG", "secret-extended")]);
+        assert_eq!(
+            redact_chunks(&redactor, &["secret-extended secret"]),
+            "***LONG*** ***SHORT***"
+        );
+        // The longer value still wins when it ends in a later chunk
+        assert_eq!(
+            redact_chunks(&redactor, &["secret-", "extended\n"]),
+            "***LONG***\n"
+        );
+        assert_eq!(
+            redact_chunks(&redactor, &["secret", "-ext", "ras\n"]),
+            "***SHORT***-extras\n"
+        );
+    }
+
+    #[test]
+    fn test_copy_from_reader() {
+        let redactor = redactor(&[("TOKEN", "hunter22")]);
+        let input = "hunter22 ".repeat(2000);
+        let output = Stream::new(&redactor, Vec::new())
+            .copy_from(input.as_bytes())
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "***TOKEN*** ".repeat(2000)
+        );
+    }
+}
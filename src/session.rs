@@ -0,0 +1,338 @@
+//! Supervision of the shell spawned by `activate`, including session expiry.
+//!
+//! Time is read through the [`Clock`] trait so the expiry logic can be driven
+//! by a fake clock in tests instead of real sleeps.
+
+use anyhow::{Context, Result};
+use std::process::{Child, ExitStatus};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::duration::format_duration;
+
+/// Environment variable holding the session deadline, in seconds since the Unix epoch.
+pub const KRAVEN_EXPIRES_AT: &str = "KRAVEN_EXPIRES_AT";
+
+/// Environment variable holding the id of the kraven process supervising the session.
+pub const KRAVEN_PID: &str = "KRAVEN_PID";
+
+/// How often the shell is checked while a deadline is pending.
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// How long before the deadline the user is warned (at most half the TTL).
+const WARN_BEFORE: Duration = Duration::from_secs(60);
+
+/// How long the shell gets to exit after the hangup signal before it is killed.
+const KILL_GRACE: Duration = Duration::from_secs(5);
+
+/// A source of time.
+pub trait Clock {
+    fn now(&self) -> SystemTime;
+    fn sleep(&self, duration: Duration);
+}
+
+/// The real wall clock.
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        std::thread::sleep(duration);
+    }
+}
+
+/// What a session should do at a given point in time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tick {
+    Running,
+    /// The deadline is near; reported once.
+    Warn(Duration),
+    Expired,
+}
+
+/// The deadline of a time-limited session.
+#[derive(Debug, Clone)]
+pub struct Expiry {
+    deadline: SystemTime,
+    warn_before: Duration,
+    warned: bool,
+}
+
+impl Expiry {
+    /// A session started at `start` that may last `ttl`.
+    ///
+    /// Fails when the deadline is too far away for the system clock.
+    pub fn new(start: SystemTime, ttl: Duration) -> Result<Self> {
+        let deadline = start
+            .checked_add(ttl)
+            .with_context(|| format!("Session length {} is too long", format_duration(ttl)))?;
+        Ok(Self {
+            deadline,
+            warn_before: WARN_BEFORE.min(ttl / 2),
+            warned: false,
+        })
+    }
+
+    /// Keeps the earlier of this deadline and `other`.
+    #[must_use]
+    pub fn min(mut self, other: SystemTime) -> Self {
+        self.deadline = self.deadline.min(other);
+        self
+    }
+
+    #[must_use]
+    pub fn deadline(&self) -> SystemTime {
+        self.deadline
+    }
+
+    /// Advances the session state to `now`.
+    pub fn check(&mut self, now: SystemTime) -> Tick {
+        let Ok(remaining) = self.deadline.duration_since(now) else {
+            return Tick::Expired;
+        };
+        if remaining.is_zero() {
+            return Tick::Expired;
+        }
+        if !self.warned && remaining <= self.warn_before {
+            self.warned = true;
+            return Tick::Warn(remaining);
+        }
+        Tick::Running
+    }
+}
+
+/// Returns the deadline of the current session, if it has one.
+#[must_use]
+pub fn current_deadline() -> Option<SystemTime> {
+    let seconds = std::env::var(KRAVEN_EXPIRES_AT).ok()?.parse().ok()?;
+    UNIX_EPOCH.checked_add(Duration::from_secs(seconds))
+}
+
+/// Returns whether the kraven process of the current session is still running.
+///
+/// `None` when that can't be told: outside a session, or in one started by a
+/// kraven that didn't set [`KRAVEN_PID`].
+#[cfg(unix)]
+#[must_use]
+pub fn supervisor_alive() -> Option<bool> {
+    let pid: libc::pid_t = std::env::var(KRAVEN_PID).ok()?.parse().ok()?;
+    // SAFETY: kill() has no memory-safety preconditions; signal 0 only checks the pid.
+    let result = unsafe { libc::kill(pid, 0) };
+    // EPERM means the process exists but belongs to another user
+    Some(result == 0 || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM))
+}
+
+#[cfg(not(unix))]
+pub fn supervisor_alive() -> Option<bool> {
+    None
+}
+
+/// Formats a deadline for [`KRAVEN_EXPIRES_AT`].
+#[must_use]
+pub fn format_deadline(deadline: SystemTime) -> String {
+    let seconds = deadline
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    seconds.to_string()
+}
+
+/// Waits for the session shell to exit, closing it once the session expires.
+pub fn supervise(
+    child: &mut Child,
+    profile_name: &str,
+    expiry: Option<Expiry>,
+    clock: &dyn Clock,
+) -> Result<ExitStatus> {
+    let Some(mut expiry) = expiry else {
+        return child.wait().context("Failed to wait for shell");
+    };
+
+    loop {
+        if let Some(status) = child.try_wait().context("Failed to wait for shell")? {
+            return Ok(status);
+        }
+
+        match expiry.check(clock.now()) {
+            Tick::Running => {}
+            Tick::Warn(remaining) => {
+                // Round up so the warning never claims "0s" while time is left
+                let seconds = remaining.as_secs() + u64::from(remaining.subsec_nanos() > 0);
+                eprintln!(
+                    "\r\nkraven: session '{profile_name}' expires in {}.\r",
+                    format_duration(Duration::from_secs(seconds))
+                );
+            }
+            Tick::Expired => {
+                eprintln!("\r\nkraven: session '{profile_name}' expired, closing the shell.\r");
+                return terminate(child, clock);
+            }
+        }
+
+        clock.sleep(POLL_INTERVAL);
+    }
+}
+
+/// Hangs up the shell, killing it if it is still running after a grace period.
+fn terminate(child: &mut Child, clock: &dyn Clock) -> Result<ExitStatus> {
+    hangup(child);
+
+    let give_up = clock.now() + KILL_GRACE;
+    while clock.now() < give_up {
+        if let Some(status) = child.try_wait().context("Failed to wait for shell")? {
+            return Ok(status);
+        }
+        clock.sleep(POLL_INTERVAL);
+    }
+
+    // The shell may exit between the last check and the kill
+    let _ = child.kill();
+    child.wait().context("Failed to wait for shell")
+}
+
+/// Interactive shells ignore SIGTERM but exit on SIGHUP, as when their terminal closes.
+#[cfg(unix)]
+fn hangup(child: &mut Child) {
+    let Ok(pid) = libc::pid_t::try_from(child.id()) else {
+        return;
+    };
+    // SAFETY: kill() has no memory-safety preconditions; the pid belongs to our child.
+    unsafe {
+        libc::kill(pid, libc::SIGHUP);
+    }
+}
+
+#[cfg(not(unix))]
+fn hangup(child: &mut Child) {
+    let _ = child.kill();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    /// A clock that only moves when slept on.
+    struct FakeClock {
+        now: Cell<SystemTime>,
+    }
+
+    impl FakeClock {
+        fn new() -> Self {
+            Self {
+                now: Cell::new(UNIX_EPOCH + Duration::from_secs(1_000_000)),
+            }
+        }
+    }
+
+    impl Clock for FakeClock {
+        fn now(&self) -> SystemTime {
+            self.now.get()
+        }
+
+        fn sleep(&self, duration: Duration) {
+            self.now.set(self.now.get() + duration);
+            // Give the child process a moment of real time to make progress
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn test_expiry_ticks() {
+        let clock = FakeClock::new();
+        let start = clock.now();
+        let mut expiry = Expiry::new(start, Duration::from_secs(600)).unwrap();
+
+        assert_eq!(expiry.check(start), Tick::Running);
+        assert_eq!(
+            expiry.check(start + Duration::from_secs(540)),
+            Tick::Warn(Duration::from_secs(60))
+        );
+        // The warning is only reported once
+        assert_eq!(
+            expiry.check(start + Duration::from_secs(560)),
+            Tick::Running
+        );
+        assert_eq!(
+            expiry.check(start + Duration::from_secs(600)),
+            Tick::Expired
+        );
+        assert_eq!(
+            expiry.check(start + Duration::from_secs(900)),
+            Tick::Expired
+        );
+    }
+
+    #[test]
+    fn test_short_ttl_warns_halfway() {
+        let start = FakeClock::new().now();
+        let mut expiry = Expiry::new(start, Duration::from_secs(20)).unwrap();
+        assert_eq!(expiry.check(start + Duration::from_secs(9)), Tick::Running);
+        assert_eq!(
+            expiry.check(start + Duration::from_secs(10)),
+            Tick::Warn(Duration::from_secs(10))
+        );
+    }
+
+    #[test]
+    fn test_expiry_rejects_deadline_past_clock_range() {
+        let start = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let err = Expiry::new(start, Duration::from_secs(u64::MAX)).unwrap_err();
+        assert!(err.to_string().contains("too long"), "{err}");
+    }
+
+    #[test]
+    fn test_expiry_keeps_earlier_deadline() {
+        let start = FakeClock::new().now();
+        let outer = start + Duration::from_secs(60);
+
+        let expiry = Expiry::new(start, Duration::from_secs(600))
+            .unwrap()
+            .min(outer);
+        assert_eq!(expiry.deadline(), outer);
+
+        let expiry = Expiry::new(start, Duration::from_secs(30))
+            .unwrap()
+            .min(outer);
+        assert_eq!(expiry.deadline(), start + Duration::from_secs(30));
+    }
+
+    #[test]
+    fn test_deadline_roundtrip() {
+        let deadline = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        assert_eq!(format_deadline(deadline), "1700000000");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_supervise_terminates_expired_session() {
+        let clock = FakeClock::new();
+        let mut child = std::process::Command::new("sleep")
+            .arg("30")
+            .spawn()
+            .unwrap();
+        let expiry = Expiry::new(clock.now(), Duration::from_secs(5)).unwrap();
+
+        let status = supervise(&mut child, "test", Some(expiry), &clock).unwrap();
+
+        assert!(!status.success());
+        assert!(clock.now() >= UNIX_EPOCH + Duration::from_secs(1_000_005));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_supervise_returns_exit_status() {
+        let clock = FakeClock::new();
+        let mut child = std::process::Command::new("sh")
+            .args(["-c", "exit 3"])
+            .spawn()
+            .unwrap();
+        let expiry = Expiry::new(clock.now(), Duration::from_secs(3_600)).unwrap();
+
+        let status = supervise(&mut child, "test", Some(expiry), &clock).unwrap();
+
+        assert_eq!(status.code(), Some(3));
+    }
+}
@@ -0,0 +1,447 @@
+//! User settings loaded from the kraven configuration file.
+//!
+//! Each setting is resolved with the same precedence: command-line flag, then
+//! environment variable, then configuration file, then the built-in default.
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::io::{self, ErrorKind, IsTerminal};
+use std::str::FromStr;
+
+use crate::config::get_config_path;
+use crate::editor;
+
+const ENV_SHELL: &str = "KRAVEN_SHELL";
+const ENV_PROMPT: &str = "KRAVEN_PROMPT";
+const ENV_MASK: &str = "KRAVEN_MASK";
+const ENV_MASK_STRATEGY: &str = "KRAVEN_MASK_STRATEGY";
+const ENV_ISOLATE_ALLOWLIST: &str = "KRAVEN_ISOLATE_ALLOWLIST";
+const ENV_NESTING: &str = "KRAVEN_NESTING";
+
+const DEFAULT_SHELL: &str = "/bin/sh";
+const DEFAULT_PROMPT: &str = "({profile}) ";
+const DEFAULT_ISOLATE_ALLOWLIST: &[&str] = &[
+    "HOME", "LANG", "LOGNAME", "PATH", "SHELL", "TERM", "TMPDIR", "USER",
+];
+
+/// Names of the settings managed by `kraven config`.
+pub const KEYS: &[&str] = &[
+    "shell",
+    "editor",
+    "prompt",
+    "mask",
+    "mask_strategy",
+    "isolate_allowlist",
+    "nesting",
+];
+
+/// Which values `show` masks.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum MaskMode {
+    #[default]
+    Never,
+    /// Values of keys that look secret or are marked with `#@secret`.
+    Auto,
+    Always,
+}
+
+impl FromStr for MaskMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "never" => Ok(Self::Never),
+            "auto" => Ok(Self::Auto),
+            "always" => Ok(Self::Always),
+            _ => bail!("Invalid mask mode '{s}': expected 'never', 'auto' or 'always'"),
+        }
+    }
+}
+
+impl fmt::Display for MaskMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Never => write!(f, "never"),
+            Self::Auto => write!(f, "auto"),
+            Self::Always => write!(f, "always"),
+        }
+    }
+}
+
+/// How masked values are shown.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum MaskStrategy {
+    /// A fixed run of `*`.
+    Full,
+    /// The first and last two characters of long values.
+    #[default]
+    Partial,
+    /// Only the length of the value.
+    Length,
+    /// A short fingerprint keyed with a per-user secret, equal for equal values.
+    Hash,
+}
+
+impl FromStr for MaskStrategy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "full" => Ok(Self::Full),
+            "partial" => Ok(Self::Partial),
+            "length" => Ok(Self::Length),
+            "hash" => Ok(Self::Hash),
+            _ => {
+                bail!("Invalid mask strategy '{s}': expected 'full', 'partial', 'length' or 'hash'")
+            }
+        }
+    }
+}
+
+impl fmt::Display for MaskStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Full => write!(f, "full"),
+            Self::Partial => write!(f, "partial"),
+            Self::Length => write!(f, "length"),
+            Self::Hash => write!(f, "hash"),
+        }
+    }
+}
+
+/// Whether a profile may be activated from inside another kraven session.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum NestingPolicy {
+    #[default]
+    Deny,
+    Allow,
+}
+
+impl FromStr for NestingPolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "deny" => Ok(Self::Deny),
+            "allow" => Ok(Self::Allow),
+            _ => bail!("Invalid nesting policy '{s}': expected 'deny' or 'allow'"),
+        }
+    }
+}
+
+impl fmt::Display for NestingPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Deny => write!(f, "deny"),
+            Self::Allow => write!(f, "allow"),
+        }
+    }
+}
+
+/// Contents of the configuration file. Unset fields fall back to the defaults.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Settings {
+    /// Shell spawned by `activate`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shell: Option<String>,
+
+    /// Editor used by `edit` when none of `$KRAVEN_EDITOR`, `$VISUAL` and `$EDITOR` is set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub editor: Option<String>,
+
+    /// Prompt prefix template; `{profile}` is replaced with the profile name.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prompt: Option<String>,
+
+    /// Which values `show` masks without `--mask`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mask: Option<MaskMode>,
+
+    /// How `show` masks values.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mask_strategy: Option<MaskStrategy>,
+
+    /// Variables kept from the parent environment by `activate --isolate`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub isolate_allowlist: Option<Vec<String>>,
+
+    /// Whether `activate` is allowed inside an active session.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nesting: Option<NestingPolicy>,
+}
+
+impl Settings {
+    /// Load the configuration file, or the defaults if it does not exist.
+    pub fn load() -> Result<Self> {
+        let path = get_config_path()?;
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => {
+                return Err(e)
+                    .with_context(|| format!("Failed to read config file: {}", path.display()))
+            }
+        };
+        Self::parse(&content).with_context(|| format!("Invalid config file: {}", path.display()))
+    }
+
+    fn parse(content: &str) -> Result<Self> {
+        Ok(toml::from_str(content)?)
+    }
+
+    /// Write the settings back to the configuration file.
+    pub fn save(&self) -> Result<()> {
+        let path = get_config_path()?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create directory: {}", parent.display()))?;
+        }
+        let content = toml::to_string(self).context("Failed to serialize config")?;
+        fs::write(&path, content)
+            .with_context(|| format!("Failed to write config file: {}", path.display()))
+    }
+
+    /// Returns the configured value of a setting, formatted as accepted by [`Settings::set`].
+    pub fn get(&self, key: &str) -> Result<Option<String>> {
+        let value = match key {
+            "shell" => self.shell.clone(),
+            "editor" => self.editor.clone(),
+            "prompt" => self.prompt.clone(),
+            "mask" => self.mask.map(|mode| mode.to_string()),
+            "mask_strategy" => self.mask_strategy.map(|strategy| strategy.to_string()),
+            "isolate_allowlist" => self.isolate_allowlist.as_ref().map(|vars| vars.join(",")),
+            "nesting" => self.nesting.map(|policy| policy.to_string()),
+            _ => bail!("Unknown config key '{key}'"),
+        };
+        Ok(value)
+    }
+
+    /// Sets a setting from its string form. Lists are comma-separated.
+    pub fn set(&mut self, key: &str, value: &str) -> Result<()> {
+        match key {
+            "shell" => self.shell = Some(value.to_string()),
+            "editor" => self.editor = Some(value.to_string()),
+            "prompt" => self.prompt = Some(value.to_string()),
+            "mask" => self.mask = Some(value.parse()?),
+            "mask_strategy" => self.mask_strategy = Some(value.parse()?),
+            "isolate_allowlist" => self.isolate_allowlist = Some(split_list(value)),
+            "nesting" => self.nesting = Some(value.parse()?),
+            _ => bail!("Unknown config key '{key}'"),
+        }
+        Ok(())
+    }
+
+    /// Clears a setting so it falls back to its default.
+    pub fn unset(&mut self, key: &str) -> Result<()> {
+        match key {
+            "shell" => self.shell = None,
+            "editor" => self.editor = None,
+            "prompt" => self.prompt = None,
+            "mask" => self.mask = None,
+            "mask_strategy" => self.mask_strategy = None,
+            "isolate_allowlist" => self.isolate_allowlist = None,
+            "nesting" => self.nesting = None,
+            _ => bail!("Unknown config key '{key}'"),
+        }
+        Ok(())
+    }
+
+    /// Shell to spawn: flag, `$KRAVEN_SHELL`, config, `$SHELL`, then `/bin/sh`.
+    pub fn shell(&self, flag: Option<&str>) -> String {
+        flag.map(str::to_string)
+            .or_else(|| std::env::var(ENV_SHELL).ok())
+            .or_else(|| self.shell.clone())
+            .or_else(|| std::env::var("SHELL").ok())
+            .unwrap_or_else(|| DEFAULT_SHELL.to_string())
+    }
+
+    /// Editor command: `$KRAVEN_EDITOR`, `$VISUAL` on a terminal, `$EDITOR`, config, then `vi`.
+    #[must_use]
+    pub fn editor(&self) -> String {
+        editor::resolve(
+            |key| std::env::var(key).ok(),
+            io::stdout().is_terminal(),
+            self.editor.as_deref(),
+        )
+    }
+
+    /// Prompt prefix template: flag, `$KRAVEN_PROMPT`, config, then `({profile}) `.
+    pub fn prompt(&self, flag: Option<&str>) -> String {
+        flag.map(str::to_string)
+            .or_else(|| std::env::var(ENV_PROMPT).ok())
+            .or_else(|| self.prompt.clone())
+            .unwrap_or_else(|| DEFAULT_PROMPT.to_string())
+    }
+
+    /// Masking mode: `--mask`, `$KRAVEN_MASK`, config, then never.
+    pub fn mask(&self, flag: Option<MaskMode>) -> Result<MaskMode> {
+        if let Some(mode) = flag {
+            return Ok(mode);
+        }
+        Ok(env_value(ENV_MASK)?.or(self.mask).unwrap_or_default())
+    }
+
+    /// Masking strategy: `--mask-strategy`, `$KRAVEN_MASK_STRATEGY`, config, then partial.
+    pub fn mask_strategy(&self, flag: Option<MaskStrategy>) -> Result<MaskStrategy> {
+        if let Some(strategy) = flag {
+            return Ok(strategy);
+        }
+        Ok(env_value(ENV_MASK_STRATEGY)?
+            .or(self.mask_strategy)
+            .unwrap_or_default())
+    }
+
+    /// Variables kept by `--isolate`: `$KRAVEN_ISOLATE_ALLOWLIST`, config, then a
+    /// minimal set needed for a usable shell.
+    #[must_use]
+    pub fn isolate_allowlist(&self) -> Vec<String> {
+        std::env::var(ENV_ISOLATE_ALLOWLIST)
+            .ok()
+            .map(|value| split_list(&value))
+            .or_else(|| self.isolate_allowlist.clone())
+            .unwrap_or_else(|| {
+                DEFAULT_ISOLATE_ALLOWLIST
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect()
+            })
+    }
+
+    /// Nesting policy: `--nesting`, `$KRAVEN_NESTING`, config, then deny.
+    pub fn nesting(&self, flag: Option<NestingPolicy>) -> Result<NestingPolicy> {
+        if let Some(policy) = flag {
+            return Ok(policy);
+        }
+        Ok(env_value(ENV_NESTING)?.or(self.nesting).unwrap_or_default())
+    }
+}
+
+/// Parses an environment variable, treating an unset variable as `None`.
+fn env_value<T: FromStr<Err = anyhow::Error>>(var: &str) -> Result<Option<T>> {
+    match std::env::var(var) {
+        Ok(value) => value
+            .parse()
+            .map(Some)
+            .with_context(|| format!("Invalid value for {var}")),
+        Err(_) => Ok(None),
+    }
+}
+
+fn split_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(ToString::to_string)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_empty() {
+        let settings = Settings::parse("").unwrap();
+        assert!(settings.shell.is_none());
+        assert!(settings.mask.is_none());
+    }
+
+    #[test]
+    fn test_parse_all_keys() {
+        let content = r#"
+            shell = "/bin/zsh"
+            editor = "nano"
+            prompt = "[{profile}] "
+            mask = "always"
+            isolate_allowlist = ["HOME", "PATH"]
+            nesting = "allow"
+        "#;
+        let settings = Settings::parse(content).unwrap();
+        assert_eq!(settings.shell.as_deref(), Some("/bin/zsh"));
+        assert_eq!(settings.mask, Some(MaskMode::Always));
+        assert_eq!(settings.nesting, Some(NestingPolicy::Allow));
+        assert_eq!(
+            settings.isolate_allowlist,
+            Some(vec!["HOME".to_string(), "PATH".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_parse_unknown_key() {
+        assert!(Settings::parse("colour = \"red\"").is_err());
+    }
+
+    #[test]
+    fn test_parse_invalid_value() {
+        assert!(Settings::parse("mask = \"sometimes\"").is_err());
+    }
+
+    #[test]
+    fn test_set_get_roundtrip() {
+        let mut settings = Settings::default();
+        for key in KEYS {
+            assert_eq!(settings.get(key).unwrap(), None);
+        }
+
+        settings.set("prompt", "<{profile}> ").unwrap();
+        settings
+            .set("isolate_allowlist", "HOME, PATH,,TERM")
+            .unwrap();
+        settings.set("nesting", "allow").unwrap();
+        settings.set("mask", "auto").unwrap();
+        settings.set("mask_strategy", "hash").unwrap();
+
+        assert_eq!(
+            settings.get("prompt").unwrap().as_deref(),
+            Some("<{profile}> ")
+        );
+        assert_eq!(
+            settings.get("isolate_allowlist").unwrap().as_deref(),
+            Some("HOME,PATH,TERM")
+        );
+        assert_eq!(settings.get("nesting").unwrap().as_deref(), Some("allow"));
+
+        let reparsed = Settings::parse(&toml::to_string(&settings).unwrap()).unwrap();
+        assert_eq!(reparsed.nesting, Some(NestingPolicy::Allow));
+        assert_eq!(reparsed.mask, Some(MaskMode::Auto));
+        assert_eq!(reparsed.mask_strategy, Some(MaskStrategy::Hash));
+
+        settings.unset("nesting").unwrap();
+        assert_eq!(settings.get("nesting").unwrap(), None);
+    }
+
+    #[test]
+    fn test_set_rejects_invalid() {
+        let mut settings = Settings::default();
+        assert!(settings.set("unknown", "value").is_err());
+        assert!(settings.set("mask", "sometimes").is_err());
+        assert!(settings.set("mask_strategy", "blur").is_err());
+        assert!(settings.set("nesting", "maybe").is_err());
+    }
+
+    #[test]
+    fn test_flag_takes_precedence() {
+        let settings = Settings {
+            shell: Some("/bin/zsh".to_string()),
+            nesting: Some(NestingPolicy::Deny),
+            mask: Some(MaskMode::Never),
+            ..Settings::default()
+        };
+        assert_eq!(settings.shell(Some("/bin/fish")), "/bin/fish");
+        assert_eq!(
+            settings.nesting(Some(NestingPolicy::Allow)).unwrap(),
+            NestingPolicy::Allow
+        );
+        assert_eq!(
+            settings.mask(Some(MaskMode::Always)).unwrap(),
+            MaskMode::Always
+        );
+    }
+}
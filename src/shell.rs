@@ -0,0 +1,395 @@
+//! Knowledge about the shells kraven can launch and generate code for.
+//!
+//! Each shell differs in how its prompt can be changed after the rc files ran,
+//! how colour sequences are marked in the prompt, and how environment
+//! variables are exported and unset.
+
+use anyhow::{anyhow, Context, Result};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::prompt::Escapes;
+
+/// The user's `PROMPT_COMMAND` before kraven wrapped it, kept for nested sessions.
+const KRAVEN_PROMPT_COMMAND: &str = "KRAVEN_PROMPT_COMMAND";
+
+/// The user's `ZDOTDIR` before kraven pointed it at the shim directory.
+const KRAVEN_USER_ZDOTDIR: &str = "KRAVEN_USER_ZDOTDIR";
+
+/// Rc files xonsh reads when `$XONSHRC` is not set.
+const XONSH_DEFAULT_RC: &str = "/etc/xonsh/xonshrc:~/.config/xonsh/rc.xsh:~/.xonshrc";
+
+/// A shell family.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Shell {
+    /// POSIX sh and compatible shells (dash, ksh, ...)
+    Sh,
+    Bash,
+    Zsh,
+    Fish,
+    #[value(alias = "nushell")]
+    Nu,
+    #[value(alias = "powershell")]
+    Pwsh,
+    Xonsh,
+}
+
+impl Shell {
+    /// Detects the shell family from a program path such as `/usr/bin/fish`.
+    ///
+    /// Unknown shells are treated as POSIX sh.
+    #[must_use]
+    pub fn from_program(program: &str) -> Self {
+        let name = Path::new(program)
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("");
+
+        match name {
+            "bash" => Self::Bash,
+            "zsh" => Self::Zsh,
+            "fish" => Self::Fish,
+            "nu" => Self::Nu,
+            "pwsh" | "powershell" => Self::Pwsh,
+            "xonsh" => Self::Xonsh,
+            _ => Self::Sh,
+        }
+    }
+
+    /// Builds the command that starts `program` as an interactive shell.
+    #[must_use]
+    pub fn interactive_command(self, program: &str) -> Command {
+        let mut cmd = Command::new(program);
+        if self == Self::Pwsh {
+            cmd.arg("-NoLogo");
+        }
+        cmd
+    }
+
+    /// How colour sequences must be marked in this shell's prompt.
+    #[must_use]
+    pub fn escapes(self) -> Escapes {
+        match self {
+            Self::Bash => Escapes::Bash,
+            Self::Zsh => Escapes::Zsh,
+            _ => Escapes::Raw,
+        }
+    }
+
+    /// Returns a statement that exports `key` with `value`.
+    #[must_use]
+    pub fn export(self, key: &str, value: &str) -> String {
+        match self {
+            Self::Sh | Self::Bash | Self::Zsh => format!("export {key}={}", sh_quote(value)),
+            Self::Fish => format!("set -gx {key} {}", fish_quote(value)),
+            Self::Nu => format!("$env.{key} = {}", nu_quote(value)),
+            Self::Pwsh => format!("$env:{key} = {}", powershell_quote(value)),
+            Self::Xonsh => format!("${key} = {}", python_quote(value)),
+        }
+    }
+
+    /// Returns a statement that removes `key` from the environment.
+    #[must_use]
+    pub fn unset(self, key: &str) -> String {
+        match self {
+            Self::Sh | Self::Bash | Self::Zsh => format!("unset {key}"),
+            Self::Fish => format!("set -e {key}"),
+            Self::Nu => format!("hide-env {key}"),
+            Self::Pwsh => format!("Remove-Item Env:{key}"),
+            Self::Xonsh => format!("del ${key}"),
+        }
+    }
+
+    /// Configures `cmd` so the shell shows `prefix` before its own prompt.
+    ///
+    /// Shell rc files usually overwrite the prompt, so each shell gets a hook
+    /// that re-applies the prefix once its configuration has loaded.
+    pub fn inject_prompt(self, cmd: &mut Command, prefix: &str) -> Result<PromptGuard> {
+        let mut guard = PromptGuard { shim_dir: None };
+
+        match self {
+            Self::Bash => inject_bash(cmd, prefix),
+            Self::Zsh => inject_zsh(cmd, prefix, guard.create_shim_dir("zsh")?)?,
+            Self::Fish => {
+                cmd.arg("--init-command").arg(format!(
+                    "functions --copy fish_prompt __kraven_fish_prompt; \
+                     function fish_prompt; printf '%s' {}; __kraven_fish_prompt; end",
+                    fish_quote(prefix)
+                ));
+            }
+            Self::Nu => {
+                cmd.arg("--execute").arg(format!(
+                    "let __kraven_prompt = $env.PROMPT_COMMAND; \
+                     $env.PROMPT_COMMAND = {{|| {} + (if ($__kraven_prompt | describe) == 'closure' \
+                     {{ do $__kraven_prompt }} else {{ $__kraven_prompt }}) }}",
+                    nu_quote(prefix)
+                ));
+            }
+            Self::Pwsh => {
+                cmd.args(["-NoExit", "-Command"]).arg(format!(
+                    "$global:__kraven_prompt = $function:prompt; \
+                     function global:prompt {{ {} + (& $global:__kraven_prompt) }}",
+                    powershell_quote(prefix)
+                ));
+            }
+            Self::Xonsh => inject_xonsh(cmd, prefix, guard.create_shim_dir("xonsh")?)?,
+            Self::Sh => {
+                // No hook available, so set PS1 directly
+                let current_ps1 = std::env::var("PS1").unwrap_or_default();
+                cmd.env("PS1", format!("{prefix}{current_ps1}"));
+            }
+        }
+
+        Ok(guard)
+    }
+}
+
+/// Files created to integrate the prompt, removed when dropped.
+pub struct PromptGuard {
+    shim_dir: Option<PathBuf>,
+}
+
+impl PromptGuard {
+    /// Creates a fresh directory only the user can access, removed with the guard.
+    ///
+    /// The name is random and the directory must not exist yet, so another user
+    /// cannot prepare it with files the shell would source.
+    fn create_shim_dir(&mut self, shell: &str) -> Result<&Path> {
+        let base = std::env::var_os("XDG_RUNTIME_DIR")
+            .map(PathBuf::from)
+            .filter(|dir| dir.is_absolute())
+            .unwrap_or_else(std::env::temp_dir);
+        let mut suffix = [0u8; 8];
+        getrandom::getrandom(&mut suffix)
+            .map_err(|err| anyhow!("Failed to generate a directory name: {err}"))?;
+        let suffix = u64::from_le_bytes(suffix);
+        let shim_dir = base.join(format!("kraven-{shell}-{suffix:016x}"));
+
+        let mut builder = fs::DirBuilder::new();
+        #[cfg(unix)]
+        std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+        builder
+            .create(&shim_dir)
+            .with_context(|| format!("Failed to create directory: {}", shim_dir.display()))?;
+        Ok(self.shim_dir.insert(shim_dir))
+    }
+}
+
+impl Drop for PromptGuard {
+    fn drop(&mut self) {
+        if let Some(dir) = &self.shim_dir {
+            let _ = fs::remove_dir_all(dir);
+        }
+    }
+}
+
+/// `PROMPT_COMMAND` runs before each prompt, so the prefix survives `.bashrc` setting PS1.
+fn inject_bash(cmd: &mut Command, prefix: &str) {
+    // Nested sessions wrap the user's command, not the outer session's hook
+    let existing = std::env::var(KRAVEN_PROMPT_COMMAND)
+        .or_else(|_| std::env::var("PROMPT_COMMAND"))
+        .unwrap_or_default();
+    let prefix = bash_quote(prefix);
+    let prefix_cmd = format!(r#"PS1="{prefix}${{PS1#"{prefix}"}}""#);
+    let new_prompt_cmd = if existing.is_empty() {
+        prefix_cmd
+    } else {
+        format!("{prefix_cmd}; {existing}")
+    };
+    cmd.env(KRAVEN_PROMPT_COMMAND, existing);
+    cmd.env("PROMPT_COMMAND", new_prompt_cmd);
+}
+
+/// zsh only reads its rc files from `$ZDOTDIR`, so point it at a shim directory
+/// whose files source the user's own and then register a `precmd` hook.
+fn inject_zsh(cmd: &mut Command, prefix: &str, shim_dir: &Path) -> Result<()> {
+    let user_zdotdir = std::env::var(KRAVEN_USER_ZDOTDIR)
+        .or_else(|_| std::env::var("ZDOTDIR"))
+        .or_else(|_| std::env::var("HOME"))
+        .unwrap_or_default();
+
+    // Startup files read before .zshrc keep ZDOTDIR on the shim so zsh comes back for it
+    for file in [".zshenv", ".zprofile"] {
+        let content = format!(
+            "ZDOTDIR=\"${KRAVEN_USER_ZDOTDIR}\"\n\
+             [[ -f \"$ZDOTDIR/{file}\" ]] && source \"$ZDOTDIR/{file}\"\n\
+             ZDOTDIR={}\n",
+            sh_quote(&shim_dir.to_string_lossy())
+        );
+        write_shim_file(&shim_dir.join(file), &content)?;
+    }
+
+    // .zshrc hands ZDOTDIR back so the user's .zlogin is read from the right place
+    let content = format!(
+        "ZDOTDIR=\"${KRAVEN_USER_ZDOTDIR}\"\n\
+         [[ -f \"$ZDOTDIR/.zshrc\" ]] && source \"$ZDOTDIR/.zshrc\"\n\
+         __kraven_prefix={}\n\
+         __kraven_precmd() {{ PROMPT=\"${{__kraven_prefix}}${{PROMPT#\"$__kraven_prefix\"}}\" }}\n\
+         precmd_functions+=(__kraven_precmd)\n",
+        sh_quote(prefix)
+    );
+    write_shim_file(&shim_dir.join(".zshrc"), &content)?;
+
+    cmd.env(KRAVEN_USER_ZDOTDIR, user_zdotdir);
+    cmd.env("ZDOTDIR", shim_dir);
+    Ok(())
+}
+
+/// xonsh reads the rc files listed in `$XONSHRC`, so append one that wraps `$PROMPT`.
+fn inject_xonsh(cmd: &mut Command, prefix: &str, shim_dir: &Path) -> Result<()> {
+    let rc_file = shim_dir.join("rc.xsh");
+    let content = format!(
+        "__kraven_prompt = $PROMPT\n\
+         $PROMPT = lambda: {} + (__kraven_prompt() if callable(__kraven_prompt) else __kraven_prompt)\n",
+        python_quote(prefix)
+    );
+    write_shim_file(&rc_file, &content)?;
+
+    let user_rc = std::env::var("XONSHRC").unwrap_or_else(|_| XONSH_DEFAULT_RC.to_string());
+    cmd.env("XONSHRC", format!("{user_rc}:{}", rc_file.display()));
+    Ok(())
+}
+
+/// Writes a new file in the shim directory, failing if something is already there.
+fn write_shim_file(path: &Path, content: &str) -> Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options
+        .open(path)
+        .and_then(|mut file| file.write_all(content.as_bytes()))
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Escapes a string for use inside bash double quotes.
+fn bash_quote(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len());
+    for c in s.chars() {
+        if matches!(c, '"' | '\\' | '$' | '`') {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted
+}
+
+/// Quotes a string as a single POSIX shell word.
+fn sh_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
+
+/// Quotes a string as a fish single-quoted string.
+fn fish_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\\', r"\\").replace('\'', r"\'"))
+}
+
+/// Quotes a string as a nushell double-quoted string.
+fn nu_quote(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str(r"\\"),
+            '\n' => quoted.push_str(r"\n"),
+            '\t' => quoted.push_str(r"\t"),
+            '\r' => quoted.push_str(r"\r"),
+            _ => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Quotes a string as a PowerShell single-quoted string.
+fn powershell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "''"))
+}
+
+/// Quotes a string as a Python (xonsh) string literal.
+fn python_quote(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('\'');
+    for c in s.chars() {
+        match c {
+            '\'' => quoted.push_str(r"\'"),
+            '\\' => quoted.push_str(r"\\"),
+            '\n' => quoted.push_str(r"\n"),
+            '\t' => quoted.push_str(r"\t"),
+            '\r' => quoted.push_str(r"\r"),
+            _ => quoted.push(c),
+        }
+    }
+    quoted.push('\'');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_program() {
+        assert_eq!(Shell::from_program("/bin/bash"), Shell::Bash);
+        assert_eq!(Shell::from_program("/usr/local/bin/fish"), Shell::Fish);
+        assert_eq!(Shell::from_program("nu"), Shell::Nu);
+        assert_eq!(Shell::from_program("powershell.exe"), Shell::Pwsh);
+        assert_eq!(Shell::from_program("/bin/dash"), Shell::Sh);
+        assert_eq!(Shell::from_program(""), Shell::Sh);
+    }
+
+    #[test]
+    fn test_export() {
+        assert_eq!(Shell::Bash.export("A", "it's"), r"export A='it'\''s'");
+        assert_eq!(Shell::Fish.export("A", r"a\b"), r"set -gx A 'a\\b'");
+        assert_eq!(Shell::Nu.export("A", "x\"y"), r#"$env.A = "x\"y""#);
+        assert_eq!(Shell::Pwsh.export("A", "it's"), "$env:A = 'it''s'");
+        assert_eq!(Shell::Xonsh.export("A", "a\nb"), r"$A = 'a\nb'");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_shim_dir_is_private_and_removed() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let mut cmd = Command::new("zsh");
+        let guard = Shell::Zsh.inject_prompt(&mut cmd, "(dev) ").unwrap();
+        let shim_dir = PathBuf::from(
+            cmd.get_envs()
+                .find(|(key, _)| *key == "ZDOTDIR")
+                .and_then(|(_, value)| value)
+                .unwrap(),
+        );
+        let mode = fs::metadata(&shim_dir).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o700);
+        assert!(shim_dir.join(".zshrc").is_file());
+        // Existing files are never written through
+        assert!(write_shim_file(&shim_dir.join(".zshrc"), "").is_err());
+
+        // Each session gets its own directory
+        let mut other = Command::new("xonsh");
+        let other_guard = Shell::Xonsh.inject_prompt(&mut other, "(dev) ").unwrap();
+        assert_ne!(other_guard.shim_dir.as_deref(), Some(shim_dir.as_path()));
+
+        drop(guard);
+        assert!(!shim_dir.exists());
+    }
+
+    #[test]
+    fn test_unset() {
+        assert_eq!(Shell::Zsh.unset("A"), "unset A");
+        assert_eq!(Shell::Fish.unset("A"), "set -e A");
+        assert_eq!(Shell::Nu.unset("A"), "hide-env A");
+        assert_eq!(Shell::Pwsh.unset("A"), "Remove-Item Env:A");
+        assert_eq!(Shell::Xonsh.unset("A"), "del $A");
+    }
+
+    #[test]
+    fn test_bash_quote() {
+        assert_eq!(bash_quote("(dev) "), "(dev) ");
+        assert_eq!(bash_quote(r#"["$x" \`] "#), r#"[\"\$x\" \\\`] "#);
+    }
+}
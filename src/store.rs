@@ -0,0 +1,379 @@
+//! Git versioning of the profile directory.
+//!
+//! When the root of a profile store is a git repository, commands that change
+//! profiles commit their changes to it, and `kraven sync` exchanges them with a
+//! remote. Everything goes through the `git` command, so the user's own git
+//! configuration (credentials, SSH keys, identity) applies.
+
+use anyhow::{bail, Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use std::sync::OnceLock;
+
+use crate::config;
+
+/// The remote `kraven sync` pulls from and pushes to.
+pub const REMOTE: &str = "origin";
+
+/// Files of the store that stay on this machine.
+const GITIGNORE: &str = "# Machine-specific kraven settings\n/config.toml\n/mask-key\n";
+
+/// Files that are never committed, on top of the store's ignore patterns:
+/// drafts kraven writes next to profiles, the key of masked fingerprints
+/// (ignored by stores created before it), and editor swap and lock files.
+const NEVER_COMMITTED: &[&str] = &[".*.kraven-*", "mask-key", ".*.sw?", ".#*", "#*#"];
+
+/// Lines git writes around conflicting changes.
+const CONFLICT_MARKERS: &[&str] = &["<<<<<<< ", "=======", ">>>>>>> "];
+
+/// Which side of a conflict to keep.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    /// The changes made on this machine.
+    Local,
+    /// The changes pulled from the remote.
+    Remote,
+}
+
+/// A profile store that is the root of a git repository.
+#[derive(Debug)]
+pub struct Repo {
+    dir: PathBuf,
+    /// Whether git has a committer identity configured, checked once.
+    has_identity: OnceLock<bool>,
+}
+
+impl Repo {
+    /// Opens the repository rooted at `dir`, if there is one.
+    ///
+    /// Only the root counts, so a project store inside a checked-out repository
+    /// is never committed to.
+    #[must_use]
+    pub fn open(dir: &Path) -> Option<Self> {
+        dir.join(".git").exists().then(|| Self::new(dir))
+    }
+
+    /// Turns `dir` into a repository holding its current profiles.
+    pub fn init(dir: &Path) -> Result<Self> {
+        let repo = Self::new(dir);
+        repo.run(&["init", "--quiet"])?;
+
+        let gitignore = dir.join(".gitignore");
+        if !gitignore.exists() {
+            fs::write(&gitignore, GITIGNORE)
+                .with_context(|| format!("Failed to write {}", gitignore.display()))?;
+        }
+        repo.commit_all("Initialize profile store")?;
+        Ok(repo)
+    }
+
+    fn new(dir: &Path) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+            has_identity: OnceLock::new(),
+        }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn git(&self) -> Command {
+        let mut cmd = Command::new("git");
+        cmd.arg("-C").arg(&self.dir);
+        let has_identity = self.has_identity.get_or_init(|| {
+            std::env::var_os("GIT_COMMITTER_EMAIL").is_some()
+                || Command::new("git")
+                    .arg("-C")
+                    .arg(&self.dir)
+                    .args(["config", "user.email"])
+                    .output()
+                    .is_ok_and(|output| output.status.success())
+        });
+        // Commits must not fail on machines where git has no identity yet
+        if !has_identity {
+            cmd.args([
+                "-c",
+                "user.name=kraven",
+                "-c",
+                "user.email=kraven@localhost",
+            ]);
+        }
+        cmd
+    }
+
+    fn output(&self, args: &[&str]) -> Result<Output> {
+        self.git()
+            .args(args)
+            .output()
+            .context("Failed to run git. Is it installed and on your PATH?")
+    }
+
+    /// Runs git, returning its standard output.
+    fn run(&self, args: &[&str]) -> Result<String> {
+        let output = self.output(args)?;
+        if !output.status.success() {
+            bail!(
+                "git {} failed: {}",
+                args.join(" "),
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+
+    /// Runs git, returning whether it succeeded.
+    fn check(&self, args: &[&str]) -> Result<bool> {
+        Ok(self.output(args)?.status.success())
+    }
+
+    /// Commits every change in the store, returning whether there was any.
+    ///
+    /// Backups, drafts and the files the store ignores are left out, as they
+    /// may hold secrets that should not reach the remote.
+    pub fn commit_all(&self, message: &str) -> Result<bool> {
+        let excludes = exclude_pathspecs(
+            NEVER_COMMITTED
+                .iter()
+                .map(|pattern| (*pattern).to_string())
+                .chain(config::ignore_patterns(&self.dir)),
+        );
+        let mut args = vec!["add", "--all", "--", "."];
+        args.extend(excludes.iter().map(String::as_str));
+        self.run(&args)?;
+        if self.check(&["diff", "--cached", "--quiet"])? {
+            return Ok(false);
+        }
+        self.run(&["commit", "--quiet", "--no-verify", "-m", message])?;
+        Ok(true)
+    }
+
+    /// Whether a sync stopped on conflicts that are not resolved yet.
+    pub fn is_merging(&self) -> bool {
+        self.check(&["rev-parse", "--quiet", "--verify", "MERGE_HEAD"])
+            .unwrap_or(false)
+    }
+
+    pub fn branch(&self) -> Result<String> {
+        Ok(self
+            .run(&["symbolic-ref", "--short", "HEAD"])
+            .context("The profile store is not on a branch")?
+            .trim()
+            .to_string())
+    }
+
+    pub fn remote_url(&self) -> Option<String> {
+        self.run(&["remote", "get-url", REMOTE])
+            .ok()
+            .map(|url| url.trim().to_string())
+    }
+
+    /// Points [`REMOTE`] at `url`.
+    pub fn set_remote(&self, url: &str) -> Result<()> {
+        if self.remote_url().is_some() {
+            self.run(&["remote", "set-url", REMOTE, url])?;
+        } else {
+            self.run(&["remote", "add", REMOTE, url])?;
+        }
+        Ok(())
+    }
+
+    pub fn fetch(&self) -> Result<()> {
+        self.run(&["fetch", "--quiet", REMOTE])?;
+        Ok(())
+    }
+
+    /// The remote branch matching `branch`, if the remote has it.
+    pub fn upstream(&self, branch: &str) -> Result<Option<String>> {
+        let upstream = format!("{REMOTE}/{branch}");
+        let exists = self.check(&["rev-parse", "--quiet", "--verify", &upstream])?;
+        Ok(exists.then_some(upstream))
+    }
+
+    /// Counts the commits reachable from `to` but not from `from`.
+    pub fn count_between(&self, from: &str, to: &str) -> Result<usize> {
+        let count = self.run(&["rev-list", "--count", &format!("{from}..{to}")])?;
+        count
+            .trim()
+            .parse()
+            .with_context(|| format!("Unexpected output from git rev-list: {count}"))
+    }
+
+    /// Merges `upstream`. Conflicts leave the merge in progress (see [`Repo::is_merging`]).
+    pub fn merge(&self, upstream: &str) -> Result<()> {
+        let output = self.output(&[
+            "merge",
+            "--quiet",
+            "--no-edit",
+            "--allow-unrelated-histories",
+            upstream,
+        ])?;
+        if output.status.success() || self.is_merging() {
+            return Ok(());
+        }
+        bail!(
+            "Failed to merge {upstream}: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )
+    }
+
+    /// Concludes a merge whose conflicts are all resolved.
+    pub fn finish_merge(&self) -> Result<()> {
+        self.run(&["commit", "--quiet", "--no-edit", "--no-verify"])?;
+        Ok(())
+    }
+
+    pub fn abort_merge(&self) -> Result<()> {
+        self.run(&["merge", "--abort"])?;
+        Ok(())
+    }
+
+    /// Files changed between two commits, relative to the store root.
+    pub fn changed_files(&self, from: &str, to: &str) -> Result<Vec<String>> {
+        Ok(self
+            .run(&["diff", "--name-only", "-z", from, to])?
+            .split('\0')
+            .filter(|path| !path.is_empty())
+            .map(str::to_string)
+            .collect())
+    }
+
+    /// Files with unresolved conflicts, relative to the store root.
+    pub fn conflicts(&self) -> Result<Vec<String>> {
+        let mut paths: Vec<String> = self
+            .run(&["diff", "--name-only", "-z", "--diff-filter=U"])?
+            .split('\0')
+            .filter(|path| !path.is_empty())
+            .map(str::to_string)
+            .collect();
+        paths.dedup();
+        Ok(paths)
+    }
+
+    /// Marks a conflicting file as resolved with its current content.
+    pub fn resolve(&self, path: &str) -> Result<()> {
+        if self.dir.join(path).exists() {
+            self.run(&["add", "--", path])?;
+        } else {
+            self.run(&["rm", "--quiet", "--cached", "--", path])?;
+        }
+        Ok(())
+    }
+
+    /// Resolves a conflicting file by keeping one side of it.
+    ///
+    /// If that side deleted the file, the file is deleted.
+    pub fn take(&self, path: &str, side: Side) -> Result<()> {
+        let flag = match side {
+            Side::Local => "--ours",
+            Side::Remote => "--theirs",
+        };
+        if self.check(&["checkout", flag, "--", path])? {
+            self.run(&["add", "--", path])?;
+        } else {
+            self.run(&["rm", "--quiet", "--force", "--", path])?;
+        }
+        Ok(())
+    }
+
+    /// Pushes `branch` to the remote.
+    pub fn push(&self, branch: &str) -> Result<()> {
+        self.run(&["push", "--quiet", "--set-upstream", REMOTE, branch])?;
+        Ok(())
+    }
+}
+
+/// Whether `content` still has conflict markers in it.
+#[must_use]
+pub fn has_conflict_markers(content: &str) -> bool {
+    content.lines().any(|line| {
+        CONFLICT_MARKERS
+            .iter()
+            .any(|marker| line.starts_with(marker))
+    })
+}
+
+/// Turns ignore patterns into git pathspecs excluding the files, and the
+/// contents of the directories, they match.
+fn exclude_pathspecs(patterns: impl IntoIterator<Item = String>) -> Vec<String> {
+    let mut pathspecs = Vec::new();
+    for pattern in patterns {
+        // kraven patterns only know `*` and `?`
+        let mut escaped = String::with_capacity(pattern.len());
+        for c in pattern.chars() {
+            if matches!(c, '[' | ']' | '\\') {
+                escaped.push('\\');
+            }
+            escaped.push(c);
+        }
+        let path = match escaped.strip_prefix('/') {
+            Some(anchored) => anchored.to_string(),
+            None if escaped.contains('/') => escaped,
+            None => format!("**/{escaped}"),
+        };
+        pathspecs.push(format!(":(exclude,glob){path}"));
+        pathspecs.push(format!(":(exclude,glob){path}/**"));
+    }
+    pathspecs
+}
+
+/// Commits the changes of a command to the store at `root`, if it is versioned.
+///
+/// A failure to commit never fails the command: the change itself was made, and
+/// the next commit or `kraven sync` picks it up.
+pub fn commit(root: &Path, message: &str) {
+    let Some(repo) = Repo::open(root) else {
+        return;
+    };
+    // Committing now would conclude the merge with the conflicts still in it
+    if repo.is_merging() {
+        eprintln!("Warning: a sync is in progress, so this change was not committed yet.");
+        return;
+    }
+    if let Err(err) = repo.commit_all(message) {
+        eprintln!("Warning: failed to commit to the profile store: {err:#}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    #[test]
+    fn test_has_conflict_markers() {
+        let conflicted = "A=1\n<<<<<<< HEAD\nB=2\n=======\nB=3\n>>>>>>> origin/main\n";
+        assert!(has_conflict_markers(conflicted));
+        assert!(!has_conflict_markers("A=1\nB=\"<<<<<<< \"\n# =====\n"));
+    }
+
+    #[test]
+    fn test_exclude_pathspecs() {
+        let patterns = ["*.bak", "/scratch", "work/tmp", "x[1]"].map(String::from);
+        assert_eq!(
+            exclude_pathspecs(patterns),
+            [
+                ":(exclude,glob)**/*.bak",
+                ":(exclude,glob)**/*.bak/**",
+                ":(exclude,glob)scratch",
+                ":(exclude,glob)scratch/**",
+                ":(exclude,glob)work/tmp",
+                ":(exclude,glob)work/tmp/**",
+                ":(exclude,glob)**/x\\[1\\]",
+                ":(exclude,glob)**/x\\[1\\]/**",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_open_requires_repository_root() {
+        let dir = TempDir::new("store");
+        fs::create_dir_all(dir.join("nested")).unwrap();
+        assert!(Repo::open(&dir).is_none());
+
+        fs::create_dir_all(dir.join(".git")).unwrap();
+        assert!(Repo::open(&dir).is_some());
+        assert!(Repo::open(&dir.join("nested")).is_none());
+    }
+}
@@ -0,0 +1,265 @@
+//! Profile templates.
+//!
+//! A template is a profile whose values may be placeholders that are asked for
+//! when a profile is created from it:
+//!
+//! ```text
+//! AWS_REGION={{ prompt "AWS region" default="us-east-1" }}
+//! AWS_SECRET_ACCESS_KEY={{ secret }}
+//! ```
+
+use anyhow::{bail, Context, Result};
+use std::path::Path;
+
+use crate::interact::Prompter;
+use crate::profile::quote_value;
+
+const OPEN: &str = "{{";
+const CLOSE: &str = "}}";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    /// Asked with the input echoed.
+    Prompt,
+    /// Asked without echoing the input.
+    Secret,
+}
+
+/// A `{{ ... }}` placeholder.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Placeholder {
+    kind: Kind,
+    /// What to ask for; defaults to the variable name.
+    label: Option<String>,
+    default: Option<String>,
+}
+
+/// A word of a placeholder, e.g. `prompt`, `"AWS region"` or `default="us-east-1"`.
+#[derive(Debug, Default)]
+struct Token {
+    /// The unquoted part, e.g. `default=`.
+    bare: String,
+    /// The quoted part, if any.
+    quoted: Option<String>,
+}
+
+/// Renders a template, asking the user for each placeholder.
+///
+/// Placeholders must make up a whole value. Answers are quoted as needed so
+/// the result parses back to exactly what was typed.
+pub fn render(content: &str, path: &Path, prompter: &mut dyn Prompter) -> Result<String> {
+    let mut rendered = String::with_capacity(content.len());
+
+    for (line_num, line) in content.lines().enumerate() {
+        let line_num = line_num + 1; // 1-indexed for error messages
+        let trimmed = line.trim();
+
+        if trimmed.starts_with('#') || !trimmed.contains(OPEN) {
+            rendered.push_str(line);
+            rendered.push('\n');
+            continue;
+        }
+
+        let Some((key, value)) = trimmed.split_once('=') else {
+            bail!(
+                "{}:{line_num}: Placeholders are only allowed in values: {trimmed}",
+                path.display()
+            );
+        };
+        let key = key.trim();
+        let Some(inner) = value
+            .trim()
+            .strip_prefix(OPEN)
+            .and_then(|rest| rest.strip_suffix(CLOSE))
+        else {
+            bail!(
+                "{}:{line_num}: A placeholder must be the whole value of '{key}'",
+                path.display()
+            );
+        };
+
+        let placeholder = parse_placeholder(inner)
+            .with_context(|| format!("{}:{line_num}: Invalid placeholder", path.display()))?;
+        let answer = ask(&placeholder, key, prompter)?;
+        rendered.push_str(key);
+        rendered.push('=');
+        rendered.push_str(&quote_value(&answer));
+        rendered.push('\n');
+    }
+
+    Ok(rendered)
+}
+
+fn ask(placeholder: &Placeholder, key: &str, prompter: &mut dyn Prompter) -> Result<String> {
+    let label = placeholder.label.as_deref().unwrap_or(key);
+    let prompt = match &placeholder.default {
+        Some(default) => format!("{label} [{default}]: "),
+        None => format!("{label}: "),
+    };
+
+    loop {
+        let answer = match placeholder.kind {
+            Kind::Prompt => prompter.read_line(&prompt),
+            Kind::Secret => prompter.read_secret(&prompt),
+        }
+        .with_context(|| format!("No value given for '{key}'"))?;
+        if !answer.is_empty() {
+            return Ok(answer);
+        }
+        if let Some(default) = &placeholder.default {
+            return Ok(default.clone());
+        }
+        // Without a terminal, asking again would read the same end of input forever
+        if !prompter.is_interactive() {
+            bail!("No value given for '{key}'");
+        }
+    }
+}
+
+fn parse_placeholder(inner: &str) -> Result<Placeholder> {
+    let mut tokens = tokenize(inner)?.into_iter();
+
+    let kind = match tokens.next() {
+        Some(token) if token.quoted.is_none() && token.bare == "prompt" => Kind::Prompt,
+        Some(token) if token.quoted.is_none() && token.bare == "secret" => Kind::Secret,
+        _ => bail!("expected 'prompt' or 'secret' in '{{{{{inner}}}}}'"),
+    };
+
+    let mut placeholder = Placeholder {
+        kind,
+        label: None,
+        default: None,
+    };
+    for token in tokens {
+        match (token.bare.as_str(), token.quoted) {
+            ("", Some(label)) if placeholder.label.is_none() => placeholder.label = Some(label),
+            ("default=", Some(_)) if kind == Kind::Secret => {
+                bail!("secrets cannot have a default, it would be stored in the template")
+            }
+            ("default=", Some(default)) => placeholder.default = Some(default),
+            (bare, quoted) => bail!(
+                "unexpected '{bare}{}'",
+                quoted
+                    .map(|quoted| format!("\"{quoted}\""))
+                    .unwrap_or_default()
+            ),
+        }
+    }
+
+    Ok(placeholder)
+}
+
+fn tokenize(inner: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut current: Option<Token> = None;
+    let mut chars = inner.chars();
+
+    while let Some(c) = chars.next() {
+        if c.is_whitespace() {
+            tokens.extend(current.take());
+            continue;
+        }
+
+        let token = current.get_or_insert_with(Token::default);
+        if token.quoted.is_some() {
+            bail!("expected a space after a quoted string");
+        }
+        if c != '"' {
+            token.bare.push(c);
+            continue;
+        }
+
+        let mut quoted = String::new();
+        loop {
+            match chars.next() {
+                Some('"') => break,
+                Some('\\') => quoted.extend(chars.next()),
+                Some(c) => quoted.push(c),
+                None => bail!("unclosed '\"'"),
+            }
+        }
+        token.quoted = Some(quoted);
+    }
+
+    tokens.extend(current);
+    Ok(tokens)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interact::testing::Scripted;
+    use crate::profile::Profile;
+    use std::path::PathBuf;
+
+    fn test_path() -> PathBuf {
+        PathBuf::from("templates/aws")
+    }
+
+    #[test]
+    fn test_render_asks_for_placeholders() {
+        let template = "# AWS\n\
+                        AWS_REGION={{ prompt \"AWS region\" default=\"us-east-1\" }}\n\
+                        AWS_ACCESS_KEY_ID={{ prompt }}\n\
+                        AWS_SECRET_ACCESS_KEY={{ secret \"Secret key\" }}\n\
+                        OUTPUT=json\n";
+        let mut prompter = Scripted::new(&["", "AKIA123", "s3cr\"t \\ key"]);
+
+        let rendered = render(template, &test_path(), &mut prompter).unwrap();
+
+        assert_eq!(
+            prompter.prompts,
+            [
+                "AWS region [us-east-1]: ",
+                "AWS_ACCESS_KEY_ID: ",
+                "secret: Secret key: ",
+            ]
+        );
+        let profile = Profile::parse(&rendered, &test_path()).unwrap();
+        assert_eq!(profile.vars["AWS_REGION"], "us-east-1");
+        assert_eq!(profile.vars["AWS_ACCESS_KEY_ID"], "AKIA123");
+        assert_eq!(profile.vars["AWS_SECRET_ACCESS_KEY"], "s3cr\"t \\ key");
+        assert_eq!(profile.vars["OUTPUT"], "json");
+        assert!(rendered.starts_with("# AWS\n"));
+    }
+
+    #[test]
+    fn test_required_answer_is_asked_again() {
+        let mut prompter = Scripted::new(&["", "value"]);
+        let rendered = render("KEY={{ prompt }}", &test_path(), &mut prompter).unwrap();
+        assert_eq!(rendered, "KEY=value\n");
+
+        let mut prompter = Scripted::new(&[""]);
+        prompter.interactive = false;
+        assert!(render("KEY={{ prompt }}", &test_path(), &mut prompter).is_err());
+
+        // The end of input stops the questions, as Ctrl-D does on a terminal
+        let mut prompter = Scripted::new(&["", ""]);
+        let err = render("KEY={{ prompt }}", &test_path(), &mut prompter).unwrap_err();
+        assert!(
+            err.to_string().contains("No value given for 'KEY'"),
+            "{err}"
+        );
+        assert_eq!(prompter.prompts.len(), 3);
+    }
+
+    #[test]
+    fn test_invalid_placeholders() {
+        let invalid = [
+            "KEY=prefix-{{ prompt }}",
+            "{{ prompt }}",
+            "KEY={{ ask }}",
+            "KEY={{ prompt \"unclosed }}",
+            "KEY={{ secret default=\"hunter2\" }}",
+            "KEY={{ prompt \"a\" \"b\" }}",
+            "KEY={{ prompt color=\"red\" }}",
+        ];
+        for template in invalid {
+            let mut prompter = Scripted::new(&["x", "x"]);
+            assert!(
+                render(template, &test_path(), &mut prompter).is_err(),
+                "{template}"
+            );
+        }
+    }
+}
@@ -0,0 +1,99 @@
+//! The project hooks the user has allowed to run.
+//!
+//! Project stores come with checked-out repositories, so their hooks only run
+//! once the user has reviewed and allowed them with `kraven allow`. The list
+//! has a line per allowed profile: the fingerprint of its hooks (see
+//! [`Hooks::fingerprint`]) and the path of the profile file. Changing a hook,
+//! or finding the profile at another path, needs a new approval.
+
+use anyhow::{bail, Context, Result};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use crate::config::get_allowed_hooks_path;
+use crate::hooks::Hooks;
+
+/// Whether the hooks of the profile stored at `profile` were allowed as they are now.
+pub fn is_allowed(profile: &Path, hooks: &Hooks) -> Result<bool> {
+    is_allowed_in(&get_allowed_hooks_path()?, profile, &hooks.fingerprint())
+}
+
+/// Allows the hooks of the profile stored at `profile` as they are now.
+pub fn allow(profile: &Path, hooks: &Hooks) -> Result<()> {
+    allow_in(&get_allowed_hooks_path()?, profile, &hooks.fingerprint())
+}
+
+fn is_allowed_in(list: &Path, profile: &Path, fingerprint: &str) -> Result<bool> {
+    let key = key(profile);
+    Ok(read(list)?
+        .iter()
+        .any(|(allowed, path)| allowed == fingerprint && *path == key))
+}
+
+fn allow_in(list: &Path, profile: &Path, fingerprint: &str) -> Result<()> {
+    let key = key(profile);
+    let Some(line_key) = key.to_str().filter(|key| !key.contains('\n')) else {
+        bail!("Cannot allow hooks of a profile at {}", profile.display());
+    };
+
+    let mut lines: Vec<String> = read(list)?
+        .into_iter()
+        .filter(|(_, path)| *path != key)
+        .map(|(allowed, path)| format!("{allowed} {}\n", path.display()))
+        .collect();
+    lines.push(format!("{fingerprint} {line_key}\n"));
+
+    if let Some(parent) = list.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create directory: {}", parent.display()))?;
+    }
+    fs::write(list, lines.concat()).with_context(|| format!("Failed to write {}", list.display()))
+}
+
+/// Reads the fingerprints and profile paths of the list.
+fn read(list: &Path) -> Result<Vec<(String, PathBuf)>> {
+    let content = match fs::read_to_string(list) {
+        Ok(content) => content,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err).with_context(|| format!("Failed to read {}", list.display())),
+    };
+    Ok(content
+        .lines()
+        .filter_map(|line| line.split_once(' '))
+        .map(|(fingerprint, path)| (fingerprint.to_string(), PathBuf::from(path)))
+        .collect())
+}
+
+/// The path a profile is listed under, the same however it was reached.
+fn key(profile: &Path) -> PathBuf {
+    fs::canonicalize(profile).unwrap_or_else(|_| profile.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    #[test]
+    fn test_allow_is_kept_per_profile_and_fingerprint() {
+        let dir = TempDir::new("trust");
+        let list = dir.join("data/allowed-hooks");
+        let (dev, ci) = (dir.join("dev"), dir.join("ci"));
+        fs::write(&dev, "").unwrap();
+        fs::write(&ci, "").unwrap();
+
+        assert!(!is_allowed_in(&list, &dev, "sha256:a").unwrap());
+        allow_in(&list, &dev, "sha256:a").unwrap();
+        allow_in(&list, &ci, "sha256:a").unwrap();
+        assert!(is_allowed_in(&list, &dev, "sha256:a").unwrap());
+        assert!(!is_allowed_in(&list, &dev, "sha256:b").unwrap());
+
+        // Allowing changed hooks replaces the old approval
+        allow_in(&list, &dev, "sha256:b").unwrap();
+        assert!(!is_allowed_in(&list, &dev, "sha256:a").unwrap());
+        assert!(is_allowed_in(&list, &dev, "sha256:b").unwrap());
+        assert!(is_allowed_in(&list, &ci, "sha256:a").unwrap());
+        assert_eq!(fs::read_to_string(&list).unwrap().lines().count(), 2);
+    }
+}
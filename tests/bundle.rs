@@ -0,0 +1,81 @@
+//! Exports profiles to bundles and imports them into another directory.
+
+mod common;
+
+use std::fs;
+
+use common::ProfileDir;
+
+#[test]
+fn test_bundle_roundtrip() {
+    let source = ProfileDir::new("bundle-src");
+    fs::write(
+        source.0.join("secret"),
+        "#@secret: TOKEN\nTOKEN=hunter2\nURL=https://example.com\n",
+    )
+    .unwrap();
+    let bundle = source.0.join("team.kvb");
+
+    let output = source
+        .kraven()
+        .args(["bundle", "export", "test", "secret", "--encrypt", "-o"])
+        .arg(&bundle)
+        .env("KRAVEN_BUNDLE_PASSPHRASE", "correct horse")
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+    assert!(!fs::read(&bundle)
+        .unwrap()
+        .windows(7)
+        .any(|w| w == b"hunter2"));
+
+    let target = ProfileDir::new("bundle-dst");
+    fs::write(target.0.join("secret"), "TOKEN=mine\n").unwrap();
+    let import = |args: &[&str]| {
+        target
+            .kraven()
+            .args(["bundle", "import"])
+            .arg(&bundle)
+            .args(args)
+            .env("KRAVEN_BUNDLE_PASSPHRASE", "correct horse")
+            .output()
+            .unwrap()
+    };
+
+    // The conflicting profile stops the import until --force is given
+    let output = import(&[]);
+    assert!(!output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("unchanged  test"), "{stdout}");
+    assert!(stdout.contains("conflict   secret"), "{stdout}");
+    assert_eq!(
+        fs::read_to_string(target.0.join("secret")).unwrap(),
+        "TOKEN=mine\n"
+    );
+
+    assert!(import(&["--force"]).status.success());
+    assert!(fs::read_to_string(target.0.join("secret"))
+        .unwrap()
+        .contains("TOKEN=hunter2"));
+
+    // Skeletons keep the secret's name but not its value
+    let skeleton = source.0.join("skeleton.kvb");
+    let output = source
+        .kraven()
+        .args(["bundle", "export", "secret", "--exclude-secrets", "-o"])
+        .arg(&skeleton)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+    let output = target
+        .kraven()
+        .args(["bundle", "import", "--force"])
+        .arg(&skeleton)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(
+        fs::read_to_string(target.0.join("secret")).unwrap(),
+        "#@secret: TOKEN\nTOKEN=\nURL=https://example.com\n"
+    );
+}
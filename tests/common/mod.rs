@@ -0,0 +1,72 @@
+//! The fixture shared by the integration tests.
+//!
+//! Every test file compiles its own copy and uses only part of it.
+#![allow(dead_code)]
+
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+
+pub const KRAVEN: &str = env!("CARGO_BIN_EXE_kraven");
+
+/// A value exercising every kind of quoting the shells care about.
+pub const TRICKY_VALUE: &str = r#"it's "quoted" $HOME `cmd` \back\slash {brace} ; # end"#;
+
+/// A temporary profile directory with a single `test` profile.
+pub struct ProfileDir(pub PathBuf);
+
+impl ProfileDir {
+    pub fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("kraven-it-{name}-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let escaped = TRICKY_VALUE.replace('\\', r"\\").replace('"', "\\\"");
+        fs::write(
+            dir.join("test"),
+            format!("KRAVEN_TEST_VALUE=\"{escaped}\"\nKRAVEN_TEST_PLAIN=plain\n"),
+        )
+        .unwrap();
+        Self(dir)
+    }
+
+    pub fn kraven(&self) -> Command {
+        let mut cmd = Command::new(KRAVEN);
+        cmd.env("KRAVEN_PROFILE_DIR", &self.0)
+            .env("KRAVEN_CONFIG", self.0.join("config.toml"))
+            .env("KRAVEN_AUDIT_LOG", self.0.join("audit.log"))
+            .env("KRAVEN_ALLOWED_HOOKS", self.0.join(".allowed-hooks"))
+            .env_remove("KRAVEN_ACTIVE")
+            .env_remove("KRAVEN_STACK")
+            .env_remove("KRAVEN_EDITOR");
+        cmd
+    }
+}
+
+impl Drop for ProfileDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+pub fn find_program(name: &str) -> Option<PathBuf> {
+    let path = std::env::var_os("PATH")?;
+    std::env::split_paths(&path)
+        .map(|dir| dir.join(name))
+        .find(|candidate| candidate.is_file())
+}
+
+pub fn run_with_stdin(mut cmd: Command, stdin: &str) -> Output {
+    let mut child = cmd
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
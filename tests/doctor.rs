@@ -0,0 +1,96 @@
+//! Checks the setup for problems with `kraven doctor`.
+
+mod common;
+
+use std::fs;
+use std::process::Command;
+
+use common::ProfileDir;
+
+#[cfg(unix)]
+#[test]
+fn test_doctor() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let profiles = ProfileDir::new("doctor");
+    let path = |name: &str| profiles.0.join(name);
+    let mode = |name: &str| fs::metadata(path(name)).unwrap().permissions().mode() & 0o777;
+    fs::set_permissions(&profiles.0, fs::Permissions::from_mode(0o700)).unwrap();
+    fs::set_permissions(path("test"), fs::Permissions::from_mode(0o644)).unwrap();
+    fs::write(path("dupes"), "A=1\nA=2\n").unwrap();
+    fs::set_permissions(path("dupes"), fs::Permissions::from_mode(0o600)).unwrap();
+    fs::write(path("my dev"), "A=1\n").unwrap();
+    fs::set_permissions(path("my dev"), fs::Permissions::from_mode(0o600)).unwrap();
+    // Files that are not profiles are still checked for permissions
+    for name in ["dev~", "hooks/test/pre-activate"] {
+        fs::create_dir_all(path(name).parent().unwrap()).unwrap();
+        fs::write(path(name), "A=1\n").unwrap();
+        fs::set_permissions(path(name), fs::Permissions::from_mode(0o644)).unwrap();
+    }
+    fs::set_permissions(path("hooks"), fs::Permissions::from_mode(0o700)).unwrap();
+    fs::set_permissions(path("hooks/test"), fs::Permissions::from_mode(0o700)).unwrap();
+    let doctor = |args: &[&str]| {
+        profiles
+            .kraven()
+            .env("SHELL", "/bin/sh")
+            .env("EDITOR", "true")
+            .env_remove("KRAVEN_SHELL")
+            .arg("doctor")
+            .args(args)
+            .output()
+            .unwrap()
+    };
+
+    // Warnings only
+    let output = doctor(&[]);
+    assert!(output.status.success(), "{output:?}");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("[pass] profiles: 2 parsed"), "{stdout}");
+    assert!(stdout.contains("[warn] permissions: 3 path(s)"), "{stdout}");
+    assert!(stdout.contains("hooks/test/pre-activate"), "{stdout}");
+    assert!(stdout.contains("[warn] profile names"), "{stdout}");
+    assert!(
+        stdout.contains("my dev: contains invalid character ' '"),
+        "{stdout}"
+    );
+    // Backups are not taken for profiles
+    assert!(!stdout.contains("dev~:"), "{stdout}");
+    assert!(stdout.contains("dupes: A on lines 1, 2"), "{stdout}");
+    assert!(stdout.contains("[pass] editor"), "{stdout}");
+    assert!(stdout.contains("kraven doctor --fix"), "{stdout}");
+
+    // Failures exit with an error; the safe fixes are still applied
+    fs::write(path("bad"), "B\n").unwrap();
+    let output = doctor(&["--fix"]);
+    assert_eq!(output.status.code(), Some(1), "{output:?}");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        stdout.contains("[fail] profiles: 1 of 3 have errors"),
+        "{stdout}"
+    );
+    assert!(
+        stdout.contains("bad: 1 error(s), the first on line 1"),
+        "{stdout}"
+    );
+    assert!(stdout.contains("[pass] permissions: fixed"), "{stdout}");
+    assert_eq!(mode("test"), 0o600);
+    assert_eq!(mode("bad"), 0o600);
+    assert_eq!(mode("dev~"), 0o600);
+    assert_eq!(mode("hooks/test/pre-activate"), 0o600);
+    fs::remove_file(path("bad")).unwrap();
+
+    // A session variable outliving its kraven process
+    let mut finished = Command::new("true").spawn().unwrap();
+    finished.wait().unwrap();
+    let output = profiles
+        .kraven()
+        .env("KRAVEN_ACTIVE", "test")
+        .env("KRAVEN_PID", finished.id().to_string())
+        .arg("doctor")
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1), "{output:?}");
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .contains("[fail] session: KRAVEN_ACTIVE=test is left over"));
+}
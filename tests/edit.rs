@@ -0,0 +1,100 @@
+//! Edits profiles in the user's editor with `kraven edit`.
+
+mod common;
+
+use std::fs;
+
+use common::ProfileDir;
+
+#[test]
+fn test_edit_reports_every_error() {
+    let profiles = ProfileDir::new("edit-errors");
+    fs::write(profiles.0.join("broken"), "A=1\nB\n9X=2\n").unwrap();
+    // An editor that saves the file unchanged
+    let output = profiles
+        .kraven()
+        .env("EDITOR", "true")
+        .env_remove("VISUAL")
+        .args(["edit", "broken"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("has 2 error(s)"), "{stderr}");
+    assert!(stderr.contains("2 | B\n  | ^\n"), "{stderr}");
+    assert!(stderr.contains("3 | 9X=2\n  | ^^\n"), "{stderr}");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_edit_with_quoted_editor() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let profiles = ProfileDir::new("editor");
+    let editor_dir = profiles.0.join("My Editor");
+    fs::create_dir_all(&editor_dir).unwrap();
+    let script = editor_dir.join("edit");
+    fs::write(
+        &script,
+        "#!/bin/sh\n[ \"$1\" = --wait ] && echo FROM=script > \"$2\"\n",
+    )
+    .unwrap();
+    fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+
+    // KRAVEN_EDITOR wins over EDITOR
+    let output = profiles
+        .kraven()
+        .env("KRAVEN_EDITOR", format!("'{}' --wait", script.display()))
+        .env("EDITOR", "false")
+        .args(["edit", "new"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(
+        fs::read_to_string(profiles.0.join("new")).unwrap(),
+        "FROM=script\n"
+    );
+
+    let output = profiles
+        .kraven()
+        .env("EDITOR", "false")
+        .args(["edit", "new"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(8), "{output:?}");
+}
+
+#[test]
+fn test_edit_new_profile_scaffold() {
+    let profiles = ProfileDir::new("scaffold");
+    let edit = |editor: &str| {
+        profiles
+            .kraven()
+            .env("EDITOR", editor)
+            .args(["edit", "new"])
+            .output()
+            .unwrap()
+    };
+
+    // Quitting with the scaffold untouched creates nothing
+    let output = edit("true");
+    assert!(output.status.success(), "{output:?}");
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .contains("was not created"));
+    assert!(!profiles.0.join("new").exists());
+
+    // The store's default-template replaces the scaffold
+    fs::write(profiles.0.join("default-template"), "#@protected\n").unwrap();
+    let output = edit(r#"sh -c 'echo B=2 >> "$0"'"#);
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(
+        fs::read_to_string(profiles.0.join("new")).unwrap(),
+        "#@protected\nB=2\n"
+    );
+
+    let output = profiles.kraven().arg("list").output().unwrap();
+    assert!(!String::from_utf8(output.stdout)
+        .unwrap()
+        .contains("default-template"));
+}
@@ -0,0 +1,67 @@
+//! Reports problems in profiles, with distinct exit codes and as JSON.
+
+mod common;
+
+use std::fs;
+
+use common::ProfileDir;
+
+#[test]
+fn test_error_exit_codes() {
+    let profiles = ProfileDir::new("errors");
+    fs::write(profiles.0.join("broken"), "A=1\nB\n").unwrap();
+    let run = |args: &[&str]| profiles.kraven().args(args).output().unwrap();
+
+    let output = run(&["show", "missing"]);
+    assert_eq!(output.status.code(), Some(3), "{output:?}");
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("Profile 'missing' does not exist."));
+
+    assert_eq!(run(&["show", "broken"]).status.code(), Some(4));
+    assert_eq!(run(&["show", "../etc"]).status.code(), Some(5));
+    let output = profiles
+        .kraven()
+        .env("KRAVEN_ACTIVE", "outer")
+        .args(["activate", "test", "--nesting", "deny"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(6), "{output:?}");
+
+    let output = run(&["--output", "json", "show", "broken"]);
+    assert_eq!(output.status.code(), Some(4));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert_eq!(stderr.lines().count(), 1, "{stderr}");
+    assert!(stderr.contains(r#""kind":"parse""#), "{stderr}");
+    assert!(stderr.contains(r#""line":2"#), "{stderr}");
+}
+
+#[test]
+fn test_unknown_directive_is_a_warning() {
+    let profiles = ProfileDir::new("directive");
+    fs::write(profiles.0.join("dev"), "#@todo rotate\nA=1\n").unwrap();
+
+    let output = profiles
+        .kraven()
+        .args(["get", "dev", "A"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "1\n");
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("dev:1: Unknown directive '#@todo rotate', ignored."),
+        "{stderr}"
+    );
+
+    let output = profiles.kraven().arg("doctor").output().unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        stdout.contains("[warn] profiles: 2 parsed, ignoring 1 line(s)"),
+        "{stdout}"
+    );
+    assert!(
+        stdout.contains("dev: line 1: Unknown directive '#@todo rotate'"),
+        "{stdout}"
+    );
+}
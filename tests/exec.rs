@@ -0,0 +1,45 @@
+//! Runs commands with the variables of a profile through `kraven exec`.
+
+mod common;
+
+use std::fs;
+
+use common::{ProfileDir, TRICKY_VALUE};
+
+#[test]
+fn test_exec_runs_command_with_profile() {
+    let profiles = ProfileDir::new("exec");
+    let output = profiles
+        .kraven()
+        .args(["exec", "test", "--", "sh", "-c"])
+        .arg(r#"printf '%s\n' "$KRAVEN_TEST_VALUE"; exit 4"#)
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(4));
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        format!("{TRICKY_VALUE}\n")
+    );
+}
+
+#[test]
+fn test_exec_redacts_output() {
+    let profiles = ProfileDir::new("exec-redact");
+    fs::write(profiles.0.join("ci"), "TOKEN=hunter22\nREGION=eu\n").unwrap();
+    // The token is written in two parts, so it spans two reads
+    let output = profiles
+        .kraven()
+        .args(["exec", "ci", "--redact", "--", "sh", "-c"])
+        .arg(
+            r#"printf 'token=hun'; sleep 0.2; printf 'ter22 in %s\n' "$REGION"; \
+               echo "$TOKEN" >&2; exit 3"#,
+        )
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(3));
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "token=***TOKEN*** in eu\n"
+    );
+    assert_eq!(String::from_utf8(output.stderr).unwrap(), "***TOKEN***\n");
+}
@@ -0,0 +1,59 @@
+//! Reads single values with `kraven get` and filters the keys `kraven show` prints.
+
+mod common;
+
+use std::fs;
+
+use common::ProfileDir;
+
+#[test]
+fn test_get_and_show_filters() {
+    let profiles = ProfileDir::new("get");
+    fs::write(
+        profiles.0.join("aws"),
+        "AWS_REGION=eu-west-1\nAWS_PROFILE=prod\nURL=\"a b\"\n",
+    )
+    .unwrap();
+    let run = |args: &[&str]| profiles.kraven().args(args).output().unwrap();
+
+    // Raw values, without the quoting `show` adds
+    let output = run(&["get", "aws", "URL"]);
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "a b\n");
+
+    let output = run(&[
+        "get",
+        "aws",
+        "AWS_REGION",
+        "MISSING",
+        "-d",
+        "none",
+        "-s",
+        ",",
+    ]);
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "eu-west-1,none\n"
+    );
+
+    let output = run(&["get", "aws", "MISSING"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(output.stdout.is_empty());
+
+    let output = run(&["show", "aws", "AWS_*"]);
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "AWS_PROFILE=prod\nAWS_REGION=eu-west-1\n"
+    );
+    assert!(!run(&["show", "aws", "AWS_REGON"]).status.success());
+
+    // The keys come after the profile, which still completes
+    let output = profiles
+        .kraven()
+        .env("COMPLETE", "fish")
+        .args(["--", "kraven", "show", "a"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "aws\n");
+}
@@ -0,0 +1,39 @@
+//! Searches the keys and values of all profiles with `kraven grep`.
+
+mod common;
+
+use std::fs;
+
+use common::ProfileDir;
+
+#[test]
+fn test_grep_masks_values() {
+    let profiles = ProfileDir::new("grep");
+    fs::write(
+        profiles.0.join("team-a"),
+        "API_TOKEN=token-aaaa-1234\nURL=https://a.example.com\n",
+    )
+    .unwrap();
+    let grep = |args: &[&str]| profiles.kraven().arg("grep").args(args).output().unwrap();
+
+    let output = grep(&["TOKEN"]);
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "team-a: API_TOKEN=to***********34\n"
+    );
+
+    // Values are searched but never printed
+    let output = grep(&["--values", "aaaa"]);
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "team-a: API_TOKEN=to***********34\n"
+    );
+    let output = grep(&["--keys", "aaaa"]);
+    assert_eq!(output.status.code(), Some(1), "{output:?}");
+
+    let output = grep(&["*.example.com"]);
+    assert!(output.status.success(), "{output:?}");
+    let output = grep(&["--values", "KRAVEN_TEST_*"]);
+    assert_eq!(output.status.code(), Some(1), "{output:?}");
+}
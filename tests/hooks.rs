@@ -0,0 +1,117 @@
+//! Runs the hooks of a profile around its activation.
+
+mod common;
+
+use std::fs;
+
+use common::{run_with_stdin, ProfileDir};
+
+#[test]
+fn test_hooks_run_around_command() {
+    let profiles = ProfileDir::new("hooks");
+    fs::write(
+        profiles.0.join("hooked"),
+        "#@pre-activate: echo \"pre $HOOKED\"\n\
+         #@post-deactivate: echo \"post $HOOKED\"\n\
+         HOOKED=yes\n",
+    )
+    .unwrap();
+
+    let output = profiles
+        .kraven()
+        .args(["exec", "hooked", "--", "sh", "-c", "echo \"cmd $HOOKED\""])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(output.stdout, b"cmd yes\n");
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "pre yes\npost yes\n"
+    );
+
+    let output = profiles
+        .kraven()
+        .args(["exec", "hooked", "--no-hooks", "--", "true"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+    assert!(output.stderr.is_empty());
+}
+
+#[test]
+fn test_failing_pre_hook_aborts_activation() {
+    let profiles = ProfileDir::new("hook-fail");
+    fs::write(profiles.0.join("broken"), "#@pre-activate: exit 1\n").unwrap();
+
+    let mut cmd = profiles.kraven();
+    cmd.args(["activate", "broken", "--shell", "sh"]);
+    let output = run_with_stdin(cmd, "echo started\n");
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+
+    let mut cmd = profiles.kraven();
+    cmd.args(["activate", "broken", "--shell", "sh", "--no-hooks"]);
+    let output = run_with_stdin(cmd, "echo started\n");
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(output.stdout, b"started\n");
+}
+
+#[test]
+fn test_project_hooks_need_approval() {
+    let profiles = ProfileDir::new("hooks-global");
+    let checkout = ProfileDir::new("hooks-checkout");
+    let store = checkout.0.join(".kraven/profiles");
+    fs::create_dir_all(store.join("hooks/dev")).unwrap();
+    fs::write(store.join("dev"), "#@pre-activate: echo pre\nA=1\n").unwrap();
+    let exec = |args: &[&str]| {
+        profiles
+            .kraven()
+            .current_dir(&checkout.0)
+            .args(["exec", "dev"])
+            .args(args)
+            .args(["--", "echo", "cmd"])
+            .output()
+            .unwrap()
+    };
+    let allow = |args: &[&str]| {
+        profiles
+            .kraven()
+            .current_dir(&checkout.0)
+            .args(["allow", "dev"])
+            .args(args)
+            .output()
+            .unwrap()
+    };
+
+    // Nothing runs until the hooks are allowed
+    let output = exec(&[]);
+    assert!(!output.status.success(), "{output:?}");
+    assert!(output.stdout.is_empty());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("kraven allow project:dev"), "{stderr}");
+    let output = exec(&["--no-hooks"]);
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(output.stdout, b"cmd\n");
+
+    assert!(!allow(&[]).status.success());
+    let output = allow(&["--yes"]);
+    assert!(output.status.success(), "{output:?}");
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .contains("pre-activate: echo pre"));
+    let output = exec(&[]);
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(output.stderr, b"pre\n");
+
+    // A new hook script needs a new approval
+    fs::write(
+        store.join("hooks/dev/pre-activate"),
+        "#!/bin/sh\necho script\n",
+    )
+    .unwrap();
+    let output = exec(&[]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("have not been allowed"));
+}
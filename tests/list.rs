@@ -0,0 +1,46 @@
+//! Lists the profiles found in the profile directory.
+
+mod common;
+
+use std::fs;
+
+use common::ProfileDir;
+
+#[test]
+fn test_list_skips_invalid_files() {
+    let profiles = ProfileDir::new("discover");
+    for name in ["dev~", "dev.bak", "my dev", "scratch/notes"] {
+        let path = profiles.0.join(name);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, "A=1\n").unwrap();
+    }
+    fs::write(profiles.0.join(".kravenignore"), "scratch\n").unwrap();
+
+    let output = profiles.kraven().arg("list").output().unwrap();
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "test\n");
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("my dev: contains invalid character ' '"),
+        "{stderr}"
+    );
+    assert!(
+        !stderr.contains("dev~") && !stderr.contains("scratch"),
+        "{stderr}"
+    );
+
+    // Completion offers the same profiles
+    let output = profiles
+        .kraven()
+        .env("COMPLETE", "fish")
+        .args(["--", "kraven", "show", ""])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let candidates: Vec<&str> = stdout
+        .lines()
+        .filter(|line| !line.starts_with('-'))
+        .collect();
+    assert_eq!(candidates, ["test"]);
+}
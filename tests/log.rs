@@ -0,0 +1,69 @@
+//! Records activations in the audit log and reads them back with `kraven log`.
+
+mod common;
+
+use std::fs;
+
+use common::ProfileDir;
+
+#[test]
+fn test_audit_log_records_exec() {
+    let profiles = ProfileDir::new("audit");
+    let output = profiles
+        .kraven()
+        .args(["exec", "test", "--", "sh", "-c", "exit 5"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(5));
+
+    let log = fs::read_to_string(profiles.0.join("audit.log")).unwrap();
+    assert_eq!(log.lines().count(), 2, "{log}");
+    assert!(log.contains(r#""event":"exec""#));
+    assert!(log.contains(r#""status":5"#));
+    assert!(!log.contains("plain"), "values must never be logged: {log}");
+
+    let output = profiles
+        .kraven()
+        .args(["log", "--profile", "test", "--since", "1h"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(stdout.lines().count(), 1, "{stdout}");
+    assert!(stdout.contains("exec      test"), "{stdout}");
+    assert!(stdout.contains("exit 5 after"), "{stdout}");
+}
+
+#[test]
+fn test_log_tells_stores_apart() {
+    let profiles = ProfileDir::new("audit-stores");
+    let checkout = ProfileDir::new("audit-checkout");
+    let store = checkout.0.join(".kraven/profiles");
+    fs::create_dir_all(&store).unwrap();
+    fs::write(store.join("test"), "A=1\n").unwrap();
+    let run = |args: &[&str]| {
+        profiles
+            .kraven()
+            .current_dir(&checkout.0)
+            .args(args)
+            .output()
+            .unwrap()
+    };
+
+    assert!(run(&["exec", "global:test", "--", "true"]).status.success());
+    assert!(run(&["exec", "project:test", "--", "true"])
+        .status
+        .success());
+    assert!(run(&["mv", "project:test", "moved"]).status.success());
+
+    let log = |profile: &str| {
+        let output = run(&["log", "--profile", profile]);
+        String::from_utf8(output.stdout).unwrap()
+    };
+    let global = log("test");
+    assert_eq!(global.lines().count(), 1, "{global}");
+    assert_eq!(log("global:test"), global);
+    let project = log("project:moved");
+    assert_eq!(project.lines().count(), 2, "{project}");
+    assert!(project.contains("exec      project:test"), "{project}");
+    assert!(project.contains("from project:test"), "{project}");
+}
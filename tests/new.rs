@@ -0,0 +1,35 @@
+//! Creates profiles from templates with `kraven new`.
+
+mod common;
+
+use std::fs;
+
+use common::{run_with_stdin, ProfileDir};
+
+#[test]
+fn test_new_from_template() {
+    let profiles = ProfileDir::new("template");
+    fs::create_dir_all(profiles.0.join("templates")).unwrap();
+    fs::write(
+        profiles.0.join("templates/aws"),
+        "AWS_REGION={{ prompt \"AWS region\" default=\"us-east-1\" }}\n\
+         AWS_SECRET_ACCESS_KEY={{ secret }}\n",
+    )
+    .unwrap();
+
+    let mut cmd = profiles.kraven();
+    cmd.args(["new", "work/aws", "--template", "aws"]);
+    let output = run_with_stdin(cmd, "\nit's secret\n");
+    assert!(output.status.success(), "{output:?}");
+
+    let created = fs::read_to_string(profiles.0.join("work/aws")).unwrap();
+    assert_eq!(
+        created,
+        "AWS_REGION=us-east-1\nAWS_SECRET_ACCESS_KEY=\"it's secret\"\n"
+    );
+
+    // Existing profiles are never overwritten
+    let mut cmd = profiles.kraven();
+    cmd.args(["new", "work/aws", "--template", "aws"]);
+    assert!(!run_with_stdin(cmd, "\nx\n").status.success());
+}
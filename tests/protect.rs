@@ -0,0 +1,37 @@
+//! Asks for confirmation before using protected profiles.
+
+mod common;
+
+use std::fs;
+
+use common::{run_with_stdin, ProfileDir};
+
+#[test]
+fn test_protected_profile_requires_yes_without_terminal() {
+    let profiles = ProfileDir::new("protected");
+    fs::write(
+        profiles.0.join("prod"),
+        "#@protected\nKRAVEN_TEST_PLAIN=prod\n",
+    )
+    .unwrap();
+
+    let mut cmd = profiles.kraven();
+    cmd.args(["exec", "prod", "--", "sh", "-c", "echo $KRAVEN_TEST_PLAIN"]);
+    let output = run_with_stdin(cmd, "prod\n");
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+
+    let mut cmd = profiles.kraven();
+    cmd.args([
+        "exec",
+        "prod",
+        "--yes",
+        "--",
+        "sh",
+        "-c",
+        "echo $KRAVEN_TEST_PLAIN",
+    ]);
+    let output = run_with_stdin(cmd, "");
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(output.stdout, b"prod\n");
+}
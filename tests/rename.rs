@@ -0,0 +1,75 @@
+//! Moves and copies profiles with `kraven mv` and `kraven cp`.
+
+mod common;
+
+use std::fs;
+
+use common::ProfileDir;
+
+#[test]
+fn test_rename_and_copy() {
+    let profiles = ProfileDir::new("rename");
+    let hooks = profiles.0.join("hooks/test");
+    fs::create_dir_all(&hooks).unwrap();
+    fs::write(hooks.join("pre-activate"), "#!/bin/sh\n").unwrap();
+
+    let run = |args: &[&str]| profiles.kraven().args(args).output().unwrap();
+
+    let output = run(&["mv", "test", "work/renamed"]);
+    assert!(output.status.success(), "{output:?}");
+    assert!(!profiles.0.join("test").exists());
+    assert!(profiles.0.join("work/renamed").is_file());
+    assert!(profiles.0.join("hooks/work/renamed/pre-activate").is_file());
+    assert!(!hooks.exists());
+
+    let output = run(&["cp", "work/renamed", "copy"]);
+    assert!(output.status.success(), "{output:?}");
+    assert!(profiles.0.join("work/renamed").is_file());
+    assert!(profiles.0.join("hooks/copy/pre-activate").is_file());
+
+    // Existing profiles are only replaced with --force, and names are validated
+    assert!(!run(&["cp", "work/renamed", "copy"]).status.success());
+    assert!(run(&["cp", "work/renamed", "copy", "--force"])
+        .status
+        .success());
+    assert!(!run(&["mv", "copy", "../escape"]).status.success());
+    assert!(!run(&["mv", "copy", "work"]).status.success());
+
+    let output = run(&["log", "--profile", "work/renamed"]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("from test"), "{stdout}");
+}
+
+#[test]
+fn test_rename_across_stores() {
+    let profiles = ProfileDir::new("rename-global");
+    let checkout = ProfileDir::new("rename-checkout");
+    let store = checkout.0.join(".kraven/profiles");
+    fs::create_dir_all(&store).unwrap();
+    let hooks = profiles.0.join("hooks/test");
+    fs::create_dir_all(&hooks).unwrap();
+    fs::write(hooks.join("pre-activate"), "#!/bin/sh\n").unwrap();
+    let run = |args: &[&str]| {
+        profiles
+            .kraven()
+            .current_dir(&checkout.0)
+            .args(args)
+            .output()
+            .unwrap()
+    };
+
+    let output = run(&["mv", "test", "project:shared"]);
+    assert!(output.status.success(), "{output:?}");
+    assert!(!profiles.0.join("test").exists());
+    assert!(!hooks.exists());
+    assert!(fs::read_to_string(store.join("shared"))
+        .unwrap()
+        .contains("KRAVEN_TEST_PLAIN=plain"));
+    assert!(store.join("hooks/shared/pre-activate").is_file());
+
+    let output = run(&["mv", "project:shared", "global:back"]);
+    assert!(output.status.success(), "{output:?}");
+    assert!(!store.join("shared").exists());
+    assert!(profiles.0.join("back").is_file());
+    assert!(profiles.0.join("hooks/back/pre-activate").is_file());
+}
@@ -0,0 +1,113 @@
+//! Changes a value in many profiles at once with `kraven replace-value`.
+
+mod common;
+
+use std::fs;
+use std::process::Command;
+
+use common::{find_program, run_with_stdin, ProfileDir};
+
+#[test]
+fn test_replace_value() {
+    let profiles = ProfileDir::new("replace");
+    let has_git = find_program("git").is_some();
+    if has_git {
+        let output = profiles
+            .kraven()
+            .args(["store", "init", "--git"])
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{output:?}");
+    }
+    fs::write(profiles.0.join("team-a"), "# CI\nAPI_TOKEN=old\nA=1\n").unwrap();
+    fs::write(profiles.0.join("team-b"), "API_TOKEN=\"old\"\n").unwrap();
+    fs::write(profiles.0.join("team-c"), "OTHER=1\n").unwrap();
+    fs::write(profiles.0.join("solo"), "API_TOKEN=old\n").unwrap();
+    let replace = |args: &[&str], stdin: &str| {
+        let mut cmd = profiles.kraven();
+        cmd.args(["replace-value", "--key", "API_TOKEN", "--from-stdin"])
+            .args(args);
+        run_with_stdin(cmd, stdin)
+    };
+
+    // Nothing changes without a confirmation
+    let output = replace(&["--profiles", "team-*"], "new token\n");
+    assert!(!output.status.success(), "{output:?}");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        stdout.contains("API_TOKEN changes in 2 profile(s):"),
+        "{stdout}"
+    );
+    assert!(stdout.contains("team-a: *** -> *********"), "{stdout}");
+    assert!(stdout.contains("1 without API_TOKEN"), "{stdout}");
+    assert!(String::from_utf8(output.stderr).unwrap().contains("--yes"));
+    assert_eq!(
+        fs::read_to_string(profiles.0.join("team-a")).unwrap(),
+        "# CI\nAPI_TOKEN=old\nA=1\n"
+    );
+
+    let output = replace(&["--profiles", "team-*", "--yes"], "new token\n");
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(
+        fs::read_to_string(profiles.0.join("team-a")).unwrap(),
+        "# CI\nAPI_TOKEN=\"new token\"\nA=1\n"
+    );
+    assert_eq!(
+        fs::read_to_string(profiles.0.join("team-b")).unwrap(),
+        "API_TOKEN=\"new token\"\n"
+    );
+    assert_eq!(
+        fs::read_to_string(profiles.0.join("solo")).unwrap(),
+        "API_TOKEN=old\n"
+    );
+    assert!(!profiles.0.join(".team-a.kraven-replace").exists());
+
+    // Each profile's history records the change
+    let output = profiles
+        .kraven()
+        .args(["log", "--profile", "team-b"])
+        .output()
+        .unwrap();
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .contains("replace-value"));
+    if has_git {
+        let output = Command::new("git")
+            .args(["log", "-1", "--format=%s"])
+            .current_dir(&profiles.0)
+            .output()
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(output.stdout).unwrap(),
+            "Replace API_TOKEN in team-a, team-b\n"
+        );
+    }
+
+    let output = replace(&["--profiles", "team-*", "--yes"], "new token\n");
+    assert!(output.status.success(), "{output:?}");
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .contains("Nothing to change."));
+
+    // A profile linked from elsewhere is changed where it lives
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        let target = profiles.0.join(".linked-target");
+        fs::write(&target, "API_TOKEN=old\n").unwrap();
+        fs::set_permissions(&target, fs::Permissions::from_mode(0o600)).unwrap();
+        std::os::unix::fs::symlink(&target, profiles.0.join("linked")).unwrap();
+
+        let output = replace(&["--profiles", "linked", "--yes"], "new token\n");
+        assert!(output.status.success(), "{output:?}");
+        let link = fs::symlink_metadata(profiles.0.join("linked")).unwrap();
+        assert!(link.file_type().is_symlink());
+        assert_eq!(
+            fs::read_to_string(&target).unwrap(),
+            "API_TOKEN=\"new token\"\n"
+        );
+        let mode = fs::metadata(&target).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+}
@@ -0,0 +1,171 @@
+//! Runs the code kraven generates for each supported shell that is installed.
+//!
+//! Shells that are not on `PATH` are skipped, so these tests pass on machines
+//! with only a subset of them.
+
+mod common;
+
+use std::path::Path;
+use std::process::Command;
+
+use common::{find_program, run_with_stdin, ProfileDir, TRICKY_VALUE};
+
+/// Generates export (or unset) code for `shell` and evaluates it together with `script`.
+fn eval_exports(shell: &str, program: &Path, unset: bool, script: &str) -> String {
+    let profiles = ProfileDir::new(shell);
+    let mut export = profiles.kraven();
+    export.args(["export", "test", "--shell", shell]);
+    if unset {
+        export.arg("--unset");
+    }
+    let output = export.output().unwrap();
+    assert!(output.status.success(), "{output:?}");
+    let exports = String::from_utf8(output.stdout).unwrap();
+
+    let mut cmd = Command::new(program);
+    if shell == "pwsh" {
+        cmd.args(["-NoProfile", "-NonInteractive", "-Command", "-"]);
+    }
+    let output = run_with_stdin(cmd, &format!("{exports}\n{script}\n"));
+    assert!(output.status.success(), "{output:?}");
+    String::from_utf8(output.stdout).unwrap()
+}
+
+fn check_export(shell: &str, program_name: &str, print: &str, unset_check: &str) {
+    let Some(program) = find_program(program_name) else {
+        eprintln!("skipping {shell}: {program_name} not found");
+        return;
+    };
+
+    let stdout = eval_exports(shell, &program, false, print);
+    assert_eq!(stdout.trim_end_matches(['\r', '\n']), TRICKY_VALUE);
+
+    let stdout = eval_exports(shell, &program, true, unset_check);
+    assert_eq!(stdout.trim(), "unset");
+}
+
+#[test]
+fn test_export_sh() {
+    check_export(
+        "sh",
+        "sh",
+        r#"printf '%s\n' "$KRAVEN_TEST_VALUE""#,
+        r#"echo "${KRAVEN_TEST_PLAIN-unset}""#,
+    );
+}
+
+#[test]
+fn test_export_bash() {
+    check_export(
+        "bash",
+        "bash",
+        r#"printf '%s\n' "$KRAVEN_TEST_VALUE""#,
+        r#"echo "${KRAVEN_TEST_PLAIN-unset}""#,
+    );
+}
+
+#[test]
+fn test_export_zsh() {
+    check_export(
+        "zsh",
+        "zsh",
+        r#"printf '%s\n' "$KRAVEN_TEST_VALUE""#,
+        r#"echo "${KRAVEN_TEST_PLAIN-unset}""#,
+    );
+}
+
+#[test]
+fn test_export_fish() {
+    check_export(
+        "fish",
+        "fish",
+        "printf '%s\\n' $KRAVEN_TEST_VALUE",
+        "set -q KRAVEN_TEST_PLAIN; or echo unset",
+    );
+}
+
+#[test]
+fn test_export_nu() {
+    check_export(
+        "nu",
+        "nu",
+        "print $env.KRAVEN_TEST_VALUE",
+        "if 'KRAVEN_TEST_PLAIN' in $env { print set } else { print unset }",
+    );
+}
+
+#[test]
+fn test_export_pwsh() {
+    check_export(
+        "pwsh",
+        "pwsh",
+        "Write-Output $env:KRAVEN_TEST_VALUE",
+        "if ($null -eq $env:KRAVEN_TEST_PLAIN) { 'unset' } else { 'set' }",
+    );
+}
+
+#[test]
+fn test_export_xonsh() {
+    check_export(
+        "xonsh",
+        "xonsh",
+        "print($KRAVEN_TEST_VALUE)",
+        "print('set' if 'KRAVEN_TEST_PLAIN' in ${...} else 'unset')",
+    );
+}
+
+/// Activates the profile in `program`, feeding `script` on stdin instead of a terminal.
+fn check_activate(program_name: &str, script: &str) {
+    let Some(program) = find_program(program_name) else {
+        eprintln!("skipping {program_name}: not found");
+        return;
+    };
+
+    let profiles = ProfileDir::new(&format!("activate-{program_name}"));
+    let mut cmd = profiles.kraven();
+    cmd.args(["activate", "test", "--shell"]).arg(&program);
+    let output = run_with_stdin(cmd, script);
+    assert!(output.status.success(), "{output:?}");
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines, [TRICKY_VALUE, "test"]);
+}
+
+#[test]
+fn test_activate_sh() {
+    check_activate(
+        "sh",
+        r#"printf '%s\n' "$KRAVEN_TEST_VALUE" "$KRAVEN_ACTIVE""#,
+    );
+}
+
+#[test]
+fn test_activate_bash() {
+    check_activate(
+        "bash",
+        r#"printf '%s\n' "$KRAVEN_TEST_VALUE" "$KRAVEN_ACTIVE""#,
+    );
+}
+
+#[test]
+fn test_activate_zsh() {
+    check_activate(
+        "zsh",
+        r#"printf '%s\n' "$KRAVEN_TEST_VALUE" "$KRAVEN_ACTIVE""#,
+    );
+}
+
+#[test]
+fn test_activate_fish() {
+    check_activate("fish", "printf '%s\\n' $KRAVEN_TEST_VALUE $KRAVEN_ACTIVE");
+}
+
+#[test]
+fn test_activate_propagates_exit_code() {
+    let profiles = ProfileDir::new("exit-code");
+    let mut cmd = profiles.kraven();
+    cmd.args(["activate", "test", "--shell", "sh"]);
+    let output = run_with_stdin(cmd, "exit 7\n");
+    assert_eq!(output.status.code(), Some(7));
+}
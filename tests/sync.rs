@@ -0,0 +1,123 @@
+//! Keeps the profile directory in git and syncs it through a remote.
+
+mod common;
+
+use std::fs;
+use std::process::Command;
+
+use common::{find_program, ProfileDir};
+
+#[test]
+fn test_sync_through_remote() {
+    if find_program("git").is_none() {
+        eprintln!("skipping sync: git not found");
+        return;
+    }
+    let remote = ProfileDir::new("sync-remote");
+    let url = remote.0.join("profiles.git");
+    let status = Command::new("git")
+        .args(["init", "--quiet", "--bare"])
+        .arg(&url)
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let run = |profiles: &ProfileDir, args: &[&str]| {
+        let output = profiles.kraven().args(args).output().unwrap();
+        assert!(output.status.success(), "{output:?}");
+        String::from_utf8(output.stdout).unwrap()
+    };
+    let url = url.to_str().unwrap();
+    let laptop = ProfileDir::new("sync-laptop");
+    let desktop = ProfileDir::new("sync-desktop");
+    run(&laptop, &["store", "init", "--git", "--remote", url]);
+    run(&desktop, &["store", "init", "--git", "--remote", url]);
+
+    // Changes made by kraven are committed and pushed
+    run(&laptop, &["cp", "test", "shared"]);
+    run(&laptop, &["sync"]);
+    let stdout = run(&desktop, &["sync"]);
+    assert!(stdout.contains("updated  profile 'shared'"), "{stdout}");
+    assert!(desktop.0.join("shared").is_file());
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = fs::metadata(desktop.0.join("shared"))
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o077, 0);
+    }
+
+    // Both machines change the same profile
+    fs::write(laptop.0.join("shared"), "SHARED=laptop\n").unwrap();
+    run(&laptop, &["sync"]);
+    fs::write(desktop.0.join("shared"), "SHARED=desktop\n").unwrap();
+
+    let output = desktop.kraven().arg("sync").output().unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("profile 'shared'"), "{stderr}");
+    assert!(stderr.contains("kraven sync --abort"), "{stderr}");
+
+    run(&desktop, &["sync", "--abort"]);
+    assert_eq!(
+        fs::read_to_string(desktop.0.join("shared")).unwrap(),
+        "SHARED=desktop\n"
+    );
+
+    // Resolving the conflict by hand lets the sync go on
+    assert!(!desktop
+        .kraven()
+        .arg("sync")
+        .output()
+        .unwrap()
+        .status
+        .success());
+    fs::write(desktop.0.join("shared"), "SHARED=both\n").unwrap();
+    run(&desktop, &["sync"]);
+    run(&laptop, &["sync"]);
+    assert_eq!(
+        fs::read_to_string(laptop.0.join("shared")).unwrap(),
+        "SHARED=both\n"
+    );
+}
+
+#[test]
+fn test_store_leaves_out_backups_and_drafts() {
+    if find_program("git").is_none() {
+        eprintln!("skipping store: git not found");
+        return;
+    }
+    let profiles = ProfileDir::new("store-ignore");
+    for name in [
+        "test.bak",
+        "test~",
+        ".test.kraven-edit",
+        ".test.swp",
+        "scratch/notes",
+    ] {
+        let path = profiles.0.join(name);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, "TOKEN=secret\n").unwrap();
+    }
+    // The audit log of the tests is kept in the profile directory
+    fs::write(profiles.0.join(".kravenignore"), "scratch\naudit.log\n").unwrap();
+    let run = |args: &[&str]| {
+        let output = profiles.kraven().args(args).output().unwrap();
+        assert!(output.status.success(), "{output:?}");
+    };
+    run(&["store", "init", "--git"]);
+    fs::write(profiles.0.join(".other.kraven-replace"), "TOKEN=new\n").unwrap();
+    run(&["cp", "test", "other"]);
+
+    let output = Command::new("git")
+        .args(["ls-files"])
+        .current_dir(&profiles.0)
+        .output()
+        .unwrap();
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        ".gitignore\n.kravenignore\nother\ntest\n"
+    );
+}